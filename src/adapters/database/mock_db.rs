@@ -1,8 +1,18 @@
-use std::sync::{atomic::AtomicI64, Arc, OnceLock};
+use std::{
+    collections::HashMap,
+    sync::{atomic::AtomicI64, Arc, OnceLock},
+};
 
 use tokio::sync::RwLock;
 
-use crate::domain::{device::DeviceAggregate, device_group::DeviceGroupAggregate};
+use crate::domain::{
+    alert::AlertAggregate,
+    device::{codec::PayloadCodec, DeviceAggregate},
+    device_group::DeviceGroupAggregate,
+    incident::IncidentAggregate,
+    notification::{DeadLetter, Delivery, Notification},
+    response::Response,
+};
 
 #[derive(Clone)]
 pub struct MockDb;
@@ -19,5 +29,56 @@ pub fn device_group_table() -> &'static Arc<RwLock<Vec<DeviceGroupAggregate>>> {
     DEVICE_TABLE.get_or_init(|| Arc::new(RwLock::new(vec![])))
 }
 
+//Mock table for payload codecs by device model which `MockDb` will access
+pub fn device_model_codec_table() -> &'static Arc<RwLock<HashMap<String, PayloadCodec>>> {
+    static DEVICE_MODEL_CODEC_TABLE: OnceLock<Arc<RwLock<HashMap<String, PayloadCodec>>>> =
+        OnceLock::new();
+    DEVICE_MODEL_CODEC_TABLE.get_or_init(|| Arc::new(RwLock::new(HashMap::new())))
+}
+
+// (device serial, idempotency key)
+type IdempotencyKey = (String, String);
+
+//Mock table for responses to idempotent requests which `MockDb` will access
+pub fn idempotency_key_table() -> &'static Arc<RwLock<HashMap<IdempotencyKey, Response>>> {
+    static IDEMPOTENCY_KEY_TABLE: OnceLock<Arc<RwLock<HashMap<IdempotencyKey, Response>>>> =
+        OnceLock::new();
+    IDEMPOTENCY_KEY_TABLE.get_or_init(|| Arc::new(RwLock::new(HashMap::new())))
+}
+
+//Mock table for alerts which `MockDb` will access
+pub fn alert_table() -> &'static Arc<RwLock<Vec<AlertAggregate>>> {
+    static ALERT_TABLE: OnceLock<Arc<RwLock<Vec<AlertAggregate>>>> = OnceLock::new();
+    ALERT_TABLE.get_or_init(|| Arc::new(RwLock::new(vec![])))
+}
+
+//Mock table for incidents which `MockDb` will access
+pub fn incident_table() -> &'static Arc<RwLock<Vec<IncidentAggregate>>> {
+    static INCIDENT_TABLE: OnceLock<Arc<RwLock<Vec<IncidentAggregate>>>> = OnceLock::new();
+    INCIDENT_TABLE.get_or_init(|| Arc::new(RwLock::new(vec![])))
+}
+
+//Mock outbox for notifications waiting to be delivered which `MockDb` will access
+pub fn notification_outbox_table() -> &'static Arc<RwLock<Vec<Notification>>> {
+    static NOTIFICATION_OUTBOX_TABLE: OnceLock<Arc<RwLock<Vec<Notification>>>> = OnceLock::new();
+    NOTIFICATION_OUTBOX_TABLE.get_or_init(|| Arc::new(RwLock::new(vec![])))
+}
+
+//Mock table for notifications on their way to webhooks which `MockDb` will access
+pub fn delivery_table() -> &'static Arc<RwLock<Vec<Delivery>>> {
+    static DELIVERY_TABLE: OnceLock<Arc<RwLock<Vec<Delivery>>>> = OnceLock::new();
+    DELIVERY_TABLE.get_or_init(|| Arc::new(RwLock::new(vec![])))
+}
+
+//Mock table for undelivered notifications which `MockDb` will access
+pub fn dead_letter_table() -> &'static Arc<RwLock<Vec<DeadLetter>>> {
+    static DEAD_LETTER_TABLE: OnceLock<Arc<RwLock<Vec<DeadLetter>>>> = OnceLock::new();
+    DEAD_LETTER_TABLE.get_or_init(|| Arc::new(RwLock::new(vec![])))
+}
+
 pub static AUTOINCREMENTED_VALUE_FOR_DEVICE: AtomicI64 = AtomicI64::new(0);
 pub static AUTOINCREMENTED_VALUE_FOR_DEVICE_GROUP: AtomicI64 = AtomicI64::new(0);
+pub static AUTOINCREMENTED_VALUE_FOR_ALERT: AtomicI64 = AtomicI64::new(0);
+pub static AUTOINCREMENTED_VALUE_FOR_INCIDENT: AtomicI64 = AtomicI64::new(0);
+pub static AUTOINCREMENTED_VALUE_FOR_NOTIFICATION: AtomicI64 = AtomicI64::new(0);
+pub static AUTOINCREMENTED_VALUE_FOR_DELIVERY: AtomicI64 = AtomicI64::new(0);
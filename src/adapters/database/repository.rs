@@ -1,17 +1,40 @@
-use super::mock_db::{device_group_table, device_table, MockDb, AUTOINCREMENTED_VALUE_FOR_DEVICE};
+use super::mock_db::{
+    alert_table, dead_letter_table, delivery_table, device_group_table, device_model_codec_table,
+    device_table, idempotency_key_table, incident_table, notification_outbox_table, MockDb,
+    AUTOINCREMENTED_VALUE_FOR_ALERT, AUTOINCREMENTED_VALUE_FOR_DELIVERY,
+    AUTOINCREMENTED_VALUE_FOR_DEVICE, AUTOINCREMENTED_VALUE_FOR_INCIDENT,
+    AUTOINCREMENTED_VALUE_FOR_NOTIFICATION,
+};
 use crate::{
     adapters::database::mock_db::AUTOINCREMENTED_VALUE_FOR_DEVICE_GROUP,
     domain::{
+        alert::{
+            repository::{TAlertPersist, TAlertQuery},
+            AlertAggregate,
+        },
         device::{
-            repository::{TDeviceGroupQuery, TDevicePersist, TDeviceQuery},
+            codec::PayloadCodec,
+            repository::{
+                TDeviceGroupQuery, TDeviceModelCodecStore, TDevicePersist, TDeviceQuery,
+                TIdempotencyKeyStore,
+            },
             DeviceAggregate,
         },
         device_group::{repository::TDeviceGroupPersist, DeviceGroupAggregate},
-        response::Error,
+        incident::{
+            repository::{TIncidentPersist, TIncidentQuery},
+            IncidentAggregate,
+        },
+        notification::{
+            repository::{TDeadLetterStore, TNotificationOutbox},
+            DeadLetter, Delivery, DeviceEvent, Notification,
+        },
+        response::{Error, Response},
     },
 };
 /// Although preferrable to separate Repository per aggregate, I lumped all of them together for
 /// simplicity reason.
+use chrono::{DateTime, Utc};
 use std::sync::atomic::Ordering;
 
 impl TDeviceGroupPersist for MockDb {
@@ -31,6 +54,18 @@ impl TDeviceGroupPersist for MockDb {
         device_group_table().write().await.push(group.clone());
         Ok(())
     }
+
+    async fn update(&self, group: &mut DeviceGroupAggregate) -> Result<(), Error> {
+        let mut guard = device_group_table().write().await;
+
+        let existing = guard
+            .iter_mut()
+            .find(|existing| existing.device_group_id == group.device_group_id)
+            .ok_or(Error::NotFound)?;
+
+        *existing = group.clone();
+        Ok(())
+    }
 }
 
 impl TDeviceGroupQuery for MockDb {
@@ -43,6 +78,10 @@ impl TDeviceGroupQuery for MockDb {
             .ok_or(Error::NotFound)?
             .clone())
     }
+
+    async fn list(&self) -> Result<Vec<DeviceGroupAggregate>, Error> {
+        Ok(device_group_table().read().await.clone())
+    }
 }
 
 impl TDevicePersist for MockDb {
@@ -84,6 +123,10 @@ impl TDeviceQuery for MockDb {
             .clone())
     }
 
+    async fn list(&self) -> Result<Vec<DeviceAggregate>, Error> {
+        Ok(device_table().read().await.clone())
+    }
+
     async fn list_by_group(
         &self,
         device_group_serial_number: &str,
@@ -92,8 +135,199 @@ impl TDeviceQuery for MockDb {
             .read()
             .await
             .iter()
-            .filter(|device| device.device_group_serial_number == device_group_serial_number)
+            .filter(|device| device.has_been_member_of(device_group_serial_number))
             .cloned()
             .collect())
     }
 }
+
+impl TIdempotencyKeyStore for MockDb {
+    async fn get_response(
+        &self,
+        serial_number: &str,
+        idempotency_key: &str,
+    ) -> Result<Option<Response>, Error> {
+        Ok(idempotency_key_table()
+            .read()
+            .await
+            .get(&(serial_number.to_string(), idempotency_key.to_string()))
+            .cloned())
+    }
+
+    async fn save_response(
+        &self,
+        serial_number: &str,
+        idempotency_key: &str,
+        response: &Response,
+    ) -> Result<(), Error> {
+        idempotency_key_table().write().await.insert(
+            (serial_number.to_string(), idempotency_key.to_string()),
+            response.clone(),
+        );
+        Ok(())
+    }
+}
+
+impl TDeviceModelCodecStore for MockDb {
+    async fn get_model_codec(&self, model: &str) -> Result<Option<PayloadCodec>, Error> {
+        Ok(device_model_codec_table().read().await.get(model).copied())
+    }
+
+    async fn set_model_codec(&self, model: &str, codec: PayloadCodec) -> Result<(), Error> {
+        device_model_codec_table()
+            .write()
+            .await
+            .insert(model.to_string(), codec);
+        Ok(())
+    }
+}
+
+impl TAlertPersist for MockDb {
+    async fn add(&self, alert: &mut AlertAggregate) -> Result<(), Error> {
+        alert.alert_id = AUTOINCREMENTED_VALUE_FOR_ALERT.fetch_add(1, Ordering::SeqCst);
+        alert_table().write().await.push(alert.clone());
+        Ok(())
+    }
+
+    async fn update(&self, alert: &mut AlertAggregate) -> Result<(), Error> {
+        let mut guard = alert_table().write().await;
+
+        let existing = guard
+            .iter_mut()
+            .find(|existing| existing.alert_id == alert.alert_id)
+            .ok_or(Error::NotFound)?;
+
+        *existing = alert.clone();
+        Ok(())
+    }
+}
+
+impl TAlertQuery for MockDb {
+    async fn get(&self, alert_id: i64) -> Result<AlertAggregate, Error> {
+        Ok(alert_table()
+            .read()
+            .await
+            .iter()
+            .find(|alert| alert.alert_id == alert_id)
+            .ok_or(Error::NotFound)?
+            .clone())
+    }
+
+    async fn list(&self) -> Result<Vec<AlertAggregate>, Error> {
+        Ok(alert_table().read().await.clone())
+    }
+
+    async fn list_by_device(&self, serial_number: &str) -> Result<Vec<AlertAggregate>, Error> {
+        Ok(alert_table()
+            .read()
+            .await
+            .iter()
+            .filter(|alert| alert.serial_number == serial_number)
+            .cloned()
+            .collect())
+    }
+}
+
+impl TIncidentPersist for MockDb {
+    async fn add(&self, incident: &mut IncidentAggregate) -> Result<(), Error> {
+        incident.incident_id = AUTOINCREMENTED_VALUE_FOR_INCIDENT.fetch_add(1, Ordering::SeqCst);
+        incident_table().write().await.push(incident.clone());
+        Ok(())
+    }
+
+    async fn update(&self, incident: &mut IncidentAggregate) -> Result<(), Error> {
+        let mut guard = incident_table().write().await;
+
+        let existing = guard
+            .iter_mut()
+            .find(|existing| existing.incident_id == incident.incident_id)
+            .ok_or(Error::NotFound)?;
+
+        *existing = incident.clone();
+        Ok(())
+    }
+}
+
+impl TIncidentQuery for MockDb {
+    async fn get(&self, incident_id: i64) -> Result<IncidentAggregate, Error> {
+        Ok(incident_table()
+            .read()
+            .await
+            .iter()
+            .find(|incident| incident.incident_id == incident_id)
+            .ok_or(Error::NotFound)?
+            .clone())
+    }
+
+    async fn list(&self) -> Result<Vec<IncidentAggregate>, Error> {
+        Ok(incident_table().read().await.clone())
+    }
+}
+
+impl TNotificationOutbox for MockDb {
+    async fn publish(&self, event: DeviceEvent) -> Result<(), Error> {
+        let mut notification = Notification::new(event);
+        notification.notification_id =
+            AUTOINCREMENTED_VALUE_FOR_NOTIFICATION.fetch_add(1, Ordering::SeqCst);
+        notification_outbox_table().write().await.push(notification);
+        Ok(())
+    }
+
+    async fn schedule_deliveries(&self, urls: &[String]) -> Result<(), Error> {
+        // Both tables are held so that no notification is ever in neither
+        let mut outbox = notification_outbox_table().write().await;
+        let mut deliveries = delivery_table().write().await;
+        let now = Utc::now();
+        for notification in std::mem::take(&mut *outbox) {
+            for url in urls {
+                deliveries.push(Delivery {
+                    delivery_id: AUTOINCREMENTED_VALUE_FOR_DELIVERY.fetch_add(1, Ordering::SeqCst),
+                    notification: notification.clone(),
+                    url: url.clone(),
+                    attempts: 0,
+                    next_attempt_at: now,
+                });
+            }
+        }
+        Ok(())
+    }
+
+    async fn list_due_deliveries(&self, now: DateTime<Utc>) -> Result<Vec<Delivery>, Error> {
+        Ok(delivery_table()
+            .read()
+            .await
+            .iter()
+            .filter(|delivery| delivery.next_attempt_at <= now)
+            .cloned()
+            .collect())
+    }
+
+    async fn reschedule_delivery(&self, delivery: &Delivery) -> Result<(), Error> {
+        let mut deliveries = delivery_table().write().await;
+        let stored = deliveries
+            .iter_mut()
+            .find(|stored| stored.delivery_id == delivery.delivery_id)
+            .ok_or(Error::NotFound)?;
+        *stored = delivery.clone();
+        Ok(())
+    }
+
+    async fn remove_delivery(&self, delivery_id: i64) -> Result<(), Error> {
+        delivery_table()
+            .write()
+            .await
+            .retain(|delivery| delivery.delivery_id != delivery_id);
+        Ok(())
+    }
+}
+
+impl TDeadLetterStore for MockDb {
+    async fn add(&self, dead_letter: &DeadLetter) -> Result<(), Error> {
+        dead_letter_table().write().await.push(dead_letter.clone());
+        Ok(())
+    }
+
+    async fn list(&self) -> Result<Vec<DeadLetter>, Error> {
+        Ok(dead_letter_table().read().await.clone())
+    }
+}
@@ -1,2 +1,3 @@
 pub mod database;
 pub mod rest_api;
+pub mod webhook;
@@ -27,6 +27,9 @@ impl IntoResponse for Exception<Error> {
             err @ Error::NotFound => (StatusCode::NOT_FOUND, format!("{:?}", err)),
             err @ Error::ConversionFailed => (StatusCode::BAD_REQUEST, format!("{:?}", err)),
             err @ Error::SchemaError => (StatusCode::UNPROCESSABLE_ENTITY, format!("{:?}", err)),
+            err @ (Error::InvalidStatusTransition | Error::DeviceNotActive) => {
+                (StatusCode::CONFLICT, format!("{:?}", err))
+            }
             err @ Error::DuplicateKeyError => {
                 (StatusCode::INTERNAL_SERVER_ERROR, format!("{:?}", err))
             }
@@ -1,6 +1,8 @@
 use axum::{
-    extract::Query,
-    routing::{get, post},
+    body::Bytes,
+    extract::{Path, Query},
+    http::HeaderMap,
+    routing::{get, patch, post, put},
     Json, Router,
 };
 
@@ -10,8 +12,25 @@ use crate::{
         rest_api::response::{Exception, WebResponse},
     },
     domain::{
-        device::commands::RegisterDevice,
-        device_group::{commands::RegisterDeviceGroup, DeviceGroupAggregate},
+        alert::{query::GetAlertQuery, AlertAggregate},
+        device::{
+            commands::{
+                DecommissionDevice, ReactivateDevice, RegisterDevice, SetDeviceCodec,
+                SetDeviceModelCodec, SuspendDevice, TransferDevice, UpdateDeviceMetadata,
+            },
+            DeviceAggregate,
+        },
+        device_group::{
+            commands::{RegisterDeviceGroup, RemoveAlertRule, SetAllowedTemperatureRange},
+            query::ListDeviceGroupsQuery,
+            DeviceGroupAggregate,
+        },
+        incident::{
+            commands::{AcknowledgeIncident, AnnotateIncident, AssignIncident},
+            query::GetIncidentQuery,
+            IncidentAggregate,
+        },
+        notification::{query::ListDeadLettersQuery, DeadLetter},
         response::Error,
         response::Response,
     },
@@ -20,10 +39,24 @@ use crate::{
 
 use super::schemas::{
     in_schema::{
-        GetDeviceAverageTemperatureDuringPeriod, GetDeviceGroupAverageTemperatureDuringPeriod,
-        SaveDeviceTemperatureBody,
+        AddAlertRuleBody, CalibrateDeviceBody, GetDeviceAverageTemperatureDuringPeriod,
+        GetDeviceExcursionsDuringPeriod, GetDeviceFlaggedReadingsDuringPeriod,
+        GetDeviceGapsDuringPeriod, GetDeviceGroupAverageTemperatureDuringPeriod,
+        GetDeviceGroupComplianceReport, GetDeviceGroupDiagnostics,
+        GetDeviceSmoothedTemperaturesDuringPeriod, GetDeviceTemperatureSeriesDuringPeriod,
+        GetDeviceTemperatureStatisticsDuringPeriod, GetDeviceTemperatureTrend,
+        ImportDeviceReadingsCsv, ListAlerts, ListDevices, ListIncidents, SaveDeviceTemperatureBody,
+        SaveDeviceTemperatureFrame, SilenceIncidentsBody,
+    },
+    out_schema::{
+        CommonOutSchema, DeviceDiagnostics, DeviceGroupAverageTemperature,
+        DeviceGroupComplianceReport, DeviceGroupOut, DeviceGroupWithDeviceCounts,
+        DeviceInGroupDuringPeriod, DeviceWithAverageTemperatureDuringPeriod,
+        DeviceWithExcursionsDuringPeriod, DeviceWithFlaggedReadingsDuringPeriod,
+        DeviceWithGapsDuringPeriod, DeviceWithSmoothedTemperaturesDuringPeriod,
+        DeviceWithTemperatureSeriesDuringPeriod, DeviceWithTemperatureStatisticsDuringPeriod,
+        DeviceWithTemperatureTrend,
     },
-    out_schema::{CommonOutSchema, DeviceGroupOut, DeviceWithAverageTemperatureDuringPeriod},
 };
 
 pub async fn register_device(
@@ -42,13 +75,231 @@ pub async fn register_device_group(
     Ok(WebResponse(res.into()))
 }
 
-pub async fn save_device_temperature(
-    Json(cmd): Json<SaveDeviceTemperatureBody>,
-) -> Result<WebResponse<Response>, Exception<Error>> {
+pub async fn list_device_groups(
+) -> Result<WebResponse<CommonOutSchema<Vec<DeviceGroupWithDeviceCounts>>>, Exception<Error>> {
+    let res = QueryHandler::new(ListDeviceGroupsQuery, MockDb)
+        .handle()
+        .await?;
+
+    Ok(WebResponse(res.into()))
+}
+
+pub async fn add_alert_rule(
+    Json(cmd): Json<AddAlertRuleBody>,
+) -> Result<WebResponse<CommonOutSchema<DeviceGroupAggregate>>, Exception<Error>> {
+    let res = CommandHandler::new(cmd.into_command()?, MockDb)
+        .handle()
+        .await?;
+
+    Ok(WebResponse(res.into()))
+}
+
+pub async fn remove_alert_rule(
+    Json(cmd): Json<RemoveAlertRule>,
+) -> Result<WebResponse<CommonOutSchema<DeviceGroupAggregate>>, Exception<Error>> {
+    let res = CommandHandler::new(cmd, MockDb).handle().await?;
+
+    Ok(WebResponse(res.into()))
+}
+
+pub async fn list_alerts(
+    Query(query): Query<ListAlerts>,
+) -> Result<WebResponse<CommonOutSchema<Vec<AlertAggregate>>>, Exception<Error>> {
+    let res = QueryHandler::new(query.into_query(), MockDb)
+        .handle()
+        .await?;
+
+    Ok(WebResponse(res.into()))
+}
+
+pub async fn get_alert(
+    Path(alert_id): Path<i64>,
+) -> Result<WebResponse<CommonOutSchema<AlertAggregate>>, Exception<Error>> {
+    let res = QueryHandler::new(GetAlertQuery { alert_id }, MockDb)
+        .handle()
+        .await?;
+
+    Ok(WebResponse(res.into()))
+}
+
+pub async fn set_allowed_temperature_range(
+    Json(cmd): Json<SetAllowedTemperatureRange>,
+) -> Result<WebResponse<CommonOutSchema<DeviceGroupAggregate>>, Exception<Error>> {
+    let res = CommandHandler::new(cmd, MockDb).handle().await?;
+
+    Ok(WebResponse(res.into()))
+}
+
+pub async fn list_incidents(
+    Query(query): Query<ListIncidents>,
+) -> Result<WebResponse<CommonOutSchema<Vec<IncidentAggregate>>>, Exception<Error>> {
+    let res = QueryHandler::new(query.into_query(), MockDb)
+        .handle()
+        .await?;
+
+    Ok(WebResponse(res.into()))
+}
+
+pub async fn get_incident(
+    Path(incident_id): Path<i64>,
+) -> Result<WebResponse<CommonOutSchema<IncidentAggregate>>, Exception<Error>> {
+    let res = QueryHandler::new(GetIncidentQuery { incident_id }, MockDb)
+        .handle()
+        .await?;
+
+    Ok(WebResponse(res.into()))
+}
+
+pub async fn acknowledge_incident(
+    Json(cmd): Json<AcknowledgeIncident>,
+) -> Result<WebResponse<CommonOutSchema<IncidentAggregate>>, Exception<Error>> {
+    let res = CommandHandler::new(cmd, MockDb).handle().await?;
+
+    Ok(WebResponse(res.into()))
+}
+
+pub async fn assign_incident(
+    Json(cmd): Json<AssignIncident>,
+) -> Result<WebResponse<CommonOutSchema<IncidentAggregate>>, Exception<Error>> {
+    let res = CommandHandler::new(cmd, MockDb).handle().await?;
+
+    Ok(WebResponse(res.into()))
+}
+
+pub async fn annotate_incident(
+    Json(cmd): Json<AnnotateIncident>,
+) -> Result<WebResponse<CommonOutSchema<IncidentAggregate>>, Exception<Error>> {
+    let res = CommandHandler::new(cmd, MockDb).handle().await?;
+
+    Ok(WebResponse(res.into()))
+}
+
+pub async fn silence_incidents(
+    Json(cmd): Json<SilenceIncidentsBody>,
+) -> Result<WebResponse<CommonOutSchema<DeviceGroupAggregate>>, Exception<Error>> {
+    let res = CommandHandler::new(cmd.into_command()?, MockDb)
+        .handle()
+        .await?;
+
+    Ok(WebResponse(res.into()))
+}
+
+pub async fn list_dead_letters(
+) -> Result<WebResponse<CommonOutSchema<Vec<DeadLetter>>>, Exception<Error>> {
+    let res = QueryHandler::new(ListDeadLettersQuery, MockDb)
+        .handle()
+        .await?;
+
+    Ok(WebResponse(res.into()))
+}
+
+pub async fn transfer_device(
+    Json(cmd): Json<TransferDevice>,
+) -> Result<WebResponse<CommonOutSchema<DeviceGroupOut>>, Exception<Error>> {
+    let out = CommandHandler::new(cmd, MockDb).handle().await?.into();
+
+    Ok(WebResponse(out))
+}
+
+pub async fn update_device_metadata(
+    Json(cmd): Json<UpdateDeviceMetadata>,
+) -> Result<WebResponse<CommonOutSchema<DeviceAggregate>>, Exception<Error>> {
+    let res = CommandHandler::new(cmd, MockDb).handle().await?;
+
+    Ok(WebResponse(res.into()))
+}
+
+pub async fn list_devices(
+    Query(query): Query<ListDevices>,
+) -> Result<WebResponse<CommonOutSchema<Vec<DeviceAggregate>>>, Exception<Error>> {
+    let res = QueryHandler::new(query.into_query(), MockDb)
+        .handle()
+        .await?;
+
+    Ok(WebResponse(res.into()))
+}
+
+pub async fn calibrate_device(
+    Json(cmd): Json<CalibrateDeviceBody>,
+) -> Result<WebResponse<CommonOutSchema<DeviceAggregate>>, Exception<Error>> {
     let res = CommandHandler::new(cmd.into_command()?, MockDb)
         .handle()
         .await?;
 
+    Ok(WebResponse(res.into()))
+}
+
+pub async fn set_device_codec(
+    Json(cmd): Json<SetDeviceCodec>,
+) -> Result<WebResponse<CommonOutSchema<DeviceAggregate>>, Exception<Error>> {
+    let res = CommandHandler::new(cmd, MockDb).handle().await?;
+
+    Ok(WebResponse(res.into()))
+}
+
+pub async fn set_device_model_codec(
+    Json(cmd): Json<SetDeviceModelCodec>,
+) -> Result<WebResponse<Response>, Exception<Error>> {
+    let res = CommandHandler::new(cmd, MockDb).handle().await?;
+
+    Ok(WebResponse(res))
+}
+
+pub async fn suspend_device(
+    Json(cmd): Json<SuspendDevice>,
+) -> Result<WebResponse<CommonOutSchema<DeviceAggregate>>, Exception<Error>> {
+    let res = CommandHandler::new(cmd, MockDb).handle().await?;
+
+    Ok(WebResponse(res.into()))
+}
+
+pub async fn reactivate_device(
+    Json(cmd): Json<ReactivateDevice>,
+) -> Result<WebResponse<CommonOutSchema<DeviceAggregate>>, Exception<Error>> {
+    let res = CommandHandler::new(cmd, MockDb).handle().await?;
+
+    Ok(WebResponse(res.into()))
+}
+
+pub async fn decommission_device(
+    Json(cmd): Json<DecommissionDevice>,
+) -> Result<WebResponse<CommonOutSchema<DeviceAggregate>>, Exception<Error>> {
+    let res = CommandHandler::new(cmd, MockDb).handle().await?;
+
+    Ok(WebResponse(res.into()))
+}
+
+// Takes either a JSON body or, with `Content-Type: application/octet-stream`, raw frames
+pub async fn save_device_temperature(
+    headers: HeaderMap,
+    body: Bytes,
+) -> Result<WebResponse<Response>, Exception<Error>> {
+    let idempotency_key = headers
+        .get("Idempotency-Key")
+        .map(|key| key.to_str().map(str::to_string))
+        .transpose()
+        .map_err(|_| Error::SchemaError)?;
+
+    let cmd = match SaveDeviceTemperatureFrame::is_sent_with(&headers) {
+        true => SaveDeviceTemperatureFrame::from_headers(&headers, &body)?
+            .into_command(idempotency_key)?,
+        false => serde_json::from_slice::<SaveDeviceTemperatureBody>(&body)
+            .map_err(|_| Error::SchemaError)?
+            .into_command(idempotency_key)?,
+    };
+    let res = CommandHandler::new(cmd, MockDb).handle().await?;
+
+    Ok(WebResponse(res))
+}
+
+// Takes the CSV either as a `text/csv` body or as the `file` part of a multipart form
+pub async fn import_device_readings(
+    headers: HeaderMap,
+    body: Bytes,
+) -> Result<WebResponse<Response>, Exception<Error>> {
+    let cmd = ImportDeviceReadingsCsv::from_body(&headers, &body)?.into_command()?;
+    let res = CommandHandler::new(cmd, MockDb).handle().await?;
+
     Ok(WebResponse(res))
 }
 
@@ -63,42 +314,213 @@ pub async fn get_device_average_tempature_during_period(
     Ok(WebResponse(res.into()))
 }
 
-pub async fn get_device_group_average_tempature_during_period(
-    Query(query): Query<GetDeviceGroupAverageTemperatureDuringPeriod>,
+pub async fn get_device_tempature_statistics_during_period(
+    Query(query): Query<GetDeviceTemperatureStatisticsDuringPeriod>,
+) -> Result<
+    WebResponse<CommonOutSchema<DeviceWithTemperatureStatisticsDuringPeriod>>,
+    Exception<Error>,
+> {
+    let query = query.into_query()?;
+    let res: DeviceWithTemperatureStatisticsDuringPeriod =
+        QueryHandler::new(query, MockDb).handle().await?.into();
+
+    Ok(WebResponse(res.into()))
+}
+
+pub async fn get_device_tempature_series_during_period(
+    Query(query): Query<GetDeviceTemperatureSeriesDuringPeriod>,
+) -> Result<WebResponse<CommonOutSchema<DeviceWithTemperatureSeriesDuringPeriod>>, Exception<Error>>
+{
+    let query = query.into_query()?;
+    let res: DeviceWithTemperatureSeriesDuringPeriod =
+        QueryHandler::new(query, MockDb).handle().await?.into();
+
+    Ok(WebResponse(res.into()))
+}
+
+pub async fn get_device_smoothed_tempatures_during_period(
+    Query(query): Query<GetDeviceSmoothedTemperaturesDuringPeriod>,
 ) -> Result<
-    WebResponse<CommonOutSchema<Vec<DeviceWithAverageTemperatureDuringPeriod>>>,
+    WebResponse<CommonOutSchema<DeviceWithSmoothedTemperaturesDuringPeriod>>,
     Exception<Error>,
 > {
     let query = query.into_query()?;
-    let res: Vec<DeviceWithAverageTemperatureDuringPeriod> = QueryHandler::new(query, MockDb)
+    let res: DeviceWithSmoothedTemperaturesDuringPeriod =
+        QueryHandler::new(query, MockDb).handle().await?.into();
+
+    Ok(WebResponse(res.into()))
+}
+
+pub async fn get_device_temperature_trend(
+    Query(query): Query<GetDeviceTemperatureTrend>,
+) -> Result<WebResponse<CommonOutSchema<DeviceWithTemperatureTrend>>, Exception<Error>> {
+    let query = query.into_query()?;
+    let res: DeviceWithTemperatureTrend = QueryHandler::new(query, MockDb).handle().await?.into();
+
+    Ok(WebResponse(res.into()))
+}
+
+pub async fn get_device_gaps_during_period(
+    Path(serial_number): Path<String>,
+    Query(query): Query<GetDeviceGapsDuringPeriod>,
+) -> Result<WebResponse<CommonOutSchema<DeviceWithGapsDuringPeriod>>, Exception<Error>> {
+    let query = query.into_query(serial_number)?;
+    let res: DeviceWithGapsDuringPeriod = QueryHandler::new(query, MockDb).handle().await?.into();
+
+    Ok(WebResponse(res.into()))
+}
+
+pub async fn get_device_excursions_during_period(
+    Path(serial_number): Path<String>,
+    Query(query): Query<GetDeviceExcursionsDuringPeriod>,
+) -> Result<WebResponse<CommonOutSchema<DeviceWithExcursionsDuringPeriod>>, Exception<Error>> {
+    let query = query.into_query(serial_number)?;
+    let res: DeviceWithExcursionsDuringPeriod =
+        QueryHandler::new(query, MockDb).handle().await?.into();
+
+    Ok(WebResponse(res.into()))
+}
+
+pub async fn get_device_flagged_readings_during_period(
+    Path(serial_number): Path<String>,
+    Query(query): Query<GetDeviceFlaggedReadingsDuringPeriod>,
+) -> Result<WebResponse<CommonOutSchema<DeviceWithFlaggedReadingsDuringPeriod>>, Exception<Error>> {
+    let query = query.into_query(serial_number)?;
+    let res: DeviceWithFlaggedReadingsDuringPeriod =
+        QueryHandler::new(query, MockDb).handle().await?.into();
+
+    Ok(WebResponse(res.into()))
+}
+
+pub async fn get_device_group_average_tempature_during_period(
+    Query(query): Query<GetDeviceGroupAverageTemperatureDuringPeriod>,
+) -> Result<WebResponse<CommonOutSchema<Vec<DeviceInGroupDuringPeriod>>>, Exception<Error>> {
+    let query = query.into_query()?;
+    let res: Vec<DeviceInGroupDuringPeriod> = QueryHandler::new(query, MockDb)
         .handle()
         .await?
+        .0
         .into_iter()
-        .map(
-            |(device, average)| DeviceWithAverageTemperatureDuringPeriod {
-                id: device.device_id,
-                serial_number: device.serial_number,
-                average_temperature: average,
-            },
-        )
+        .map(DeviceInGroupDuringPeriod::from)
         .collect::<Vec<_>>();
 
     Ok(WebResponse(res.into()))
 }
 
+pub async fn get_device_group_temperature_summary(
+    Query(query): Query<GetDeviceGroupAverageTemperatureDuringPeriod>,
+) -> Result<WebResponse<CommonOutSchema<DeviceGroupAverageTemperature>>, Exception<Error>> {
+    let res = QueryHandler::new(query.into_query()?, MockDb)
+        .handle()
+        .await?;
+
+    Ok(WebResponse(res.into()))
+}
+
+pub async fn get_device_group_compliance_report(
+    Query(query): Query<GetDeviceGroupComplianceReport>,
+) -> Result<WebResponse<CommonOutSchema<DeviceGroupComplianceReport>>, Exception<Error>> {
+    let res = QueryHandler::new(query.into_query()?, MockDb)
+        .handle()
+        .await?;
+
+    Ok(WebResponse(res.into()))
+}
+
+pub async fn get_device_group_diagnostics(
+    Query(query): Query<GetDeviceGroupDiagnostics>,
+) -> Result<WebResponse<CommonOutSchema<Vec<DeviceDiagnostics>>>, Exception<Error>> {
+    let res = QueryHandler::new(query.into_query()?, MockDb)
+        .handle()
+        .await?;
+
+    Ok(WebResponse(res.into()))
+}
+
 pub fn routers() -> Router {
     Router::new()
-        .route("/device_groups", post(register_device_group))
+        .route(
+            "/device_groups",
+            get(list_device_groups).post(register_device_group),
+        )
+        .route(
+            "/device_groups/alert_rules",
+            post(add_alert_rule).delete(remove_alert_rule),
+        )
+        .route(
+            "/device_groups/allowed_range",
+            put(set_allowed_temperature_range),
+        )
+        .route("/alerts", get(list_alerts))
+        .route("/alerts/:alert_id", get(get_alert))
+        .route("/incidents", get(list_incidents))
+        .route("/incidents/:incident_id", get(get_incident))
+        .route("/incidents/acknowledge", post(acknowledge_incident))
+        .route("/incidents/assign", post(assign_incident))
+        .route("/incidents/notes", post(annotate_incident))
+        .route("/incidents/silence", post(silence_incidents))
+        .route("/webhooks/dead_letters", get(list_dead_letters))
         .route(
             "/device_groups/temperature",
             get(get_device_group_average_tempature_during_period),
         )
+        .route(
+            "/device_groups/temperature/summary",
+            get(get_device_group_temperature_summary),
+        )
+        .route(
+            "/device_groups/compliance",
+            get(get_device_group_compliance_report),
+        )
+        .route(
+            "/device_groups/diagnostics",
+            get(get_device_group_diagnostics),
+        )
         .route(
             "/devices",
-            post(register_device).patch(save_device_temperature),
+            get(list_devices)
+                .post(register_device)
+                .patch(save_device_temperature),
+        )
+        .route("/devices/metadata", patch(update_device_metadata))
+        .route("/devices/calibrations", post(calibrate_device))
+        .route("/devices/codec", patch(set_device_codec))
+        .route("/device_models/codec", put(set_device_model_codec))
+        .route("/devices/transfer", post(transfer_device))
+        .route("/devices/suspend", post(suspend_device))
+        .route("/devices/reactivate", post(reactivate_device))
+        .route("/devices/decommission", post(decommission_device))
+        .route(
+            "/devices/:serial_number/gaps",
+            get(get_device_gaps_during_period),
+        )
+        .route(
+            "/devices/:serial_number/excursions",
+            get(get_device_excursions_during_period),
+        )
+        .route(
+            "/devices/:serial_number/anomalies",
+            get(get_device_flagged_readings_during_period),
         )
         .route(
             "/devices/temperature",
             get(get_device_average_tempature_during_period),
         )
+        .route("/devices/temperature/import", post(import_device_readings))
+        .route(
+            "/devices/temperature/stats",
+            get(get_device_tempature_statistics_during_period),
+        )
+        .route(
+            "/devices/temperature/series",
+            get(get_device_tempature_series_during_period),
+        )
+        .route(
+            "/devices/temperature/smoothed",
+            get(get_device_smoothed_tempatures_during_period),
+        )
+        .route(
+            "/devices/temperature/trend",
+            get(get_device_temperature_trend),
+        )
 }
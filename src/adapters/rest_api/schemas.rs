@@ -1,17 +1,44 @@
+use axum::http::{header::CONTENT_TYPE, HeaderMap};
+use serde::de::{value, IntoDeserializer};
 use serde::Deserialize;
 
-use crate::domain::device::commands::SaveDeviceTemperature;
-use crate::domain::response::Error;
+use crate::domain::device::commands::{
+    default_channels, CalibrateDevice, ImportDeviceReadings, ImportedReading,
+    SaveDeviceTemperature, MAX_REPORTING_INTERVAL_SECS,
+};
+use crate::domain::device::statistics::{
+    ReadingWeighting, Smoothing, TemperatureMetric, DEFAULT_ACTIVATION_ENERGY,
+};
+use crate::domain::device::MeasurementChannel;
+use crate::domain::device_group::AllowedTemperatureRange;
+use crate::domain::response::{Error, ImportRowError};
 
 use chrono::DateTime;
+use chrono::Duration;
 use chrono::NaiveDateTime;
 use chrono::TimeZone;
 use chrono::Utc;
 
 pub mod in_schema {
-    use crate::domain::device::query::{
-        GetDeviceAverageTemperatureDuringPeriodQuery,
-        GetDeviceGroupAverageTemperatureDuringPeriodQuery,
+    use crate::domain::alert::{query::ListAlertsQuery, AlertStatus};
+    use crate::domain::device::{
+        compliance::DEFAULT_MIN_COVERAGE,
+        fault::physical_range_of,
+        query::{
+            GetDeviceAverageTemperatureDuringPeriodQuery, GetDeviceExcursionsDuringPeriodQuery,
+            GetDeviceFlaggedReadingsDuringPeriodQuery, GetDeviceGapsDuringPeriodQuery,
+            GetDeviceGroupAverageTemperatureDuringPeriodQuery, GetDeviceGroupComplianceReportQuery,
+            GetDeviceGroupDiagnosticsQuery, GetDeviceSmoothedTemperaturesDuringPeriodQuery,
+            GetDeviceTemperatureSeriesDuringPeriodQuery,
+            GetDeviceTemperatureStatisticsDuringPeriodQuery, GetDeviceTemperatureTrendQuery,
+            ListDevicesQuery,
+        },
+        statistics::DEFAULT_PERCENTILES,
+        trend::{DEFAULT_TREND_READINGS, MAX_TREND_HORIZON_DAYS},
+    };
+    use crate::domain::device_group::{alert_rule::ThresholdCondition, commands::AddAlertRule};
+    use crate::domain::incident::{
+        commands::SilenceIncidents, query::ListIncidentsQuery, IncidentStatus,
     };
 
     use super::*;
@@ -22,15 +49,285 @@ pub mod in_schema {
         pub interval: i64,
         pub temperatures: String,
         pub registered_at: String,
+        #[serde(default = "default_channels")]
+        pub channels: Vec<MeasurementChannel>,
     }
 
     impl SaveDeviceTemperatureBody {
-        pub fn into_command(self) -> Result<SaveDeviceTemperature, Error> {
+        pub fn into_command(
+            self,
+            idempotency_key: Option<String>,
+        ) -> Result<SaveDeviceTemperature, Error> {
             Ok(SaveDeviceTemperature {
                 serial_number: self.serial_number,
-                interval: self.interval,
+                interval: convert_to_reporting_interval(self.interval)?,
                 temperatures: self.temperatures,
                 registered_at: convert_string_to_utc_datetime(&self.registered_at)?,
+                channels: self.channels,
+                idempotency_key,
+            })
+        }
+    }
+
+    // `application/octet-stream` variant of `SaveDeviceTemperatureBody` which spares the hex
+    // encoding. Everything but the readings comes in headers:
+    // - X-Serial-Number
+    // - X-Interval
+    // - X-Registered-At
+    // - X-Channels (optional, comma separated)
+    // and the body carries the raw frames as they are laid out in `temperatures`.
+    pub struct SaveDeviceTemperatureFrame {
+        pub serial_number: String,
+        pub interval: i64,
+        pub registered_at: String,
+        pub channels: Vec<MeasurementChannel>,
+        pub readings: Vec<u8>,
+    }
+
+    impl SaveDeviceTemperatureFrame {
+        // Going by the media type alone, whatever its case and parameters
+        pub fn is_sent_with(headers: &HeaderMap) -> bool {
+            convert_to_media_type(headers).as_deref() == Some("application/octet-stream")
+        }
+
+        pub fn from_headers(headers: &HeaderMap, readings: &[u8]) -> Result<Self, Error> {
+            let header = |name: &str| -> Result<Option<&str>, Error> {
+                headers
+                    .get(name)
+                    .map(|value| value.to_str().map_err(|_| Error::SchemaError))
+                    .transpose()
+            };
+            let required = |name: &str| header(name)?.ok_or(Error::SchemaError);
+
+            Ok(Self {
+                serial_number: required("X-Serial-Number")?.to_string(),
+                interval: required("X-Interval")?
+                    .parse()
+                    .map_err(|_| Error::SchemaError)?,
+                registered_at: required("X-Registered-At")?.to_string(),
+                channels: match header("X-Channels")? {
+                    Some(channels) => convert_string_to_channels(channels)?,
+                    None => default_channels(),
+                },
+                readings: readings.to_vec(),
+            })
+        }
+
+        pub fn into_command(
+            self,
+            idempotency_key: Option<String>,
+        ) -> Result<SaveDeviceTemperature, Error> {
+            SaveDeviceTemperatureBody {
+                serial_number: self.serial_number,
+                interval: self.interval,
+                temperatures: self
+                    .readings
+                    .iter()
+                    .map(|byte| format!("{:02X}", byte))
+                    .collect(),
+                registered_at: self.registered_at,
+                channels: self.channels,
+            }
+            .into_command(idempotency_key)
+        }
+    }
+
+    // CSV export of a logger, sent as the body itself or as the `file` part of a multipart form.
+    // The header row names the `serialNumber`, `checkedAt` and `value` columns plus an optional
+    // `channel` column, in any order. Quoted fields are not supported.
+    pub struct ImportDeviceReadingsCsv {
+        pub content: String,
+    }
+
+    impl ImportDeviceReadingsCsv {
+        pub fn from_body(headers: &HeaderMap, body: &[u8]) -> Result<Self, Error> {
+            let content_type = headers
+                .get(CONTENT_TYPE)
+                .map(|value| value.to_str().map_err(|_| Error::SchemaError))
+                .transpose()?
+                .unwrap_or_default();
+            let content = match convert_to_media_type(headers).as_deref() {
+                Some("multipart/form-data") => {
+                    let (_, params) = content_type.split_once(';').ok_or(Error::SchemaError)?;
+                    let boundary = params
+                        .split(';')
+                        .find_map(|param| param.trim().strip_prefix("boundary="))
+                        .ok_or(Error::SchemaError)?;
+                    extract_multipart_file(body, boundary.trim_matches('"'))?
+                }
+                _ => body,
+            };
+
+            Ok(Self {
+                content: String::from_utf8(content.to_vec()).map_err(|_| Error::SchemaError)?,
+            })
+        }
+
+        // Rows that cannot be parsed are carried on the command so that they get reported along
+        // with the ones rejected by the devices
+        pub fn into_command(self) -> Result<ImportDeviceReadings, Error> {
+            let mut lines = self
+                .content
+                .lines()
+                .enumerate()
+                .map(|(idx, line)| (idx + 1, line.trim()))
+                .filter(|(_, line)| !line.is_empty());
+
+            let (_, header) = lines.next().ok_or(Error::SchemaError)?;
+            let columns = header.split(',').map(str::trim).collect::<Vec<_>>();
+            let column = |name: &str| columns.iter().position(|column| *column == name);
+            let (serial_number, checked_at, value, channel) = (
+                column("serialNumber").ok_or(Error::SchemaError)?,
+                column("checkedAt").ok_or(Error::SchemaError)?,
+                column("value").ok_or(Error::SchemaError)?,
+                column("channel"),
+            );
+
+            let mut cmd = ImportDeviceReadings {
+                readings: vec![],
+                rejected_rows: vec![],
+            };
+            for (line, row) in lines {
+                let fields = row.split(',').map(str::trim).collect::<Vec<_>>();
+                let reading = if fields.len() != columns.len() || fields[serial_number].is_empty() {
+                    Err(Error::SchemaError)
+                } else {
+                    parse_reading(line, &fields, serial_number, checked_at, value, channel)
+                };
+                match reading {
+                    Ok(reading) => cmd.readings.push(reading),
+                    Err(error) => cmd.rejected_rows.push(ImportRowError { line, error }),
+                }
+            }
+            Ok(cmd)
+        }
+    }
+
+    fn parse_reading(
+        line: usize,
+        fields: &[&str],
+        serial_number: usize,
+        checked_at: usize,
+        value: usize,
+        channel: Option<usize>,
+    ) -> Result<ImportedReading, Error> {
+        Ok(ImportedReading {
+            line,
+            serial_number: fields[serial_number].to_string(),
+            channel: match channel.map(|idx| fields[idx]) {
+                Some(channel) if !channel.is_empty() => {
+                    MeasurementChannel::deserialize(channel.into_deserializer())
+                        .map_err(|_: value::Error| Error::SchemaError)?
+                }
+                _ => MeasurementChannel::default(),
+            },
+            checked_at: convert_string_to_utc_datetime(fields[checked_at])?,
+            value: fields[value].parse().map_err(|_| Error::ConversionFailed)?,
+        })
+    }
+
+    #[derive(Deserialize)]
+    pub struct AddAlertRuleBody {
+        #[serde(rename = "deviceGroupSerial")]
+        pub device_group_serial: String,
+        #[serde(default)]
+        pub channel: MeasurementChannel,
+        pub condition: ThresholdCondition,
+        pub threshold: f32,
+        // e.g. "15m". Without it, any single reading breaching the threshold raises an alert
+        pub duration: Option<String>,
+    }
+
+    impl AddAlertRuleBody {
+        pub fn into_command(self) -> Result<AddAlertRule, Error> {
+            Ok(AddAlertRule {
+                device_group_serial: self.device_group_serial,
+                channel: self.channel,
+                condition: self.condition,
+                threshold: self.threshold,
+                duration_in_secs: match self.duration.as_deref() {
+                    Some(duration) => convert_string_to_duration(duration)?.num_seconds(),
+                    None => 0,
+                },
+            })
+        }
+    }
+
+    #[derive(Deserialize)]
+    pub struct ListAlerts {
+        #[serde(rename = "deviceGroupSerial")]
+        pub device_group_serial: Option<String>,
+        #[serde(rename = "serialNumber")]
+        pub serial_number: Option<String>,
+        pub status: Option<AlertStatus>,
+    }
+    impl ListAlerts {
+        pub fn into_query(self) -> ListAlertsQuery {
+            ListAlertsQuery {
+                device_group_serial: self.device_group_serial,
+                serial_number: self.serial_number,
+                status: self.status,
+            }
+        }
+    }
+
+    #[derive(Deserialize)]
+    pub struct SilenceIncidentsBody {
+        #[serde(rename = "deviceGroupSerial")]
+        pub device_group_serial: String,
+        // Without it, the whole group is silenced
+        #[serde(rename = "serialNumber")]
+        pub serial_number: Option<String>,
+        pub until: String,
+        pub actor: String,
+    }
+
+    impl SilenceIncidentsBody {
+        pub fn into_command(self) -> Result<SilenceIncidents, Error> {
+            Ok(SilenceIncidents {
+                device_group_serial: self.device_group_serial,
+                serial_number: self.serial_number,
+                until: convert_string_to_utc_datetime(&self.until)?,
+                actor: self.actor,
+            })
+        }
+    }
+
+    #[derive(Deserialize)]
+    pub struct ListIncidents {
+        #[serde(rename = "deviceGroupSerial")]
+        pub device_group_serial: Option<String>,
+        #[serde(rename = "serialNumber")]
+        pub serial_number: Option<String>,
+        pub status: Option<IncidentStatus>,
+    }
+    impl ListIncidents {
+        pub fn into_query(self) -> ListIncidentsQuery {
+            ListIncidentsQuery {
+                device_group_serial: self.device_group_serial,
+                serial_number: self.serial_number,
+                status: self.status,
+            }
+        }
+    }
+
+    #[derive(Deserialize)]
+    pub struct CalibrateDeviceBody {
+        #[serde(rename = "serialNumber")]
+        pub serial_number: String,
+        pub offset: f32,
+        pub multiplier: f32,
+        #[serde(rename = "validFrom")]
+        pub valid_from: String,
+    }
+
+    impl CalibrateDeviceBody {
+        pub fn into_command(self) -> Result<CalibrateDevice, Error> {
+            Ok(CalibrateDevice {
+                serial_number: self.serial_number,
+                offset: self.offset,
+                multiplier: self.multiplier,
+                valid_from: convert_string_to_utc_datetime(&self.valid_from)?,
             })
         }
     }
@@ -39,10 +336,20 @@ pub mod in_schema {
     pub struct GetDeviceAverageTemperatureDuringPeriod {
         #[serde(rename = "serialNumber")]
         pub serial_number: String,
+        #[serde(default)]
+        pub channel: MeasurementChannel,
         #[serde(rename = "startDate")]
         pub start_date: String,
         #[serde(rename = "endDate")]
         pub end_date: String,
+        // "mean" or "mkt". Defaults to "mean"
+        pub metric: Option<String>,
+        // In kJ/mol, for "mkt" only
+        #[serde(rename = "activationEnergy")]
+        pub activation_energy: Option<f32>,
+        // Leaves out the readings flagged as anomalies
+        #[serde(rename = "excludeFlagged", default)]
+        pub exclude_flagged: bool,
     }
     impl GetDeviceAverageTemperatureDuringPeriod {
         pub fn into_query(self) -> Result<GetDeviceAverageTemperatureDuringPeriodQuery, Error> {
@@ -51,8 +358,151 @@ pub mod in_schema {
 
             Ok(GetDeviceAverageTemperatureDuringPeriodQuery {
                 serial_number: self.serial_number,
+                channel: self.channel,
+                start_date,
+                end_date,
+                metric: convert_to_temperature_metric(
+                    self.metric.as_deref(),
+                    self.activation_energy,
+                )?,
+                exclude_flagged: self.exclude_flagged,
+            })
+        }
+    }
+
+    #[derive(Deserialize)]
+    pub struct GetDeviceTemperatureStatisticsDuringPeriod {
+        #[serde(rename = "serialNumber")]
+        pub serial_number: String,
+        #[serde(default)]
+        pub channel: MeasurementChannel,
+        #[serde(rename = "startDate")]
+        pub start_date: String,
+        #[serde(rename = "endDate")]
+        pub end_date: String,
+        // Comma separated, e.g. "50,90,99"
+        pub percentiles: Option<String>,
+        #[serde(rename = "excludeFlagged", default)]
+        pub exclude_flagged: bool,
+    }
+    impl GetDeviceTemperatureStatisticsDuringPeriod {
+        pub fn into_query(self) -> Result<GetDeviceTemperatureStatisticsDuringPeriodQuery, Error> {
+            let start_date = convert_string_to_utc_datetime(&self.start_date)?;
+            let end_date = convert_string_to_utc_datetime(&self.end_date)?;
+            let percentiles = match self.percentiles {
+                Some(percentiles) => convert_string_to_percentiles(&percentiles)?,
+                None => DEFAULT_PERCENTILES.to_vec(),
+            };
+
+            Ok(GetDeviceTemperatureStatisticsDuringPeriodQuery {
+                serial_number: self.serial_number,
+                channel: self.channel,
+                start_date,
+                end_date,
+                percentiles,
+                exclude_flagged: self.exclude_flagged,
+            })
+        }
+    }
+
+    #[derive(Deserialize)]
+    pub struct GetDeviceTemperatureSeriesDuringPeriod {
+        #[serde(rename = "serialNumber")]
+        pub serial_number: String,
+        #[serde(default)]
+        pub channel: MeasurementChannel,
+        #[serde(rename = "startDate")]
+        pub start_date: String,
+        #[serde(rename = "endDate")]
+        pub end_date: String,
+        // e.g. "30s", "5m", "1h", "1d"
+        pub bucket: String,
+        #[serde(rename = "excludeFlagged", default)]
+        pub exclude_flagged: bool,
+    }
+    impl GetDeviceTemperatureSeriesDuringPeriod {
+        pub fn into_query(self) -> Result<GetDeviceTemperatureSeriesDuringPeriodQuery, Error> {
+            let start_date = convert_string_to_utc_datetime(&self.start_date)?;
+            let end_date = convert_string_to_utc_datetime(&self.end_date)?;
+
+            Ok(GetDeviceTemperatureSeriesDuringPeriodQuery {
+                serial_number: self.serial_number,
+                channel: self.channel,
                 start_date,
                 end_date,
+                bucket_size: convert_string_to_duration(&self.bucket)?,
+                exclude_flagged: self.exclude_flagged,
+            })
+        }
+    }
+
+    #[derive(Deserialize)]
+    pub struct GetDeviceSmoothedTemperaturesDuringPeriod {
+        #[serde(rename = "serialNumber")]
+        pub serial_number: String,
+        #[serde(default)]
+        pub channel: MeasurementChannel,
+        #[serde(rename = "startDate")]
+        pub start_date: String,
+        #[serde(rename = "endDate")]
+        pub end_date: String,
+        // "sma" or "ewma". Defaults to "sma"
+        pub method: Option<String>,
+        // In readings
+        pub window: usize,
+        #[serde(rename = "excludeFlagged", default)]
+        pub exclude_flagged: bool,
+    }
+    impl GetDeviceSmoothedTemperaturesDuringPeriod {
+        pub fn into_query(self) -> Result<GetDeviceSmoothedTemperaturesDuringPeriodQuery, Error> {
+            Ok(GetDeviceSmoothedTemperaturesDuringPeriodQuery {
+                serial_number: self.serial_number,
+                channel: self.channel,
+                start_date: convert_string_to_utc_datetime(&self.start_date)?,
+                end_date: convert_string_to_utc_datetime(&self.end_date)?,
+                smoothing: convert_to_smoothing(self.method.as_deref(), self.window)?,
+                exclude_flagged: self.exclude_flagged,
+            })
+        }
+    }
+
+    #[derive(Deserialize)]
+    pub struct GetDeviceTemperatureTrend {
+        #[serde(rename = "serialNumber")]
+        pub serial_number: String,
+        #[serde(default)]
+        pub channel: MeasurementChannel,
+        // At least 2. Defaults to 12
+        pub readings: Option<usize>,
+        // e.g. "30s", "5m", "1h", "1d". Up to 30 days
+        pub horizon: String,
+        // Within what a sensor of the channel can measure
+        pub threshold: Option<f32>,
+        #[serde(rename = "excludeFlagged", default)]
+        pub exclude_flagged: bool,
+    }
+    impl GetDeviceTemperatureTrend {
+        pub fn into_query(self) -> Result<GetDeviceTemperatureTrendQuery, Error> {
+            let reading_count = self.readings.unwrap_or(DEFAULT_TREND_READINGS);
+            let horizon = convert_string_to_duration(&self.horizon)?;
+            if reading_count < 2
+                || horizon > Duration::days(MAX_TREND_HORIZON_DAYS)
+                || matches!(self.threshold, Some(threshold) if !threshold.is_finite())
+                || matches!(
+                    (self.threshold, physical_range_of(self.channel)),
+                    (Some(threshold), Some(range)) if !range.contains(&threshold)
+                )
+            {
+                return Err(Error::SchemaError);
+            }
+
+            Ok(GetDeviceTemperatureTrendQuery {
+                serial_number: self.serial_number,
+                channel: self.channel,
+                reading_count,
+                horizon,
+                threshold: self.threshold,
+                exclude_flagged: self.exclude_flagged,
             })
         }
     }
@@ -61,10 +511,25 @@ pub mod in_schema {
     pub struct GetDeviceGroupAverageTemperatureDuringPeriod {
         #[serde(rename = "deviceGroupSerial")]
         pub device_group_serial: String,
+        #[serde(default)]
+        pub channel: MeasurementChannel,
         #[serde(rename = "startDate")]
         pub start_date: String,
         #[serde(rename = "endDate")]
         pub end_date: String,
+        #[serde(rename = "includeDecommissioned", default)]
+        pub include_decommissioned: bool,
+        // Comma separated. Only devices carrying every tag are included
+        pub tags: Option<String>,
+        // "mean" or "mkt". Defaults to "mean"
+        pub metric: Option<String>,
+        // In kJ/mol, for "mkt" only
+        #[serde(rename = "activationEnergy")]
+        pub activation_energy: Option<f32>,
+        // "reading", "device" or "time", for the summary only. Defaults to "reading"
+        pub weighting: Option<String>,
+        #[serde(rename = "excludeFlagged", default)]
+        pub exclude_flagged: bool,
     }
     impl GetDeviceGroupAverageTemperatureDuringPeriod {
         pub fn into_query(
@@ -75,17 +540,209 @@ pub mod in_schema {
 
             Ok(GetDeviceGroupAverageTemperatureDuringPeriodQuery {
                 device_group_serial: self.device_group_serial,
+                channel: self.channel,
                 start_date,
                 end_date,
+                include_decommissioned: self.include_decommissioned,
+                tags: convert_string_to_tags(self.tags.as_deref()),
+                metric: convert_to_temperature_metric(
+                    self.metric.as_deref(),
+                    self.activation_energy,
+                )?,
+                weighting: convert_to_reading_weighting(self.weighting.as_deref())?,
+                exclude_flagged: self.exclude_flagged,
+            })
+        }
+    }
+
+    #[derive(Deserialize)]
+    pub struct GetDeviceGroupComplianceReport {
+        #[serde(rename = "deviceGroupSerial")]
+        pub device_group_serial: String,
+        #[serde(default)]
+        pub channel: MeasurementChannel,
+        #[serde(rename = "startDate")]
+        pub start_date: String,
+        #[serde(rename = "endDate")]
+        pub end_date: String,
+        // Both or neither. Without them, the allowed range of the group applies
+        pub min: Option<f32>,
+        pub max: Option<f32>,
+        // From 0 to 1
+        #[serde(rename = "minCoverage")]
+        pub min_coverage: Option<f32>,
+        #[serde(rename = "includeDecommissioned", default)]
+        pub include_decommissioned: bool,
+        // Comma separated. Only devices carrying every tag are included
+        pub tags: Option<String>,
+        #[serde(rename = "excludeFlagged", default)]
+        pub exclude_flagged: bool,
+    }
+    impl GetDeviceGroupComplianceReport {
+        pub fn into_query(self) -> Result<GetDeviceGroupComplianceReportQuery, Error> {
+            let min_coverage = self.min_coverage.unwrap_or(DEFAULT_MIN_COVERAGE);
+            if !(0.0..=1.0).contains(&min_coverage) {
+                return Err(Error::SchemaError);
+            }
+
+            Ok(GetDeviceGroupComplianceReportQuery {
+                device_group_serial: self.device_group_serial,
+                channel: self.channel,
+                start_date: convert_string_to_utc_datetime(&self.start_date)?,
+                end_date: convert_string_to_utc_datetime(&self.end_date)?,
+                allowed_range: convert_to_allowed_range(self.min, self.max)?,
+                min_coverage,
+                include_decommissioned: self.include_decommissioned,
+                tags: convert_string_to_tags(self.tags.as_deref()),
+                exclude_flagged: self.exclude_flagged,
+            })
+        }
+    }
+
+    #[derive(Deserialize)]
+    pub struct GetDeviceGroupDiagnostics {
+        #[serde(rename = "deviceGroupSerial")]
+        pub device_group_serial: String,
+        #[serde(default)]
+        pub channel: MeasurementChannel,
+        #[serde(rename = "startDate")]
+        pub start_date: String,
+        #[serde(rename = "endDate")]
+        pub end_date: String,
+        // From 0 to 1
+        #[serde(rename = "minFaultyRatio", default)]
+        pub min_faulty_ratio: f32,
+    }
+    impl GetDeviceGroupDiagnostics {
+        pub fn into_query(self) -> Result<GetDeviceGroupDiagnosticsQuery, Error> {
+            if !(0.0..=1.0).contains(&self.min_faulty_ratio) {
+                return Err(Error::SchemaError);
+            }
+
+            Ok(GetDeviceGroupDiagnosticsQuery {
+                device_group_serial: self.device_group_serial,
+                channel: self.channel,
+                start_date: convert_string_to_utc_datetime(&self.start_date)?,
+                end_date: convert_string_to_utc_datetime(&self.end_date)?,
+                min_faulty_ratio: self.min_faulty_ratio,
+            })
+        }
+    }
+
+    // The serial number comes in the path
+    #[derive(Deserialize)]
+    pub struct GetDeviceExcursionsDuringPeriod {
+        #[serde(default)]
+        pub channel: MeasurementChannel,
+        #[serde(rename = "startDate")]
+        pub start_date: String,
+        #[serde(rename = "endDate")]
+        pub end_date: String,
+        // Both or neither. Without them, the allowed range of the group applies
+        pub min: Option<f32>,
+        pub max: Option<f32>,
+        #[serde(rename = "excludeFlagged", default)]
+        pub exclude_flagged: bool,
+    }
+    impl GetDeviceExcursionsDuringPeriod {
+        pub fn into_query(
+            self,
+            serial_number: String,
+        ) -> Result<GetDeviceExcursionsDuringPeriodQuery, Error> {
+            Ok(GetDeviceExcursionsDuringPeriodQuery {
+                serial_number,
+                channel: self.channel,
+                start_date: convert_string_to_utc_datetime(&self.start_date)?,
+                end_date: convert_string_to_utc_datetime(&self.end_date)?,
+                allowed_range: convert_to_allowed_range(self.min, self.max)?,
+                exclude_flagged: self.exclude_flagged,
+            })
+        }
+    }
+
+    // The serial number comes in the path
+    #[derive(Deserialize)]
+    pub struct GetDeviceFlaggedReadingsDuringPeriod {
+        #[serde(rename = "startDate")]
+        pub start_date: String,
+        #[serde(rename = "endDate")]
+        pub end_date: String,
+    }
+    impl GetDeviceFlaggedReadingsDuringPeriod {
+        pub fn into_query(
+            self,
+            serial_number: String,
+        ) -> Result<GetDeviceFlaggedReadingsDuringPeriodQuery, Error> {
+            Ok(GetDeviceFlaggedReadingsDuringPeriodQuery {
+                serial_number,
+                start_date: convert_string_to_utc_datetime(&self.start_date)?,
+                end_date: convert_string_to_utc_datetime(&self.end_date)?,
+            })
+        }
+    }
+
+    // The serial number comes in the path
+    #[derive(Deserialize)]
+    pub struct GetDeviceGapsDuringPeriod {
+        #[serde(default)]
+        pub channel: MeasurementChannel,
+        #[serde(rename = "startDate")]
+        pub start_date: String,
+        #[serde(rename = "endDate")]
+        pub end_date: String,
+    }
+    impl GetDeviceGapsDuringPeriod {
+        pub fn into_query(
+            self,
+            serial_number: String,
+        ) -> Result<GetDeviceGapsDuringPeriodQuery, Error> {
+            Ok(GetDeviceGapsDuringPeriodQuery {
+                serial_number,
+                channel: self.channel,
+                start_date: convert_string_to_utc_datetime(&self.start_date)?,
+                end_date: convert_string_to_utc_datetime(&self.end_date)?,
             })
         }
     }
+
+    #[derive(Deserialize)]
+    pub struct ListDevices {
+        #[serde(rename = "deviceGroupSerial")]
+        pub device_group_serial: String,
+        // Comma separated. Only devices carrying every tag are included
+        pub tags: Option<String>,
+    }
+    impl ListDevices {
+        pub fn into_query(self) -> ListDevicesQuery {
+            ListDevicesQuery {
+                device_group_serial: self.device_group_serial,
+                tags: convert_string_to_tags(self.tags.as_deref()),
+            }
+        }
+    }
 }
 
 pub mod out_schema {
     use serde::Serialize;
 
-    use crate::domain::{device::DeviceAggregate, device_group::DeviceGroupAggregate};
+    use crate::domain::{
+        alert::AlertAggregate,
+        device::{
+            anomaly::FlaggedReading,
+            compliance::ComplianceReport,
+            excursion::Excursion,
+            fault::SensorDiagnostics,
+            statistics::{
+                GroupTemperature, ReadingGap, SmoothedReading, TemperatureBucket,
+                TemperatureStatistics,
+            },
+            trend::TemperatureTrend,
+            DeviceAggregate, DeviceCounts,
+        },
+        device_group::{AllowedTemperatureRange, DeviceGroupAggregate},
+        incident::IncidentAggregate,
+        notification::DeadLetter,
+    };
 
     #[derive(Serialize)]
     pub struct CommonOutSchema<T: Serialize> {
@@ -120,8 +777,8 @@ pub mod out_schema {
         }
     }
 
-    impl From<DeviceGroupAggregate> for CommonOutSchema<DeviceGroupAggregate> {
-        fn from(value: DeviceGroupAggregate) -> Self {
+    impl From<DeviceAggregate> for CommonOutSchema<DeviceAggregate> {
+        fn from(value: DeviceAggregate) -> Self {
             Self {
                 msg: "success".to_string(),
                 data: value,
@@ -129,28 +786,17 @@ pub mod out_schema {
         }
     }
 
-    #[derive(Serialize)]
-    pub struct DeviceWithAverageTemperatureDuringPeriod {
-        pub id: i64,
-        #[serde(rename = "serialNumber")]
-        pub serial_number: String,
-
-        #[serde(rename = "averageTemperature")]
-        pub average_temperature: f32,
-    }
-    impl From<(DeviceAggregate, f32)> for DeviceWithAverageTemperatureDuringPeriod {
-        fn from(value: (DeviceAggregate, f32)) -> Self {
+    impl From<Vec<DeviceAggregate>> for CommonOutSchema<Vec<DeviceAggregate>> {
+        fn from(value: Vec<DeviceAggregate>) -> Self {
             Self {
-                id: value.0.device_id,
-                serial_number: value.0.serial_number,
-                average_temperature: value.1,
+                msg: "success".to_string(),
+                data: value,
             }
         }
     }
-    impl From<DeviceWithAverageTemperatureDuringPeriod>
-        for CommonOutSchema<DeviceWithAverageTemperatureDuringPeriod>
-    {
-        fn from(value: DeviceWithAverageTemperatureDuringPeriod) -> Self {
+
+    impl From<DeviceGroupAggregate> for CommonOutSchema<DeviceGroupAggregate> {
+        fn from(value: DeviceGroupAggregate) -> Self {
             Self {
                 msg: "success".to_string(),
                 data: value,
@@ -158,22 +804,602 @@ pub mod out_schema {
         }
     }
 
-    impl From<Vec<DeviceWithAverageTemperatureDuringPeriod>>
-        for CommonOutSchema<Vec<DeviceWithAverageTemperatureDuringPeriod>>
-    {
-        fn from(value: Vec<DeviceWithAverageTemperatureDuringPeriod>) -> Self {
+    impl From<AlertAggregate> for CommonOutSchema<AlertAggregate> {
+        fn from(value: AlertAggregate) -> Self {
             Self {
                 msg: "success".to_string(),
                 data: value,
             }
         }
     }
-}
 
-fn convert_string_to_utc_datetime(given: &str) -> Result<DateTime<Utc>, Error> {
-    let naive_time = NaiveDateTime::parse_from_str(given, "%Y-%m-%d %H:%M:%S")
-        .map_err(|_| Error::SchemaError)?;
-    Ok(Utc.from_local_datetime(&naive_time).unwrap())
+    impl From<IncidentAggregate> for CommonOutSchema<IncidentAggregate> {
+        fn from(value: IncidentAggregate) -> Self {
+            Self {
+                msg: "success".to_string(),
+                data: value,
+            }
+        }
+    }
+
+    impl From<Vec<IncidentAggregate>> for CommonOutSchema<Vec<IncidentAggregate>> {
+        fn from(value: Vec<IncidentAggregate>) -> Self {
+            Self {
+                msg: "success".to_string(),
+                data: value,
+            }
+        }
+    }
+
+    impl From<Vec<AlertAggregate>> for CommonOutSchema<Vec<AlertAggregate>> {
+        fn from(value: Vec<AlertAggregate>) -> Self {
+            Self {
+                msg: "success".to_string(),
+                data: value,
+            }
+        }
+    }
+
+    impl From<Vec<DeadLetter>> for CommonOutSchema<Vec<DeadLetter>> {
+        fn from(value: Vec<DeadLetter>) -> Self {
+            Self {
+                msg: "success".to_string(),
+                data: value,
+            }
+        }
+    }
+
+    #[derive(Serialize)]
+    pub struct DeviceGroupWithDeviceCounts {
+        #[serde(flatten)]
+        pub device_group: DeviceGroupAggregate,
+        #[serde(flatten)]
+        pub device_counts: DeviceCounts,
+    }
+
+    impl From<Vec<(DeviceGroupAggregate, DeviceCounts)>>
+        for CommonOutSchema<Vec<DeviceGroupWithDeviceCounts>>
+    {
+        fn from(value: Vec<(DeviceGroupAggregate, DeviceCounts)>) -> Self {
+            Self {
+                msg: "success".to_string(),
+                data: value
+                    .into_iter()
+                    .map(
+                        |(device_group, device_counts)| DeviceGroupWithDeviceCounts {
+                            device_group,
+                            device_counts,
+                        },
+                    )
+                    .collect(),
+            }
+        }
+    }
+
+    #[derive(Serialize)]
+    pub struct DeviceWithAverageTemperatureDuringPeriod {
+        pub id: i64,
+        #[serde(rename = "serialNumber")]
+        pub serial_number: String,
+
+        #[serde(rename = "averageTemperature")]
+        pub average_temperature: f32,
+    }
+    impl From<(DeviceAggregate, f32)> for DeviceWithAverageTemperatureDuringPeriod {
+        fn from(value: (DeviceAggregate, f32)) -> Self {
+            Self {
+                id: value.0.device_id,
+                serial_number: value.0.serial_number,
+                average_temperature: value.1,
+            }
+        }
+    }
+    impl From<DeviceWithAverageTemperatureDuringPeriod>
+        for CommonOutSchema<DeviceWithAverageTemperatureDuringPeriod>
+    {
+        fn from(value: DeviceWithAverageTemperatureDuringPeriod) -> Self {
+            Self {
+                msg: "success".to_string(),
+                data: value,
+            }
+        }
+    }
+
+    #[derive(Serialize)]
+    pub struct DeviceWithTemperatureStatisticsDuringPeriod {
+        pub id: i64,
+        #[serde(rename = "serialNumber")]
+        pub serial_number: String,
+
+        #[serde(flatten)]
+        pub statistics: TemperatureStatistics,
+    }
+    impl From<(DeviceAggregate, TemperatureStatistics)>
+        for DeviceWithTemperatureStatisticsDuringPeriod
+    {
+        fn from(value: (DeviceAggregate, TemperatureStatistics)) -> Self {
+            Self {
+                id: value.0.device_id,
+                serial_number: value.0.serial_number,
+                statistics: value.1,
+            }
+        }
+    }
+    impl From<DeviceWithTemperatureStatisticsDuringPeriod>
+        for CommonOutSchema<DeviceWithTemperatureStatisticsDuringPeriod>
+    {
+        fn from(value: DeviceWithTemperatureStatisticsDuringPeriod) -> Self {
+            Self {
+                msg: "success".to_string(),
+                data: value,
+            }
+        }
+    }
+
+    #[derive(Serialize)]
+    pub struct DeviceWithTemperatureSeriesDuringPeriod {
+        pub id: i64,
+        #[serde(rename = "serialNumber")]
+        pub serial_number: String,
+
+        pub buckets: Vec<TemperatureBucket>,
+    }
+    impl From<(DeviceAggregate, Vec<TemperatureBucket>)> for DeviceWithTemperatureSeriesDuringPeriod {
+        fn from(value: (DeviceAggregate, Vec<TemperatureBucket>)) -> Self {
+            Self {
+                id: value.0.device_id,
+                serial_number: value.0.serial_number,
+                buckets: value.1,
+            }
+        }
+    }
+    impl From<DeviceWithTemperatureSeriesDuringPeriod>
+        for CommonOutSchema<DeviceWithTemperatureSeriesDuringPeriod>
+    {
+        fn from(value: DeviceWithTemperatureSeriesDuringPeriod) -> Self {
+            Self {
+                msg: "success".to_string(),
+                data: value,
+            }
+        }
+    }
+
+    #[derive(Serialize)]
+    pub struct DeviceWithSmoothedTemperaturesDuringPeriod {
+        pub id: i64,
+        #[serde(rename = "serialNumber")]
+        pub serial_number: String,
+
+        pub readings: Vec<SmoothedReading>,
+    }
+    impl From<(DeviceAggregate, Vec<SmoothedReading>)> for DeviceWithSmoothedTemperaturesDuringPeriod {
+        fn from(value: (DeviceAggregate, Vec<SmoothedReading>)) -> Self {
+            Self {
+                id: value.0.device_id,
+                serial_number: value.0.serial_number,
+                readings: value.1,
+            }
+        }
+    }
+    impl From<DeviceWithSmoothedTemperaturesDuringPeriod>
+        for CommonOutSchema<DeviceWithSmoothedTemperaturesDuringPeriod>
+    {
+        fn from(value: DeviceWithSmoothedTemperaturesDuringPeriod) -> Self {
+            Self {
+                msg: "success".to_string(),
+                data: value,
+            }
+        }
+    }
+
+    #[derive(Serialize)]
+    pub struct DeviceWithTemperatureTrend {
+        pub id: i64,
+        #[serde(rename = "serialNumber")]
+        pub serial_number: String,
+
+        // `None` with fewer than two readings
+        pub trend: Option<TemperatureTrend>,
+    }
+    impl From<(DeviceAggregate, Option<TemperatureTrend>)> for DeviceWithTemperatureTrend {
+        fn from(value: (DeviceAggregate, Option<TemperatureTrend>)) -> Self {
+            Self {
+                id: value.0.device_id,
+                serial_number: value.0.serial_number,
+                trend: value.1,
+            }
+        }
+    }
+    impl From<DeviceWithTemperatureTrend> for CommonOutSchema<DeviceWithTemperatureTrend> {
+        fn from(value: DeviceWithTemperatureTrend) -> Self {
+            Self {
+                msg: "success".to_string(),
+                data: value,
+            }
+        }
+    }
+
+    #[derive(Serialize)]
+    pub struct DeviceWithFlaggedReadingsDuringPeriod {
+        pub id: i64,
+        #[serde(rename = "serialNumber")]
+        pub serial_number: String,
+
+        pub readings: Vec<FlaggedReading>,
+    }
+    impl From<(DeviceAggregate, Vec<FlaggedReading>)> for DeviceWithFlaggedReadingsDuringPeriod {
+        fn from(value: (DeviceAggregate, Vec<FlaggedReading>)) -> Self {
+            Self {
+                id: value.0.device_id,
+                serial_number: value.0.serial_number,
+                readings: value.1,
+            }
+        }
+    }
+    impl From<DeviceWithFlaggedReadingsDuringPeriod>
+        for CommonOutSchema<DeviceWithFlaggedReadingsDuringPeriod>
+    {
+        fn from(value: DeviceWithFlaggedReadingsDuringPeriod) -> Self {
+            Self {
+                msg: "success".to_string(),
+                data: value,
+            }
+        }
+    }
+
+    #[derive(Serialize)]
+    pub struct DeviceWithGapsDuringPeriod {
+        pub id: i64,
+        #[serde(rename = "serialNumber")]
+        pub serial_number: String,
+        #[serde(rename = "reportingInterval")]
+        pub reporting_interval: Option<i64>,
+
+        pub gaps: Vec<ReadingGap>,
+    }
+    impl From<(DeviceAggregate, Vec<ReadingGap>)> for DeviceWithGapsDuringPeriod {
+        fn from(value: (DeviceAggregate, Vec<ReadingGap>)) -> Self {
+            Self {
+                id: value.0.device_id,
+                serial_number: value.0.serial_number,
+                reporting_interval: value.0.reporting_interval,
+                gaps: value.1,
+            }
+        }
+    }
+    impl From<DeviceWithGapsDuringPeriod> for CommonOutSchema<DeviceWithGapsDuringPeriod> {
+        fn from(value: DeviceWithGapsDuringPeriod) -> Self {
+            Self {
+                msg: "success".to_string(),
+                data: value,
+            }
+        }
+    }
+
+    #[derive(Serialize)]
+    pub struct DeviceWithExcursionsDuringPeriod {
+        pub id: i64,
+        #[serde(rename = "serialNumber")]
+        pub serial_number: String,
+        #[serde(rename = "allowedRange")]
+        pub allowed_range: AllowedTemperatureRange,
+
+        pub excursions: Vec<Excursion>,
+    }
+    impl From<(DeviceAggregate, AllowedTemperatureRange, Vec<Excursion>)>
+        for DeviceWithExcursionsDuringPeriod
+    {
+        fn from(value: (DeviceAggregate, AllowedTemperatureRange, Vec<Excursion>)) -> Self {
+            Self {
+                id: value.0.device_id,
+                serial_number: value.0.serial_number,
+                allowed_range: value.1,
+                excursions: value.2,
+            }
+        }
+    }
+    impl From<DeviceWithExcursionsDuringPeriod> for CommonOutSchema<DeviceWithExcursionsDuringPeriod> {
+        fn from(value: DeviceWithExcursionsDuringPeriod) -> Self {
+            Self {
+                msg: "success".to_string(),
+                data: value,
+            }
+        }
+    }
+
+    #[derive(Serialize)]
+    pub struct DeviceInGroupDuringPeriod {
+        pub id: i64,
+        #[serde(rename = "serialNumber")]
+        pub serial_number: String,
+
+        #[serde(rename = "averageTemperature")]
+        pub average_temperature: f32,
+        #[serde(rename = "gapCount")]
+        pub gap_count: usize,
+    }
+    impl From<(DeviceAggregate, f32, usize)> for DeviceInGroupDuringPeriod {
+        fn from(value: (DeviceAggregate, f32, usize)) -> Self {
+            Self {
+                id: value.0.device_id,
+                serial_number: value.0.serial_number,
+                average_temperature: value.1,
+                gap_count: value.2,
+            }
+        }
+    }
+
+    impl From<Vec<DeviceInGroupDuringPeriod>> for CommonOutSchema<Vec<DeviceInGroupDuringPeriod>> {
+        fn from(value: Vec<DeviceInGroupDuringPeriod>) -> Self {
+            Self {
+                msg: "success".to_string(),
+                data: value,
+            }
+        }
+    }
+
+    #[derive(Serialize)]
+    pub struct DeviceGroupAverageTemperature {
+        #[serde(flatten)]
+        pub group: GroupTemperature,
+        pub devices: Vec<DeviceInGroupDuringPeriod>,
+    }
+
+    impl From<(Vec<(DeviceAggregate, f32, usize)>, GroupTemperature)>
+        for CommonOutSchema<DeviceGroupAverageTemperature>
+    {
+        fn from(value: (Vec<(DeviceAggregate, f32, usize)>, GroupTemperature)) -> Self {
+            let (devices, group) = value;
+            Self {
+                msg: "success".to_string(),
+                data: DeviceGroupAverageTemperature {
+                    group,
+                    devices: devices
+                        .into_iter()
+                        .map(DeviceInGroupDuringPeriod::from)
+                        .collect(),
+                },
+            }
+        }
+    }
+
+    #[derive(Serialize)]
+    pub struct DeviceGroupComplianceReport {
+        #[serde(rename = "deviceGroupSerial")]
+        pub device_group_serial: String,
+        #[serde(rename = "allowedRange")]
+        pub allowed_range: AllowedTemperatureRange,
+        // When every device passed
+        pub passed: bool,
+        pub devices: Vec<DeviceComplianceReport>,
+    }
+
+    #[derive(Serialize)]
+    pub struct DeviceComplianceReport {
+        pub id: i64,
+        #[serde(rename = "serialNumber")]
+        pub serial_number: String,
+        #[serde(flatten)]
+        pub report: ComplianceReport,
+    }
+
+    impl
+        From<(
+            DeviceGroupAggregate,
+            AllowedTemperatureRange,
+            Vec<(DeviceAggregate, ComplianceReport)>,
+        )> for CommonOutSchema<DeviceGroupComplianceReport>
+    {
+        fn from(
+            value: (
+                DeviceGroupAggregate,
+                AllowedTemperatureRange,
+                Vec<(DeviceAggregate, ComplianceReport)>,
+            ),
+        ) -> Self {
+            let (group, allowed_range, reports) = value;
+            Self {
+                msg: "success".to_string(),
+                data: DeviceGroupComplianceReport {
+                    device_group_serial: group.serial_number,
+                    allowed_range,
+                    passed: reports.iter().all(|(_, report)| report.passed),
+                    devices: reports
+                        .into_iter()
+                        .map(|(device, report)| DeviceComplianceReport {
+                            id: device.device_id,
+                            serial_number: device.serial_number,
+                            report,
+                        })
+                        .collect(),
+                },
+            }
+        }
+    }
+
+    #[derive(Serialize)]
+    pub struct DeviceDiagnostics {
+        pub id: i64,
+        #[serde(rename = "serialNumber")]
+        pub serial_number: String,
+        #[serde(flatten)]
+        pub diagnostics: SensorDiagnostics,
+    }
+
+    impl From<Vec<(DeviceAggregate, SensorDiagnostics)>> for CommonOutSchema<Vec<DeviceDiagnostics>> {
+        fn from(value: Vec<(DeviceAggregate, SensorDiagnostics)>) -> Self {
+            Self {
+                msg: "success".to_string(),
+                data: value
+                    .into_iter()
+                    .map(|(device, diagnostics)| DeviceDiagnostics {
+                        id: device.device_id,
+                        serial_number: device.serial_number,
+                        diagnostics,
+                    })
+                    .collect(),
+            }
+        }
+    }
+}
+
+fn convert_string_to_utc_datetime(given: &str) -> Result<DateTime<Utc>, Error> {
+    let naive_time = NaiveDateTime::parse_from_str(given, "%Y-%m-%d %H:%M:%S")
+        .map_err(|_| Error::SchemaError)?;
+    Ok(Utc.from_local_datetime(&naive_time).unwrap())
+}
+
+fn convert_string_to_percentiles(given: &str) -> Result<Vec<f32>, Error> {
+    given
+        .split(',')
+        .map(|percentile| {
+            let percentile: f32 = percentile.trim().parse().map_err(|_| Error::SchemaError)?;
+            if !(0.0..=100.0).contains(&percentile) {
+                return Err(Error::SchemaError);
+            }
+            Ok(percentile)
+        })
+        .collect()
+}
+
+fn convert_string_to_channels(given: &str) -> Result<Vec<MeasurementChannel>, Error> {
+    given
+        .split(',')
+        .map(|channel| {
+            MeasurementChannel::deserialize(channel.trim().into_deserializer())
+                .map_err(|_: value::Error| Error::SchemaError)
+        })
+        .collect()
+}
+
+// Returns the content of the `file` part of a multipart/form-data body
+fn extract_multipart_file<'a>(body: &'a [u8], boundary: &str) -> Result<&'a [u8], Error> {
+    let delimiter = format!("--{}", boundary);
+    let find = |haystack: &[u8], needle: &[u8]| {
+        haystack
+            .windows(needle.len())
+            .position(|window| window == needle)
+    };
+
+    let mut rest = body;
+    while let Some(start) = find(rest, delimiter.as_bytes()) {
+        rest = &rest[start + delimiter.len()..];
+        let Some(end) = find(rest, delimiter.as_bytes()) else {
+            break;
+        };
+        let part = &rest[..end];
+        let Some(header_end) = find(part, b"\r\n\r\n") else {
+            continue;
+        };
+        let part_headers = String::from_utf8_lossy(&part[..header_end]);
+        if part_headers.contains("name=\"file\"") {
+            let content = &part[header_end + 4..];
+            return Ok(content.strip_suffix(b"\r\n").unwrap_or(content));
+        }
+    }
+    eprintln!("[ERROR] No file part given");
+    Err(Error::SchemaError)
+}
+
+fn convert_string_to_tags(given: Option<&str>) -> Vec<String> {
+    given
+        .unwrap_or_default()
+        .split(',')
+        .map(str::trim)
+        .filter(|tag| !tag.is_empty())
+        .map(str::to_string)
+        .collect()
+}
+
+// Accepts a positive amount followed by a unit among s(econds), m(inutes), h(ours) and d(ays)
+// Media type of the `Content-Type` header, lowercased and without its parameters
+fn convert_to_media_type(headers: &HeaderMap) -> Option<String> {
+    let content_type = headers.get(CONTENT_TYPE)?.to_str().ok()?;
+    let (media_type, _) = content_type.split_once(';').unwrap_or((content_type, ""));
+    Some(media_type.trim().to_ascii_lowercase())
+}
+
+// Positive, and up to `MAX_REPORTING_INTERVAL_SECS`
+fn convert_to_reporting_interval(interval: i64) -> Result<i64, Error> {
+    match Duration::try_seconds(interval) {
+        Some(duration)
+            if duration > Duration::zero()
+                && duration <= Duration::seconds(MAX_REPORTING_INTERVAL_SECS) =>
+        {
+            Ok(interval)
+        }
+        _ => Err(Error::SchemaError),
+    }
+}
+
+fn convert_string_to_duration(given: &str) -> Result<Duration, Error> {
+    let unit_at = given.char_indices().last().map_or(0, |(at, _)| at);
+    let (amount, unit) = given.split_at(unit_at);
+    let amount: i64 = amount.parse().map_err(|_| Error::SchemaError)?;
+    if amount <= 0 {
+        return Err(Error::SchemaError);
+    }
+    match unit {
+        "s" => Duration::try_seconds(amount),
+        "m" => Duration::try_minutes(amount),
+        "h" => Duration::try_hours(amount),
+        "d" => Duration::try_days(amount),
+        _ => None,
+    }
+    .ok_or(Error::SchemaError)
+}
+
+// Both bounds or neither
+fn convert_to_allowed_range(
+    min: Option<f32>,
+    max: Option<f32>,
+) -> Result<Option<AllowedTemperatureRange>, Error> {
+    match (min, max) {
+        (None, None) => Ok(None),
+        (Some(min), Some(max)) if min.is_finite() && max.is_finite() && min <= max => {
+            Ok(Some(AllowedTemperatureRange { min, max }))
+        }
+        _ => Err(Error::SchemaError),
+    }
+}
+
+// The activation energy defaults to the one customary for pharmaceuticals and goes with "mkt" only
+fn convert_to_temperature_metric(
+    metric: Option<&str>,
+    activation_energy: Option<f32>,
+) -> Result<TemperatureMetric, Error> {
+    match (metric.unwrap_or("mean"), activation_energy) {
+        ("mean", None) => Ok(TemperatureMetric::Mean),
+        ("mkt", None) => Ok(TemperatureMetric::MeanKinetic {
+            activation_energy: DEFAULT_ACTIVATION_ENERGY,
+        }),
+        ("mkt", Some(activation_energy))
+            if activation_energy.is_finite() && activation_energy > 0.0 =>
+        {
+            Ok(TemperatureMetric::MeanKinetic { activation_energy })
+        }
+        _ => Err(Error::SchemaError),
+    }
+}
+
+fn convert_to_reading_weighting(weighting: Option<&str>) -> Result<ReadingWeighting, Error> {
+    match weighting.unwrap_or("reading") {
+        "reading" => Ok(ReadingWeighting::PerReading),
+        "device" => Ok(ReadingWeighting::PerDevice),
+        "time" => Ok(ReadingWeighting::TimeWeighted),
+        _ => Err(Error::SchemaError),
+    }
+}
+
+fn convert_to_smoothing(method: Option<&str>, window: usize) -> Result<Smoothing, Error> {
+    if window == 0 {
+        return Err(Error::SchemaError);
+    }
+    match method.unwrap_or("sma") {
+        "sma" => Ok(Smoothing::MovingAverage { window }),
+        "ewma" => Ok(Smoothing::Exponential { window }),
+        _ => Err(Error::SchemaError),
+    }
 }
 
 #[test]
@@ -182,6 +1408,156 @@ fn test_naive_time_conversion() {
     convert_string_to_utc_datetime(naive).unwrap();
 }
 
+#[test]
+fn test_percentiles_conversion() {
+    assert_eq!(
+        convert_string_to_percentiles("50, 90,99.9").unwrap(),
+        vec![50.0, 90.0, 99.9]
+    );
+    assert!(convert_string_to_percentiles("50,101").is_err());
+    assert!(convert_string_to_percentiles("fifty").is_err());
+}
+
+#[test]
+fn test_duration_conversion() {
+    assert_eq!(
+        convert_string_to_duration("5m").unwrap(),
+        Duration::minutes(5)
+    );
+    assert_eq!(convert_string_to_duration("1d").unwrap(), Duration::days(1));
+    assert!(convert_string_to_duration("0h").is_err());
+    assert!(convert_string_to_duration("5w").is_err());
+    assert!(convert_string_to_duration("").is_err());
+    assert!(convert_string_to_duration("5é").is_err());
+    assert!(convert_string_to_duration("99999999999999d").is_err());
+}
+
+#[test]
+fn test_allowed_range_conversion() {
+    assert_eq!(convert_to_allowed_range(None, None).unwrap(), None);
+    assert_eq!(
+        convert_to_allowed_range(Some(2.0), Some(8.0)).unwrap(),
+        Some(AllowedTemperatureRange { min: 2.0, max: 8.0 })
+    );
+    assert!(convert_to_allowed_range(Some(2.0), None).is_err());
+    assert!(convert_to_allowed_range(Some(8.0), Some(2.0)).is_err());
+}
+
+#[test]
+fn test_temperature_metric_conversion() {
+    assert_eq!(
+        convert_to_temperature_metric(None, None).unwrap(),
+        TemperatureMetric::Mean
+    );
+    assert_eq!(
+        convert_to_temperature_metric(Some("mkt"), None).unwrap(),
+        TemperatureMetric::MeanKinetic {
+            activation_energy: DEFAULT_ACTIVATION_ENERGY
+        }
+    );
+    assert_eq!(
+        convert_to_temperature_metric(Some("mkt"), Some(60.0)).unwrap(),
+        TemperatureMetric::MeanKinetic {
+            activation_energy: 60.0
+        }
+    );
+    assert!(convert_to_temperature_metric(Some("mkt"), Some(-1.0)).is_err());
+    assert!(convert_to_temperature_metric(Some("mean"), Some(60.0)).is_err());
+    assert!(convert_to_temperature_metric(Some("median"), None).is_err());
+}
+
+#[test]
+fn test_smoothing_conversion() {
+    assert_eq!(
+        convert_to_smoothing(None, 12).unwrap(),
+        Smoothing::MovingAverage { window: 12 }
+    );
+    assert_eq!(
+        convert_to_smoothing(Some("ewma"), 12).unwrap(),
+        Smoothing::Exponential { window: 12 }
+    );
+    assert!(convert_to_smoothing(Some("ewma"), 0).is_err());
+    assert!(convert_to_smoothing(Some("median"), 12).is_err());
+}
+
+#[test]
+fn test_reading_weighting_conversion() {
+    assert_eq!(
+        convert_to_reading_weighting(None).unwrap(),
+        ReadingWeighting::PerReading
+    );
+    assert_eq!(
+        convert_to_reading_weighting(Some("time")).unwrap(),
+        ReadingWeighting::TimeWeighted
+    );
+    assert!(convert_to_reading_weighting(Some("devices")).is_err());
+}
+
+#[test]
+fn test_tags_conversion() {
+    assert_eq!(
+        convert_string_to_tags(Some("reefer, pharma,")),
+        vec!["reefer".to_string(), "pharma".to_string()]
+    );
+    assert!(convert_string_to_tags(None).is_empty());
+}
+
+#[test]
+fn test_binary_frame_into_command() {
+    use in_schema::{SaveDeviceTemperatureBody, SaveDeviceTemperatureFrame};
+
+    //GIVEN
+    let mut headers = HeaderMap::new();
+    headers.insert("X-Serial-Number", "C48302DDB".parse().unwrap());
+    headers.insert("X-Interval", "300".parse().unwrap());
+    headers.insert("X-Registered-At", "2023-02-01 19:00:00".parse().unwrap());
+    headers.insert("X-Channels", "temperature,humidity".parse().unwrap());
+    let body = SaveDeviceTemperatureBody {
+        serial_number: "C48302DDB".to_string(),
+        interval: 300,
+        temperatures: "FFFE00370001003A".to_string(),
+        registered_at: "2023-02-01 19:00:00".to_string(),
+        channels: vec![
+            MeasurementChannel::Temperature,
+            MeasurementChannel::Humidity,
+        ],
+    };
+
+    //WHEN
+    let from_frame =
+        SaveDeviceTemperatureFrame::from_headers(&headers, &[0xFF, 0xFE, 0, 0x37, 0, 1, 0, 0x3A])
+            .unwrap()
+            .into_command(None)
+            .unwrap();
+    let from_body = body.into_command(None).unwrap();
+
+    //THEN
+    assert_eq!(from_frame.serial_number, from_body.serial_number);
+    assert_eq!(from_frame.interval, from_body.interval);
+    assert_eq!(from_frame.temperatures, from_body.temperatures);
+    assert_eq!(from_frame.registered_at, from_body.registered_at);
+    assert_eq!(from_frame.channels, from_body.channels);
+
+    headers.remove("X-Interval");
+    assert!(SaveDeviceTemperatureFrame::from_headers(&headers, &[]).is_err());
+    // Up to a day between frames
+    for interval in ["0", "-300", "86401", "1000000000000000000"] {
+        headers.insert("X-Interval", interval.parse().unwrap());
+        assert!(SaveDeviceTemperatureFrame::from_headers(&headers, &[])
+            .unwrap()
+            .into_command(None)
+            .is_err());
+    }
+    headers.insert("X-Interval", "86400".parse().unwrap());
+    assert!(SaveDeviceTemperatureFrame::from_headers(&headers, &[])
+        .unwrap()
+        .into_command(None)
+        .is_ok());
+    headers.insert("X-Interval", "300".parse().unwrap());
+    headers.insert("X-Channels", "pressure".parse().unwrap());
+    assert!(SaveDeviceTemperatureFrame::from_headers(&headers, &[]).is_err());
+}
+
 #[test]
 fn datetime_conversion() {
     use chrono::DateTime;
@@ -196,3 +1572,133 @@ fn datetime_conversion() {
 
     let _date_time: DateTime<Utc> = Utc.from_local_datetime(&datetime).unwrap();
 }
+
+#[test]
+fn test_csv_import_into_command() {
+    use in_schema::ImportDeviceReadingsCsv;
+
+    //GIVEN
+    let csv = "value,serialNumber,checkedAt,channel
+4.5,C48302DDB,2023-02-01 19:00:00,
+61,C48302DDB,2023-02-01 19:00:00,humidity
+
+cold,C48302DDB,2023-02-01 19:05:00,
+4.1,C48302DDB,2023-02-01 19:05,
+4.2,C48302DDB
+3.9,C48302DDC,2023-02-01 19:05:00,pressure";
+
+    //WHEN
+    let cmd = ImportDeviceReadingsCsv {
+        content: csv.to_string(),
+    }
+    .into_command()
+    .unwrap();
+
+    //THEN
+    assert_eq!(
+        cmd.readings
+            .iter()
+            .map(|r| (r.line, r.channel, r.value))
+            .collect::<Vec<_>>(),
+        vec![
+            (2, MeasurementChannel::Temperature, 4.5),
+            (3, MeasurementChannel::Humidity, 61.0)
+        ]
+    );
+    assert_eq!(
+        cmd.rejected_rows
+            .iter()
+            .map(|row| (row.line, format!("{:?}", row.error)))
+            .collect::<Vec<_>>(),
+        vec![
+            (5, "ConversionFailed".to_string()),
+            (6, "SchemaError".to_string()),
+            (7, "SchemaError".to_string()),
+            (8, "SchemaError".to_string()),
+        ]
+    );
+    assert!(ImportDeviceReadingsCsv {
+        content: "serialNumber,value".to_string()
+    }
+    .into_command()
+    .is_err());
+}
+
+#[test]
+fn test_binary_frame_content_type() {
+    use in_schema::SaveDeviceTemperatureFrame;
+
+    let mut headers = HeaderMap::new();
+    assert!(!SaveDeviceTemperatureFrame::is_sent_with(&headers));
+    for content_type in [
+        "application/octet-stream",
+        "Application/Octet-Stream",
+        "application/octet-stream; charset=binary",
+    ] {
+        headers.insert(CONTENT_TYPE, content_type.parse().unwrap());
+        assert!(SaveDeviceTemperatureFrame::is_sent_with(&headers));
+    }
+    headers.insert(CONTENT_TYPE, "application/json".parse().unwrap());
+    assert!(!SaveDeviceTemperatureFrame::is_sent_with(&headers));
+}
+
+#[test]
+fn test_csv_import_from_multipart_body() {
+    use in_schema::ImportDeviceReadingsCsv;
+
+    //GIVEN
+    let mut headers = HeaderMap::new();
+    headers.insert(
+        CONTENT_TYPE,
+        "multipart/form-data; boundary=XyZ".parse().unwrap(),
+    );
+    let body = "--XyZ\r\nContent-Disposition: form-data; name=\"note\"\r\n\r\ntrip 42\r\n\
+--XyZ\r\nContent-Disposition: form-data; name=\"file\"; filename=\"trip.csv\"\r\n\
+Content-Type: text/csv\r\n\r\nserialNumber,checkedAt,value\r\n\
+C48302DDB,2023-02-01 19:00:00,4.5\r\n--XyZ--\r\n";
+
+    //WHEN
+    let csv = ImportDeviceReadingsCsv::from_body(&headers, body.as_bytes()).unwrap();
+
+    //THEN
+    assert_eq!(
+        csv.content,
+        "serialNumber,checkedAt,value\r\nC48302DDB,2023-02-01 19:00:00,4.5"
+    );
+    headers.insert(CONTENT_TYPE, "text/csv".parse().unwrap());
+    assert_eq!(
+        ImportDeviceReadingsCsv::from_body(&headers, b"a,b")
+            .unwrap()
+            .content,
+        "a,b"
+    );
+}
+
+#[test]
+fn test_trend_into_query_bounds() {
+    use in_schema::GetDeviceTemperatureTrend;
+
+    let trend = |channel, horizon: &str, threshold| GetDeviceTemperatureTrend {
+        serial_number: "T18302DDA".to_string(),
+        channel,
+        readings: None,
+        horizon: horizon.to_string(),
+        threshold,
+        exclude_flagged: false,
+    };
+    let temperature = MeasurementChannel::Temperature;
+
+    assert!(trend(temperature, "30d", Some(8.0)).into_query().is_ok());
+    assert!(trend(temperature, "31d", Some(8.0)).into_query().is_err());
+    assert!(trend(temperature, "9223372036854775807s", None)
+        .into_query()
+        .is_err());
+    assert!(trend(temperature, "2h", Some(1e30)).into_query().is_err());
+    assert!(trend(temperature, "2h", Some(f32::NAN))
+        .into_query()
+        .is_err());
+    // Battery voltage and door state have no physical range to bound it
+    assert!(trend(MeasurementChannel::BatteryVoltage, "2h", Some(1e3))
+        .into_query()
+        .is_ok());
+}
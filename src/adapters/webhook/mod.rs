@@ -0,0 +1,48 @@
+use std::time::Duration;
+
+use reqwest::{header::CONTENT_TYPE, Client};
+
+use crate::services::notifier::TWebhookSender;
+
+// Posts JSON bodies over HTTP or HTTPS, following redirects
+#[derive(Clone)]
+pub struct HttpWebhookSender {
+    client: Client,
+}
+
+impl HttpWebhookSender {
+    // Gives up on a webhook not done responding within `timeout`
+    pub fn new(timeout: Duration) -> Result<Self, String> {
+        Ok(Self {
+            client: Client::builder()
+                .timeout(timeout)
+                .build()
+                .map_err(|err| err.to_string())?,
+        })
+    }
+}
+
+impl Default for HttpWebhookSender {
+    fn default() -> Self {
+        Self::new(Duration::from_secs(10)).expect("default HTTP client builds")
+    }
+}
+
+impl TWebhookSender for HttpWebhookSender {
+    async fn send(&self, url: &str, headers: &[(&str, String)], body: &str) -> Result<(), String> {
+        let mut request = self
+            .client
+            .post(url)
+            .header(CONTENT_TYPE, "application/json")
+            .body(body.to_string());
+        for (name, value) in headers {
+            request = request.header(*name, value);
+        }
+
+        let response = request.send().await.map_err(|err| err.to_string())?;
+        match response.status() {
+            status if status.is_success() => Ok(()),
+            status => Err(format!("responded with {}", status.as_u16())),
+        }
+    }
+}
@@ -1,6 +1,14 @@
-use std::{env, net::SocketAddr};
+use std::{env, net::SocketAddr, time::Duration};
 
-use middle_mile::adapters::rest_api::routers::routers;
+use chrono::Utc;
+use middle_mile::{
+    adapters::{database::mock_db::MockDb, rest_api::routers::routers, webhook::HttpWebhookSender},
+    domain::device::commands::DetectOfflineDevices,
+    services::{
+        handlers::CommandHandler,
+        notifier::{WebhookConfig, WebhookNotifier},
+    },
+};
 use tokio::net::TcpListener;
 
 #[tokio::main(flavor = "current_thread")]
@@ -9,6 +17,28 @@ async fn main() {
         .await
         .unwrap();
 
+    tokio::spawn(detect_offline_devices(
+        env_or("OFFLINE_CHECK_PERIOD", 60),
+        env_or("OFFLINE_INTERVAL_MULTIPLE", 3),
+    ));
+    let webhook_config = WebhookConfig {
+        urls: env::var("WEBHOOK_URLS")
+            .unwrap_or_default()
+            .split(',')
+            .map(|url| url.trim().to_string())
+            .filter(|url| !url.is_empty())
+            .collect(),
+        secret: env::var("WEBHOOK_SECRET").unwrap_or_default(),
+        max_attempts: env_or("WEBHOOK_MAX_ATTEMPTS", 5),
+        base_backoff: Duration::from_millis(env_or("WEBHOOK_BACKOFF_MS", 500).into()),
+    };
+    // Unsigned bodies would let anyone pass for us
+    if !webhook_config.urls.is_empty() && webhook_config.secret.is_empty() {
+        eprintln!("[ERROR] WEBHOOK_SECRET must be set along with WEBHOOK_URLS, notifications won't be dispatched");
+    } else {
+        tokio::spawn(dispatch_notifications(webhook_config));
+    }
+
     println!("Server running...");
     axum::serve(
         listener,
@@ -17,3 +47,40 @@ async fn main() {
     .await
     .unwrap();
 }
+
+// Every `period_in_secs`, marks offline the devices silent for `interval_multiple` reporting intervals
+async fn detect_offline_devices(period_in_secs: u32, interval_multiple: u32) {
+    let mut ticker = tokio::time::interval(Duration::from_secs(period_in_secs.max(1).into()));
+    loop {
+        ticker.tick().await;
+        let cmd = DetectOfflineDevices {
+            now: Utc::now(),
+            interval_multiple,
+        };
+        match CommandHandler::new(cmd, MockDb).handle().await {
+            Ok(devices) => devices.iter().for_each(|device| {
+                eprintln!("[WARN] Device went offline {}", device.serial_number)
+            }),
+            Err(err) => eprintln!("[ERROR] Offline detection failed {:?}", err),
+        }
+    }
+}
+
+// Every second, attempts the deliveries to the webhooks which are due
+async fn dispatch_notifications(config: WebhookConfig) {
+    let notifier = WebhookNotifier::new(MockDb, HttpWebhookSender::default(), config);
+    let mut ticker = tokio::time::interval(Duration::from_secs(1));
+    loop {
+        ticker.tick().await;
+        if let Err(err) = notifier.dispatch().await {
+            eprintln!("[ERROR] Notification dispatch failed {:?}", err);
+        }
+    }
+}
+
+fn env_or(name: &str, default: u32) -> u32 {
+    env::var(name)
+        .ok()
+        .and_then(|value| value.parse().ok())
+        .unwrap_or(default)
+}
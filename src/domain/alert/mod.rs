@@ -0,0 +1,98 @@
+pub mod query;
+pub mod repository;
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use super::{device::excursion::Excursion, device_group::alert_rule::AlertRule};
+
+#[derive(Default, Clone, Copy, Serialize, Deserialize, Debug, PartialEq, Eq)]
+pub enum AlertStatus {
+    #[default]
+    Open,
+    Resolved,
+}
+
+// Consecutive readings of a device breaching an alert rule
+#[derive(Clone, Debug, PartialEq)]
+pub struct Breach {
+    pub started_at: DateTime<Utc>,
+    pub last_breached_at: DateTime<Utc>,
+    // The highest value above the threshold, or the lowest one below it
+    pub peak_value: f32,
+    // The first reading back within the threshold. `None` while the breach is going on.
+    pub ended_at: Option<DateTime<Utc>>,
+}
+
+impl From<Excursion> for Breach {
+    fn from(value: Excursion) -> Self {
+        Self {
+            started_at: value.start,
+            last_breached_at: value.last_out_of_range_at,
+            peak_value: value.peak_value,
+            ended_at: (!value.ongoing).then_some(value.end),
+        }
+    }
+}
+
+#[derive(Clone, Serialize, Debug)]
+pub struct AlertAggregate {
+    #[serde(rename = "alertId")]
+    pub alert_id: i64,
+    #[serde(rename = "deviceGroupSerial")]
+    pub device_group_serial: String,
+    #[serde(rename = "serialNumber")]
+    pub serial_number: String,
+    // As it was when the alert was triggered
+    pub rule: AlertRule,
+    pub status: AlertStatus,
+    #[serde(rename = "triggeredAt")]
+    pub triggered_at: DateTime<Utc>,
+    #[serde(rename = "lastBreachedAt")]
+    pub last_breached_at: DateTime<Utc>,
+    #[serde(rename = "peakValue")]
+    pub peak_value: f32,
+    #[serde(rename = "resolvedAt")]
+    pub resolved_at: Option<DateTime<Utc>>,
+}
+
+impl AlertAggregate {
+    pub fn new(
+        device_group_serial: &str,
+        serial_number: &str,
+        rule: &AlertRule,
+        breach: Breach,
+    ) -> Self {
+        let mut alert = Self {
+            alert_id: 0,
+            device_group_serial: device_group_serial.to_string(),
+            serial_number: serial_number.to_string(),
+            rule: rule.clone(),
+            status: AlertStatus::Open,
+            triggered_at: breach.started_at,
+            last_breached_at: breach.last_breached_at,
+            peak_value: breach.peak_value,
+            resolved_at: None,
+        };
+        alert.follow(breach);
+        alert
+    }
+
+    // Whether the breach is the one the alert was raised for, as seen with more readings
+    pub fn is_raised_for(&self, rule_id: u32, breach: &Breach) -> bool {
+        self.rule.rule_id == rule_id
+            && breach.started_at <= self.last_breached_at
+            && self.triggered_at <= breach.last_breached_at
+    }
+
+    // Resolves the alert once the breach is over
+    pub fn follow(&mut self, breach: Breach) {
+        self.triggered_at = self.triggered_at.min(breach.started_at);
+        self.last_breached_at = self.last_breached_at.max(breach.last_breached_at);
+        self.peak_value = self.rule.worse_of(self.peak_value, breach.peak_value);
+        (self.status, self.resolved_at) = match breach.ended_at {
+            Some(ended_at) => (AlertStatus::Resolved, Some(ended_at)),
+            None => (AlertStatus::Open, None),
+        };
+    }
+}
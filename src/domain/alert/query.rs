@@ -0,0 +1,11 @@
+use super::AlertStatus;
+
+pub struct GetAlertQuery {
+    pub alert_id: i64,
+}
+
+pub struct ListAlertsQuery {
+    pub device_group_serial: Option<String>,
+    pub serial_number: Option<String>,
+    pub status: Option<AlertStatus>,
+}
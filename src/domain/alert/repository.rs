@@ -0,0 +1,29 @@
+use crate::domain::response::Error;
+
+use super::AlertAggregate;
+
+pub trait TAlertQuery {
+    fn get(
+        &self,
+        alert_id: i64,
+    ) -> impl std::future::Future<Output = Result<AlertAggregate, Error>> + Send;
+
+    fn list(&self) -> impl std::future::Future<Output = Result<Vec<AlertAggregate>, Error>> + Send;
+
+    fn list_by_device(
+        &self,
+        serial_number: &str,
+    ) -> impl std::future::Future<Output = Result<Vec<AlertAggregate>, Error>> + Send;
+}
+
+pub trait TAlertPersist {
+    fn add(
+        &self,
+        alert: &mut AlertAggregate,
+    ) -> impl std::future::Future<Output = Result<(), Error>> + Send;
+
+    fn update(
+        &self,
+        alert: &mut AlertAggregate,
+    ) -> impl std::future::Future<Output = Result<(), Error>> + Send;
+}
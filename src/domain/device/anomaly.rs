@@ -0,0 +1,171 @@
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+
+use super::DeviceTemperature;
+
+// Readings a spike is measured against, and how many standard deviations away it lies
+pub const ROLLING_WINDOW: usize = 12;
+pub const SPIKE_Z_SCORE: f32 = 4.0;
+// Floor of the standard deviation, so a sensor reading steadily doesn't turn its own noise
+// into spikes
+pub const MIN_STANDARD_DEVIATION: f32 = 0.5;
+// Fastest change the temperature of a cold-chain load can physically go through, in degrees
+// per minute
+pub const MAX_RATE_PER_MINUTE: f32 = 2.0;
+// Sensors are noisy in their last digit, so this many identical raw values in a row means the
+// sensor stopped measuring
+pub const FLATLINE_LENGTH: usize = 24;
+
+#[derive(Clone, Copy, Serialize, Debug, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum AnomalyFlag {
+    // Far from the rolling mean of the readings before it
+    Spike,
+    // Further from the last plausible reading than the temperature could have changed since
+    Jump,
+    // Part of a run of identical raw values
+    Flatline,
+}
+
+#[derive(Clone, Serialize, Debug, PartialEq)]
+pub struct FlaggedReading {
+    #[serde(rename = "checkedAt")]
+    pub checked_at: DateTime<Utc>,
+    pub value: f32,
+    #[serde(rename = "rawValue")]
+    pub raw_value: f32,
+    pub anomalies: Vec<AnomalyFlag>,
+}
+
+impl From<&DeviceTemperature> for FlaggedReading {
+    fn from(temp: &DeviceTemperature) -> Self {
+        Self {
+            checked_at: temp.checked_at,
+            value: temp.value,
+            raw_value: temp.raw_value,
+            anomalies: temp.anomalies.clone(),
+        }
+    }
+}
+
+// `preceding` are the readings of the same channel taken before `reading`, latest first.
+// Spikes are measured against every reading of the window, flagged or not, so the mean catches
+// up with an actual change of temperature rather than flagging all that follows.
+pub(crate) fn detect<'a>(
+    reading: &DeviceTemperature,
+    preceding: impl Iterator<Item = &'a DeviceTemperature> + Clone,
+) -> Vec<AnomalyFlag> {
+    let mut anomalies = vec![];
+
+    let window = preceding
+        .clone()
+        .take(ROLLING_WINDOW)
+        .map(|temp| temp.value)
+        .collect::<Vec<_>>();
+    if window.len() == ROLLING_WINDOW {
+        let mean = window.iter().sum::<f32>() / window.len() as f32;
+        let variance = window
+            .iter()
+            .map(|value| (value - mean).powi(2))
+            .sum::<f32>()
+            / window.len() as f32;
+        let std_deviation = variance.sqrt().max(MIN_STANDARD_DEVIATION);
+        if (reading.value - mean).abs() / std_deviation > SPIKE_Z_SCORE {
+            anomalies.push(AnomalyFlag::Spike);
+        }
+    }
+
+    let last_plausible = preceding.clone().find(|temp| {
+        !temp
+            .anomalies
+            .iter()
+            .any(|anomaly| matches!(anomaly, AnomalyFlag::Spike | AnomalyFlag::Jump))
+    });
+    if let Some(last_plausible) = last_plausible {
+        // Readings taken within a minute may differ by as much as within a minute
+        let minutes =
+            ((reading.checked_at - last_plausible.checked_at).num_seconds() as f32 / 60.0).max(1.0);
+        if (reading.value - last_plausible.value).abs() > MAX_RATE_PER_MINUTE * minutes {
+            anomalies.push(AnomalyFlag::Jump);
+        }
+    }
+
+    let same_raw_values = preceding
+        .take(FLATLINE_LENGTH - 1)
+        .take_while(|temp| temp.raw_value == reading.raw_value)
+        .count();
+    if same_raw_values == FLATLINE_LENGTH - 1 {
+        anomalies.push(AnomalyFlag::Flatline);
+    }
+
+    anomalies
+}
+
+#[cfg(test)]
+mod test_anomaly {
+    use chrono::{Duration, Utc};
+
+    use crate::domain::device::{DeviceTemperature, MeasurementChannel};
+
+    use super::{detect, AnomalyFlag, FLATLINE_LENGTH, ROLLING_WINDOW};
+
+    #[test]
+    fn anomalies_of_reading() {
+        //GIVEN
+        let start = Utc::now() - Duration::days(1);
+        let reading = |minutes: i64, value: f32| {
+            DeviceTemperature::new(
+                1,
+                MeasurementChannel::Temperature,
+                start + Duration::minutes(minutes),
+                value,
+                None,
+            )
+        };
+        // every 5 minutes, around 5 degrees
+        let readings = (0..ROLLING_WINDOW as i64)
+            .map(|i| reading(i * 5, 5.0 + (i % 2) as f32 * 0.2))
+            .collect::<Vec<_>>();
+        let next = ROLLING_WINDOW as i64 * 5;
+
+        //WHEN
+        let flags_of = |temp: &DeviceTemperature, preceding: &[DeviceTemperature]| {
+            detect(temp, preceding.iter().rev())
+        };
+
+        //THEN
+        assert_eq!(flags_of(&reading(next, 5.4), &readings), vec![]);
+        // 3 degrees in 5 minutes is plausible, but not next to readings this steady
+        assert_eq!(
+            flags_of(&reading(next, 8.0), &readings),
+            vec![AnomalyFlag::Spike]
+        );
+        assert_eq!(
+            flags_of(&reading(next, 40.0), &readings),
+            vec![AnomalyFlag::Spike, AnomalyFlag::Jump]
+        );
+        // Not enough readings for a window yet
+        assert_eq!(
+            flags_of(&reading(10, 40.0), &readings[..2]),
+            vec![AnomalyFlag::Jump]
+        );
+
+        // Jumps are measured against the last reading which wasn't one
+        let mut glitched = readings.clone();
+        glitched.last_mut().unwrap().anomalies = vec![AnomalyFlag::Spike, AnomalyFlag::Jump];
+        glitched.last_mut().unwrap().value = 40.0;
+        assert_eq!(flags_of(&reading(next, 5.2), &glitched), vec![]);
+
+        let stuck = (0..FLATLINE_LENGTH as i64 - 1)
+            .map(|i| reading(i * 5, 5.0))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            flags_of(&reading(FLATLINE_LENGTH as i64 * 5, 5.0), &stuck),
+            vec![AnomalyFlag::Flatline]
+        );
+        assert_eq!(
+            flags_of(&reading(FLATLINE_LENGTH as i64 * 5, 5.0), &stuck[1..]),
+            vec![]
+        );
+    }
+}
@@ -0,0 +1,21 @@
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+
+// Profiles are never edited in place. Each calibration adds a new version so that the value a
+// reading was corrected with can always be traced back through `DeviceTemperature::calibration_version`.
+#[derive(Clone, Serialize, Debug, PartialEq)]
+pub struct CalibrationProfile {
+    pub version: u32,
+    pub offset: f32,
+    pub multiplier: f32,
+    #[serde(rename = "validFrom")]
+    pub valid_from: DateTime<Utc>,
+    #[serde(rename = "createdAt")]
+    pub created_at: DateTime<Utc>,
+}
+
+impl CalibrationProfile {
+    pub fn apply(&self, raw: f32) -> f32 {
+        raw * self.multiplier + self.offset
+    }
+}
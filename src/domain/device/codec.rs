@@ -0,0 +1,167 @@
+use serde::{Deserialize, Serialize};
+
+use crate::domain::response::Error;
+
+#[derive(Default, Clone, Copy, Serialize, Deserialize, Debug, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum Endianness {
+    #[default]
+    Big,
+    Little,
+}
+
+// Describes how a single value is laid out in the hex encoded `temperatures` payload.
+// The default is the original format: big-endian signed 16-bit whole units in 4 hex digits.
+#[derive(Clone, Copy, Serialize, Deserialize, Debug, PartialEq)]
+pub struct PayloadCodec {
+    // Number of bytes per value. One of 1, 2 and 4
+    #[serde(rename = "width")]
+    pub width_in_bytes: usize,
+    pub endianness: Endianness,
+    pub signed: bool,
+    // Decoded integers are multiplied by it, e.g. 0.1 for values sent in tenths of a degree
+    #[serde(rename = "scaleFactor")]
+    pub scale_factor: f32,
+}
+
+impl Default for PayloadCodec {
+    fn default() -> Self {
+        Self {
+            width_in_bytes: 2,
+            endianness: Endianness::Big,
+            signed: true,
+            scale_factor: 1.0,
+        }
+    }
+}
+
+impl PayloadCodec {
+    pub fn validate(&self) -> Result<(), Error> {
+        if ![1, 2, 4].contains(&self.width_in_bytes)
+            || !self.scale_factor.is_finite()
+            || self.scale_factor == 0.0
+        {
+            eprintln!("[ERROR] Invalid codec given");
+            return Err(Error::SchemaError);
+        }
+        Ok(())
+    }
+
+    pub fn hex_width(&self) -> usize {
+        self.width_in_bytes * 2
+    }
+
+    pub fn decode(&self, hex: &str) -> Result<f32, Error> {
+        Ok(self.scale(self.decode_integer(hex)?))
+    }
+
+    pub(crate) fn scale(&self, integer: i64) -> f32 {
+        integer as f32 * self.scale_factor
+    }
+
+    // Smallest difference between two decoded values
+    pub(crate) fn resolution(&self) -> f32 {
+        self.scale_factor.abs()
+    }
+
+    // Loggers send the extremes of the integer range for a disconnected sensor, e.g. 7FFF and
+    // 8000 in the default format
+    pub(crate) fn is_sentinel(&self, integer: i64) -> bool {
+        let bits = 8 * self.width_in_bytes as u32;
+        match self.signed {
+            true => integer == (1 << (bits - 1)) - 1 || integer == -(1 << (bits - 1)),
+            false => integer == (1 << bits) - 1,
+        }
+    }
+
+    pub(crate) fn decode_integer(&self, hex: &str) -> Result<i64, Error> {
+        if hex.len() != self.hex_width() {
+            eprintln!("[ERROR] Wrong length given ");
+            return Err(Error::ConversionFailed);
+        }
+        if !hex.bytes().all(|byte| byte.is_ascii_hexdigit()) {
+            eprintln!("[ERROR] Non-hex digit given {}", hex);
+            return Err(Error::ConversionFailed);
+        }
+
+        let mut value = u32::from_str_radix(hex, 16).map_err(|err| {
+            eprintln!("[ERROR] Conversion failed {}", err);
+            Error::ConversionFailed
+        })?;
+        if self.endianness == Endianness::Little {
+            value = value.swap_bytes() >> (32 - 8 * self.width_in_bytes);
+        }
+
+        let bits = 8 * self.width_in_bytes as u32;
+        if self.signed && value >> (bits - 1) == 1 {
+            return Ok(value as i64 - (1_i64 << bits));
+        }
+        Ok(value as i64)
+    }
+}
+
+#[cfg(test)]
+mod test_codec {
+    use super::{Endianness, PayloadCodec};
+
+    #[test]
+    fn default_codec() {
+        let codec = PayloadCodec::default();
+
+        assert_eq!(codec.decode("FFFE").unwrap(), -2.0);
+        assert_eq!(codec.decode("0003").unwrap(), 3.0);
+        assert!(codec.decode("003").is_err());
+        assert!(codec.decode("00G3").is_err());
+        assert!(codec.decode("+003").is_err());
+        assert!(codec.is_sentinel(codec.decode_integer("7FFF").unwrap()));
+        assert!(codec.is_sentinel(codec.decode_integer("8000").unwrap()));
+        assert!(!codec.is_sentinel(codec.decode_integer("7FFE").unwrap()));
+    }
+
+    #[test]
+    fn little_endian_tenths_of_degree() {
+        //GIVEN
+        let codec = PayloadCodec {
+            endianness: Endianness::Little,
+            scale_factor: 0.1,
+            ..Default::default()
+        };
+
+        //WHEN
+        // 0x0041 = 65 and 0xFFEC = -20
+        let values = ["4100", "ECFF"]
+            .into_iter()
+            .map(|hex| codec.decode(hex).unwrap())
+            .collect::<Vec<_>>();
+
+        //THEN
+        assert_eq!(values, vec![6.5, -2.0]);
+    }
+
+    #[test]
+    fn width_and_signedness() {
+        let unsigned_byte = PayloadCodec {
+            width_in_bytes: 1,
+            signed: false,
+            ..Default::default()
+        };
+        let signed_word = PayloadCodec {
+            width_in_bytes: 4,
+            endianness: Endianness::Little,
+            ..Default::default()
+        };
+
+        assert_eq!(unsigned_byte.decode_integer("FE").unwrap(), 254);
+        assert_eq!(signed_word.decode_integer("FEFFFFFF").unwrap(), -2);
+        assert_eq!(signed_word.decode_integer("01000000").unwrap(), 1);
+        assert!(unsigned_byte.is_sentinel(255));
+        assert!(!unsigned_byte.is_sentinel(0));
+        assert!(signed_word.is_sentinel(i32::MIN as i64));
+        assert!(PayloadCodec {
+            width_in_bytes: 3,
+            ..Default::default()
+        }
+        .validate()
+        .is_err());
+    }
+}
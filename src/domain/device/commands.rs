@@ -1,18 +1,128 @@
+use std::collections::{BTreeMap, BTreeSet};
+
 use chrono::{DateTime, Utc};
 use serde::Deserialize;
 
-#[derive(Deserialize)]
+use super::{codec::PayloadCodec, MeasurementChannel};
+use crate::domain::response::ImportRowError;
+
+#[derive(Deserialize, Default)]
 pub struct RegisterDevice {
     #[serde(rename = "serialNumber")]
     pub serial_number: String,
     #[serde(rename = "deviceGroupSerial")]
     pub device_group_serial: String,
+    // Free-form key/value pairs such as location, trailer id and product type
+    #[serde(default)]
+    pub metadata: BTreeMap<String, String>,
+    #[serde(default)]
+    pub tags: BTreeSet<String>,
+    // Selects the payload codec registered for the model, if any
+    #[serde(default)]
+    pub model: Option<String>,
 }
 
+// Longest `interval` a batch may declare between its frames
+pub const MAX_REPORTING_INTERVAL_SECS: i64 = 24 * 60 * 60;
+
 #[derive(Deserialize)]
 pub struct SaveDeviceTemperature {
     pub serial_number: String,
     pub interval: i64,
     pub temperatures: String,
     pub registered_at: DateTime<Utc>,
+    // Order in which channel values appear within each frame of `temperatures`
+    #[serde(default = "default_channels")]
+    pub channels: Vec<MeasurementChannel>,
+    // Replaying a command with the same key returns the original response without saving again
+    #[serde(default)]
+    pub idempotency_key: Option<String>,
+}
+
+#[derive(Deserialize)]
+pub struct SuspendDevice {
+    #[serde(rename = "serialNumber")]
+    pub serial_number: String,
+}
+
+#[derive(Deserialize)]
+pub struct ReactivateDevice {
+    #[serde(rename = "serialNumber")]
+    pub serial_number: String,
+}
+
+#[derive(Deserialize)]
+pub struct DecommissionDevice {
+    #[serde(rename = "serialNumber")]
+    pub serial_number: String,
+}
+
+#[derive(Deserialize)]
+pub struct TransferDevice {
+    #[serde(rename = "serialNumber")]
+    pub serial_number: String,
+    #[serde(rename = "deviceGroupSerial")]
+    pub device_group_serial: String,
+}
+
+#[derive(Deserialize, Default)]
+pub struct UpdateDeviceMetadata {
+    #[serde(rename = "serialNumber")]
+    pub serial_number: String,
+    // Given keys are upserted and the ones with `null` are removed. Keys not given are left as-is.
+    #[serde(default)]
+    pub metadata: BTreeMap<String, Option<String>>,
+    #[serde(rename = "addTags", default)]
+    pub add_tags: BTreeSet<String>,
+    #[serde(rename = "removeTags", default)]
+    pub remove_tags: BTreeSet<String>,
+}
+
+#[derive(Deserialize)]
+pub struct CalibrateDevice {
+    pub serial_number: String,
+    pub offset: f32,
+    pub multiplier: f32,
+    pub valid_from: DateTime<Utc>,
+}
+
+#[derive(Deserialize)]
+pub struct SetDeviceCodec {
+    #[serde(rename = "serialNumber")]
+    pub serial_number: String,
+    // `null` falls back to the codec of the device model
+    pub codec: Option<PayloadCodec>,
+}
+
+#[derive(Deserialize)]
+pub struct SetDeviceModelCodec {
+    pub model: String,
+    pub codec: PayloadCodec,
+}
+
+// Historical readings recovered from a logger, possibly for several devices at once
+pub struct ImportDeviceReadings {
+    pub readings: Vec<ImportedReading>,
+    // Rows that could not even be parsed. They are reported back along with the rest.
+    pub rejected_rows: Vec<ImportRowError>,
+}
+
+pub struct ImportedReading {
+    pub line: usize,
+    pub serial_number: String,
+    pub channel: MeasurementChannel,
+    pub checked_at: DateTime<Utc>,
+    // Decoded value before calibration
+    pub value: f32,
+}
+
+// Run periodically rather than requested through the API
+pub struct DetectOfflineDevices {
+    pub now: DateTime<Utc>,
+    // Reporting intervals a device may stay silent for before it is considered offline
+    pub interval_multiple: u32,
+}
+
+pub fn default_channels() -> Vec<MeasurementChannel> {
+    vec![MeasurementChannel::Temperature]
 }
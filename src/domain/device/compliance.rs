@@ -0,0 +1,141 @@
+use chrono::{DateTime, Duration, Utc};
+use serde::Serialize;
+
+use crate::domain::device_group::AllowedTemperatureRange;
+
+use super::excursion::{covered_until, Excursion};
+
+// Share of a period readings must cover for a device to pass, unless told otherwise
+pub const DEFAULT_MIN_COVERAGE: f32 = 0.9;
+
+#[derive(Clone, Serialize, Debug, PartialEq)]
+pub struct ComplianceReport {
+    #[serde(rename = "timeInRangeSecs")]
+    pub time_in_range_secs: i64,
+    #[serde(rename = "timeOutOfRangeSecs")]
+    pub time_out_of_range_secs: i64,
+    #[serde(rename = "excursionCount")]
+    pub excursion_count: usize,
+    #[serde(rename = "worstExcursion")]
+    pub worst_excursion: Option<Excursion>,
+    // Share of the period covered by readings, from 0 to 1
+    pub coverage: f32,
+    // Without any excursion, and with at least the minimum coverage
+    pub passed: bool,
+}
+
+impl ComplianceReport {
+    // Readings must be in chronological order. See `covered_until` for how long each stands for.
+    pub(crate) fn evaluate(
+        start_date: DateTime<Utc>,
+        end_date: DateTime<Utc>,
+        interval: Option<Duration>,
+        allowed_range: &AllowedTemperatureRange,
+        min_coverage: f32,
+        readings: impl Iterator<Item = (DateTime<Utc>, f32)>,
+    ) -> Self {
+        let readings = readings.collect::<Vec<_>>();
+        let mut time_in_range_secs = 0;
+        let mut time_out_of_range_secs = 0;
+        for (index, &(checked_at, value)) in readings.iter().enumerate() {
+            let covered_secs =
+                (covered_until(&readings, index, interval, end_date) - checked_at).num_seconds();
+            match allowed_range.contains(value) {
+                true => time_in_range_secs += covered_secs,
+                false => time_out_of_range_secs += covered_secs,
+            }
+        }
+        let excursions = Excursion::detect(end_date, interval, allowed_range, &readings);
+
+        let period_secs = (end_date - start_date).num_seconds();
+        let coverage = match period_secs > 0 {
+            true => (time_in_range_secs + time_out_of_range_secs) as f32 / period_secs as f32,
+            false => 0.0,
+        };
+        Self {
+            time_in_range_secs,
+            time_out_of_range_secs,
+            excursion_count: excursions.len(),
+            passed: excursions.is_empty() && coverage >= min_coverage,
+            worst_excursion: excursions.into_iter().max_by(|a, b| {
+                allowed_range
+                    .deviation_of(a.peak_value)
+                    .total_cmp(&allowed_range.deviation_of(b.peak_value))
+            }),
+            coverage,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test_compliance {
+    use chrono::{Duration, Utc};
+
+    use crate::domain::device_group::AllowedTemperatureRange;
+
+    use super::ComplianceReport;
+
+    #[test]
+    fn compliance_of_readings() {
+        //GIVEN
+        let start = Utc::now() - Duration::hours(1);
+        let minutes = |minutes: i64| start + Duration::minutes(minutes);
+        let allowed_range = AllowedTemperatureRange { min: 2.0, max: 8.0 };
+        // every 5 minutes, with nothing at 20 nor 25
+        let readings = [
+            (0, 5.0),
+            (5, 9.0),
+            (10, 12.0),
+            (15, 4.0),
+            (30, 1.0),
+            (35, 5.0),
+            (40, 6.0),
+            (45, 7.0),
+            (50, 7.0),
+            (55, 7.0),
+        ]
+        .map(|(at, value)| (minutes(at), value));
+
+        //WHEN
+        let report = ComplianceReport::evaluate(
+            start,
+            minutes(60),
+            Some(Duration::minutes(5)),
+            &allowed_range,
+            0.8,
+            readings.into_iter(),
+        );
+
+        //THEN
+        assert_eq!(report.time_in_range_secs, 35 * 60);
+        assert_eq!(report.time_out_of_range_secs, 15 * 60);
+        assert_eq!(report.excursion_count, 2);
+        let worst_excursion = report.worst_excursion.unwrap();
+        assert_eq!(worst_excursion.start, minutes(5));
+        assert_eq!(worst_excursion.peak_value, 12.0);
+        assert_eq!(report.coverage, 50.0 / 60.0);
+        assert!(!report.passed);
+
+        let in_range_only = ComplianceReport::evaluate(
+            start,
+            minutes(60),
+            Some(Duration::minutes(5)),
+            &allowed_range,
+            0.8,
+            readings.into_iter().skip(5),
+        );
+        assert_eq!(in_range_only.excursion_count, 0);
+        assert!(!in_range_only.passed);
+        assert!(
+            ComplianceReport::evaluate(
+                start,
+                minutes(60),
+                Some(Duration::minutes(5)),
+                &allowed_range,
+                0.4,
+                readings.into_iter().skip(5),
+            )
+            .passed
+        );
+    }
+}
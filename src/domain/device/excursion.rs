@@ -0,0 +1,188 @@
+use chrono::{DateTime, Duration, Utc};
+use serde::Serialize;
+
+use crate::domain::device_group::AllowedTemperatureRange;
+
+// Consecutive readings outside of an allowed range. Alerts, compliance reports and claims all
+// go by this definition.
+#[derive(Clone, Serialize, Debug, PartialEq)]
+pub struct Excursion {
+    // The first reading outside of the range
+    pub start: DateTime<Utc>,
+    // The first reading back within the range. While the excursion goes on, as far as the last
+    // reading outside of the range stands for.
+    pub end: DateTime<Utc>,
+    #[serde(rename = "durationInSecs")]
+    pub duration_in_secs: i64,
+    #[serde(rename = "lastOutOfRangeAt")]
+    pub last_out_of_range_at: DateTime<Utc>,
+    // The furthest reading from the range
+    #[serde(rename = "peakValue")]
+    pub peak_value: f32,
+    // How far beyond the range readings went over time, in degree-minutes
+    #[serde(rename = "areaUnderThreshold")]
+    pub area_under_threshold: f32,
+    // Until a reading is back within the range
+    pub ongoing: bool,
+}
+
+impl Excursion {
+    // Readings must be in chronological order. See `covered_until` for how long each stands for.
+    pub(crate) fn detect(
+        end_date: DateTime<Utc>,
+        interval: Option<Duration>,
+        allowed_range: &AllowedTemperatureRange,
+        readings: &[(DateTime<Utc>, f32)],
+    ) -> Vec<Self> {
+        let mut excursions = vec![];
+        let mut current: Option<Self> = None;
+
+        for (index, &(checked_at, value)) in readings.iter().enumerate() {
+            if allowed_range.contains(value) {
+                if let Some(mut excursion) = current.take() {
+                    excursion.end = checked_at;
+                    excursion.ongoing = false;
+                    excursions.push(excursion.with_duration());
+                }
+                continue;
+            }
+
+            let covered_until = covered_until(readings, index, interval, end_date);
+            let deviation = allowed_range.deviation_of(value);
+            let excursion = current.get_or_insert(Self {
+                start: checked_at,
+                end: covered_until,
+                duration_in_secs: 0,
+                last_out_of_range_at: checked_at,
+                peak_value: value,
+                area_under_threshold: 0.0,
+                ongoing: true,
+            });
+            excursion.end = covered_until;
+            excursion.last_out_of_range_at = checked_at;
+            if deviation > allowed_range.deviation_of(excursion.peak_value) {
+                excursion.peak_value = value;
+            }
+            excursion.area_under_threshold +=
+                deviation * (covered_until - checked_at).num_seconds() as f32 / 60.0;
+        }
+        excursions.extend(current.map(Self::with_duration));
+        excursions
+    }
+
+    fn with_duration(mut self) -> Self {
+        self.duration_in_secs = (self.end - self.start).num_seconds();
+        self
+    }
+}
+
+// A reading stands for the time until the next one, up to a reporting interval, and no later
+// than `end_date`. Without an interval, the last reading stands for no time at all.
+pub(crate) fn covered_until(
+    readings: &[(DateTime<Utc>, f32)],
+    index: usize,
+    interval: Option<Duration>,
+    end_date: DateTime<Utc>,
+) -> DateTime<Utc> {
+    let checked_at = readings[index].0;
+    let next = readings.get(index + 1).map(|&(next, _)| next);
+    // An interval reaching beyond any date doesn't cut anything
+    let interval_end = interval.map(|interval| {
+        checked_at
+            .checked_add_signed(interval)
+            .unwrap_or(DateTime::<Utc>::MAX_UTC)
+    });
+    match (next, interval_end) {
+        (Some(next), Some(interval_end)) => next.min(interval_end),
+        (Some(next), None) => next,
+        (None, Some(interval_end)) => interval_end,
+        (None, None) => checked_at,
+    }
+    .min(end_date)
+    .max(checked_at)
+}
+
+#[cfg(test)]
+mod test_excursion {
+    use chrono::{Duration, Utc};
+
+    use crate::domain::device_group::AllowedTemperatureRange;
+
+    use super::{covered_until, Excursion};
+
+    #[test]
+    fn excursions_of_readings() {
+        //GIVEN
+        let start = Utc::now() - Duration::hours(1);
+        let minutes = |minutes: i64| start + Duration::minutes(minutes);
+        let allowed_range = AllowedTemperatureRange { min: 2.0, max: 8.0 };
+        // every 5 minutes, with nothing at 20 nor 25
+        let readings = [
+            (0, 5.0),
+            (5, 9.0),
+            (10, 12.0),
+            (15, 4.0),
+            (30, 1.0),
+            (35, -1.0),
+        ]
+        .map(|(at, value)| (minutes(at), value));
+
+        //WHEN
+        let excursions = Excursion::detect(
+            minutes(60),
+            Some(Duration::minutes(5)),
+            &allowed_range,
+            &readings,
+        );
+
+        //THEN
+        assert_eq!(
+            excursions,
+            vec![
+                Excursion {
+                    start: minutes(5),
+                    end: minutes(15),
+                    duration_in_secs: 10 * 60,
+                    last_out_of_range_at: minutes(10),
+                    peak_value: 12.0,
+                    area_under_threshold: 1.0 * 5.0 + 4.0 * 5.0,
+                    ongoing: false,
+                },
+                Excursion {
+                    start: minutes(30),
+                    end: minutes(40),
+                    duration_in_secs: 10 * 60,
+                    last_out_of_range_at: minutes(35),
+                    peak_value: -1.0,
+                    area_under_threshold: 1.0 * 5.0 + 3.0 * 5.0,
+                    ongoing: true,
+                }
+            ]
+        );
+
+        // Without an interval, readings stand until the next one
+        let excursions = Excursion::detect(minutes(60), None, &allowed_range, &readings);
+        assert_eq!(excursions[1].end, minutes(35));
+        assert_eq!(excursions[1].area_under_threshold, 1.0 * 5.0);
+
+        // An interval reaching beyond any date leaves readings standing until the next one, and
+        // the last until the end
+        let excursions = Excursion::detect(
+            minutes(60),
+            Some(Duration::milliseconds(i64::MAX)),
+            &allowed_range,
+            &readings,
+        );
+        assert_eq!(excursions[0].end, minutes(15));
+        assert_eq!(excursions[1].end, minutes(60));
+        assert_eq!(
+            covered_until(
+                &readings,
+                readings.len() - 1,
+                Some(Duration::milliseconds(i64::MAX)),
+                minutes(60)
+            ),
+            minutes(60)
+        );
+    }
+}
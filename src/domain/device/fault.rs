@@ -0,0 +1,228 @@
+use std::collections::BTreeMap;
+use std::ops::RangeInclusive;
+
+use chrono::{DateTime, Duration, Utc};
+use serde::Serialize;
+
+use super::anomaly::FLATLINE_LENGTH;
+use super::{DeviceTemperature, MeasurementChannel};
+
+// A flatline only means the sensor stopped measuring once it lasts long enough too. Loggers
+// reporting every few seconds may well repeat themselves for minutes.
+pub const STUCK_MIN_MINUTES: i64 = 60;
+// Coarser codecs don't show the noise, so a steady fridge repeats itself in whole degrees. Such
+// runs are only flagged as `AnomalyFlag::Flatline`.
+pub const STUCK_MAX_RESOLUTION: f32 = 0.1;
+
+// Why a reading doesn't measure anything. Faulty readings are kept, but left out of statistics.
+#[derive(Clone, Copy, Serialize, Debug, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(rename_all = "camelCase")]
+pub enum SensorFault {
+    // What loggers send for a disconnected sensor, see `PayloadCodec::is_sentinel`
+    SentinelCode,
+    // Part of a long run of identical raw temperatures, see `FLATLINE_LENGTH`
+    Stuck,
+    // Beyond what the sensor can measure
+    OutOfPhysicalRange,
+}
+
+// Raw values a sensor of the channel can measure at all. The temperature range is the widest of
+// the probes in use, from ultra-low freezers to reefer exhausts.
+pub fn physical_range_of(channel: MeasurementChannel) -> Option<RangeInclusive<f32>> {
+    match channel {
+        MeasurementChannel::Temperature => Some(-90.0..=125.0),
+        MeasurementChannel::Humidity => Some(0.0..=100.0),
+        MeasurementChannel::BatteryVoltage | MeasurementChannel::DoorState => None,
+    }
+}
+
+// `preceding` are the readings of the same channel taken before `reading`, latest first. Sentinel
+// codes are told by the codec when decoding, so aren't classified here. `resolution` is the one
+// of the codec `reading` was decoded with, unknown for imported readings which are then never
+// taken as stuck.
+pub(crate) fn classify<'a>(
+    reading: &DeviceTemperature,
+    resolution: Option<f32>,
+    preceding: impl Iterator<Item = &'a DeviceTemperature>,
+) -> Option<SensorFault> {
+    if matches!(physical_range_of(reading.channel), Some(range) if !range.contains(&reading.raw_value))
+    {
+        return Some(SensorFault::OutOfPhysicalRange);
+    }
+    // A door staying shut or a battery holding its voltage is no fault
+    if reading.channel == MeasurementChannel::Temperature
+        && matches!(resolution, Some(resolution) if resolution <= STUCK_MAX_RESOLUTION)
+        && preceding
+            .take_while(|temp| temp.raw_value == reading.raw_value)
+            .enumerate()
+            .any(|(index, temp)| {
+                index + 2 >= FLATLINE_LENGTH
+                    && reading.checked_at - temp.checked_at >= Duration::minutes(STUCK_MIN_MINUTES)
+            })
+    {
+        return Some(SensorFault::Stuck);
+    }
+    None
+}
+
+#[derive(Clone, Serialize, Debug, PartialEq)]
+pub struct SensorDiagnostics {
+    #[serde(rename = "readingCount")]
+    pub reading_count: usize,
+    #[serde(rename = "faultyReadingCount")]
+    pub faulty_reading_count: usize,
+    // From 0 to 1. 0 without any reading.
+    #[serde(rename = "faultyRatio")]
+    pub faulty_ratio: f32,
+    // Number of faulty readings by fault
+    pub faults: BTreeMap<SensorFault, usize>,
+    #[serde(rename = "lastFaultAt")]
+    pub last_fault_at: Option<DateTime<Utc>>,
+}
+
+impl SensorDiagnostics {
+    // Readings must be in chronological order
+    pub(crate) fn of<'a>(readings: impl Iterator<Item = &'a DeviceTemperature>) -> Self {
+        let mut reading_count = 0;
+        let mut faults = BTreeMap::new();
+        let mut last_fault_at = None;
+        for temp in readings {
+            reading_count += 1;
+            if let Some(fault) = temp.fault {
+                *faults.entry(fault).or_insert(0) += 1;
+                last_fault_at = Some(temp.checked_at);
+            }
+        }
+
+        let faulty_reading_count = faults.values().sum();
+        Self {
+            reading_count,
+            faulty_reading_count,
+            faulty_ratio: match reading_count {
+                0 => 0.0,
+                _ => faulty_reading_count as f32 / reading_count as f32,
+            },
+            faults,
+            last_fault_at,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test_fault {
+    use chrono::{Duration, Utc};
+
+    use crate::domain::device::{DeviceTemperature, MeasurementChannel};
+
+    use super::{classify, SensorDiagnostics, SensorFault, FLATLINE_LENGTH, STUCK_MIN_MINUTES};
+
+    #[test]
+    fn faults_of_reading() {
+        //GIVEN
+        let start = Utc::now() - Duration::days(1);
+        let reading = |channel, minutes: i64, value: f32| {
+            DeviceTemperature::new(1, channel, start + Duration::minutes(minutes), value, None)
+        };
+        let temperature = |minutes, value| reading(MeasurementChannel::Temperature, minutes, value);
+        let stuck = (0..FLATLINE_LENGTH as i64 - 1)
+            .map(|i| temperature(i * 5, 5.0))
+            .collect::<Vec<_>>();
+        let next = FLATLINE_LENGTH as i64 * 5;
+
+        //WHEN
+        let fault_of = |temp: &DeviceTemperature, preceding: &[DeviceTemperature]| {
+            classify(temp, Some(0.1), preceding.iter().rev())
+        };
+
+        //THEN
+        assert_eq!(
+            fault_of(&temperature(next, 5.0), &stuck),
+            Some(SensorFault::Stuck)
+        );
+        assert_eq!(fault_of(&temperature(next, 5.0), &stuck[1..]), None);
+        assert_eq!(fault_of(&temperature(next, 5.1), &stuck), None);
+        // Whole degrees repeat themselves in a steady fridge, and imported readings could too
+        assert_eq!(
+            classify(&temperature(next, 5.0), Some(1.0), stuck.iter().rev()),
+            None
+        );
+        assert_eq!(
+            classify(&temperature(next, 5.0), None, stuck.iter().rev()),
+            None
+        );
+        // Readings every 10 seconds need a longer run than that
+        let seconds = |seconds: i64, value: f32| {
+            DeviceTemperature::new(
+                1,
+                MeasurementChannel::Temperature,
+                start + Duration::seconds(seconds),
+                value,
+                None,
+            )
+        };
+        let fast = (0..STUCK_MIN_MINUTES * 6)
+            .map(|i| seconds(i * 10, 5.0))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            fault_of(&fast[FLATLINE_LENGTH - 1], &fast[..FLATLINE_LENGTH - 1]),
+            None
+        );
+        assert_eq!(
+            fault_of(&seconds(STUCK_MIN_MINUTES * 60, 5.0), &fast),
+            Some(SensorFault::Stuck)
+        );
+        assert_eq!(
+            fault_of(&temperature(next, 3276.7), &stuck),
+            Some(SensorFault::OutOfPhysicalRange)
+        );
+        assert_eq!(
+            fault_of(&reading(MeasurementChannel::Humidity, 0, 120.0), &[]),
+            Some(SensorFault::OutOfPhysicalRange)
+        );
+        // A door shut for hours
+        let shut = (0..FLATLINE_LENGTH as i64)
+            .map(|i| reading(MeasurementChannel::DoorState, i * 5, 0.0))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            fault_of(&shut[FLATLINE_LENGTH - 1], &shut[..FLATLINE_LENGTH - 1]),
+            None
+        );
+    }
+
+    #[test]
+    fn diagnostics_of_readings() {
+        //GIVEN
+        let start = Utc::now() - Duration::days(1);
+        let mut readings = (0..8)
+            .map(|i| {
+                DeviceTemperature::new(
+                    1,
+                    MeasurementChannel::Temperature,
+                    start + Duration::minutes(i * 5),
+                    5.0,
+                    None,
+                )
+            })
+            .collect::<Vec<_>>();
+        readings[2].fault = Some(SensorFault::SentinelCode);
+        readings[3].fault = Some(SensorFault::SentinelCode);
+        readings[6].fault = Some(SensorFault::OutOfPhysicalRange);
+
+        //WHEN
+        let diagnostics = SensorDiagnostics::of(readings.iter());
+
+        //THEN
+        assert_eq!(diagnostics.reading_count, 8);
+        assert_eq!(diagnostics.faulty_reading_count, 3);
+        assert_eq!(diagnostics.faulty_ratio, 3.0 / 8.0);
+        assert_eq!(
+            diagnostics.faults.into_iter().collect::<Vec<_>>(),
+            vec![
+                (SensorFault::SentinelCode, 2),
+                (SensorFault::OutOfPhysicalRange, 1)
+            ]
+        );
+        assert_eq!(diagnostics.last_fault_at, Some(readings[6].checked_at));
+        assert_eq!(SensorDiagnostics::of([].iter()).faulty_ratio, 0.0);
+    }
+}
@@ -1,14 +1,42 @@
+pub mod anomaly;
+pub mod calibration;
+pub mod codec;
 pub mod commands;
+pub mod compliance;
+pub mod excursion;
+pub mod fault;
 pub mod query;
 pub mod repository;
-use crate::domain::response::Error;
+pub mod statistics;
+pub mod trend;
+use std::collections::{BTreeMap, BTreeSet, HashSet};
+
+use crate::domain::response::{Error, ImportRowError, Response};
 use chrono::DateTime;
 use chrono::Duration;
 use chrono::Utc;
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
 
+use self::anomaly::{AnomalyFlag, FlaggedReading};
+use self::calibration::CalibrationProfile;
+use self::codec::PayloadCodec;
+use self::commands::CalibrateDevice;
+use self::commands::ImportedReading;
 use self::commands::RegisterDevice;
 use self::commands::SaveDeviceTemperature;
+use self::commands::UpdateDeviceMetadata;
+use self::compliance::ComplianceReport;
+use self::excursion::{covered_until, Excursion};
+use self::fault::{SensorDiagnostics, SensorFault};
+use self::query::GetDeviceGroupComplianceReportQuery;
+use self::statistics::{
+    mean_kinetic_temperature_of, ReadingGap, ReadingWeighting, SmoothedReading, Smoothing,
+    TemperatureBucket, TemperatureStatistics,
+};
+use self::trend::TemperatureTrend;
+use super::alert::Breach;
+use super::device_group::alert_rule::AlertRule;
+use super::device_group::AllowedTemperatureRange;
 
 #[derive(Default, Clone, Serialize, Debug)]
 pub struct DeviceAggregate {
@@ -20,97 +48,946 @@ pub struct DeviceAggregate {
     pub serial_number: String,
     #[serde(rename = "createdAt")]
     pub created_at: DateTime<Utc>,
+    pub status: DeviceStatus,
+    #[serde(rename = "groupMemberships")]
+    pub group_memberships: Vec<DeviceGroupMembership>,
+    pub metadata: BTreeMap<String, String>,
+    pub tags: BTreeSet<String>,
+    pub calibrations: Vec<CalibrationProfile>,
+    // Device models sharing a payload format get a codec through the model, which a device
+    // specific `codec` overrides.
+    pub model: Option<String>,
+    pub codec: Option<PayloadCodec>,
+    // Expected cadence in seconds, as declared by the latest batch
+    #[serde(rename = "reportingInterval")]
+    pub reporting_interval: Option<i64>,
+    // When the device last uploaded a batch itself. Imported readings don't count.
+    #[serde(rename = "lastIngestedAt")]
+    pub last_ingested_at: Option<DateTime<Utc>>,
+    // The latest `checked_at` among the saved readings
+    #[serde(rename = "lastReadingAt")]
+    pub last_reading_at: Option<DateTime<Utc>>,
+    pub connectivity: DeviceConnectivity,
 
+    // Ordered by `checked_at`
     #[serde(skip_serializing)]
     pub temperatures: Vec<DeviceTemperature>,
 }
 
+#[derive(Clone, Serialize, Debug)]
+pub struct DeviceGroupMembership {
+    #[serde(rename = "deviceGroupSerialNumber")]
+    pub device_group_serial_number: String,
+    // `None` for the group a device is registered to, so readings taken before the registration
+    // (backfill) are still attributed to it.
+    #[serde(rename = "joinedAt")]
+    pub joined_at: Option<DateTime<Utc>>,
+    #[serde(rename = "leftAt")]
+    pub left_at: Option<DateTime<Utc>>,
+}
+
+impl DeviceGroupMembership {
+    fn covers(&self, device_group_serial: &str, at: DateTime<Utc>) -> bool {
+        self.device_group_serial_number == device_group_serial
+            && !matches!(self.joined_at, Some(joined_at) if at < joined_at)
+            && !matches!(self.left_at, Some(left_at) if left_at <= at)
+    }
+}
+
+// Active <-> Suspended -> Decommissioned
+// Decommissioned is terminal; a retired sensor has to be registered again under a new serial.
+#[derive(Default, Clone, Copy, Serialize, Debug, PartialEq, Eq)]
+pub enum DeviceStatus {
+    #[default]
+    Active,
+    Suspended,
+    Decommissioned,
+}
+
+// Set back to Online as soon as the device uploads again
+#[derive(Default, Clone, Copy, Serialize, Debug, PartialEq, Eq)]
+pub enum DeviceConnectivity {
+    #[default]
+    Online,
+    Offline,
+}
+
+// Devices currently in a group, by connectivity. Only active devices are expected to report,
+// so the others are counted as neither online nor offline.
+#[derive(Default, Clone, Serialize, Debug, PartialEq)]
+pub struct DeviceCounts {
+    #[serde(rename = "deviceCount")]
+    pub total: usize,
+    #[serde(rename = "onlineCount")]
+    pub online: usize,
+    #[serde(rename = "offlineCount")]
+    pub offline: usize,
+}
+
+impl<'a> FromIterator<&'a DeviceAggregate> for DeviceCounts {
+    fn from_iter<I: IntoIterator<Item = &'a DeviceAggregate>>(devices: I) -> Self {
+        devices
+            .into_iter()
+            .fold(Self::default(), |mut counts, device| {
+                counts.total += 1;
+                match (device.status, device.connectivity) {
+                    (DeviceStatus::Active, DeviceConnectivity::Online) => counts.online += 1,
+                    (DeviceStatus::Active, DeviceConnectivity::Offline) => counts.offline += 1,
+                    _ => {}
+                }
+                counts
+            })
+    }
+}
+
 impl DeviceAggregate {
     pub fn new(cmd: RegisterDevice) -> Self {
         Self {
+            group_memberships: vec![DeviceGroupMembership {
+                device_group_serial_number: cmd.device_group_serial.clone(),
+                joined_at: None,
+                left_at: None,
+            }],
             device_group_serial_number: cmd.device_group_serial,
             serial_number: cmd.serial_number,
             created_at: Utc::now(),
+            metadata: cmd.metadata,
+            tags: cmd.tags,
+            model: cmd.model,
             ..Default::default()
         }
     }
 
-    // ? How are you going to make sure of idempotency
-    pub fn save_temperatures(&mut self, cmd: SaveDeviceTemperature) -> Result<(), Error> {
+    pub fn update_metadata(&mut self, cmd: UpdateDeviceMetadata) {
+        for (key, value) in cmd.metadata {
+            match value {
+                Some(value) => self.metadata.insert(key, value),
+                None => self.metadata.remove(&key),
+            };
+        }
+        self.tags.extend(cmd.add_tags);
+        self.tags.retain(|tag| !cmd.remove_tags.contains(tag));
+    }
+
+    // A device matches when it carries every one of the given tags
+    pub fn has_tags(&self, tags: &[String]) -> bool {
+        tags.iter().all(|tag| self.tags.contains(tag))
+    }
+
+    // Closes the current membership and opens a new one so that history stays with the device.
+    // Only active devices are moved between groups
+    pub fn transfer(&mut self, device_group_serial: String) -> Result<(), Error> {
+        if self.status != DeviceStatus::Active {
+            return Err(Error::DeviceNotActive);
+        }
+        if self.device_group_serial_number == device_group_serial {
+            return Ok(());
+        }
+
+        let now = Utc::now();
+        if let Some(current) = self
+            .group_memberships
+            .iter_mut()
+            .find(|membership| membership.left_at.is_none())
+        {
+            current.left_at = Some(now);
+        }
+        self.group_memberships.push(DeviceGroupMembership {
+            device_group_serial_number: device_group_serial.clone(),
+            joined_at: Some(now),
+            left_at: None,
+        });
+        self.device_group_serial_number = device_group_serial;
+        Ok(())
+    }
+
+    pub fn has_been_member_of(&self, device_group_serial: &str) -> bool {
+        self.device_group_serial_number == device_group_serial
+            || self
+                .group_memberships
+                .iter()
+                .any(|membership| membership.device_group_serial_number == device_group_serial)
+    }
+
+    pub fn set_codec(&mut self, codec: Option<PayloadCodec>) -> Result<(), Error> {
+        if let Some(codec) = &codec {
+            codec.validate()?;
+        }
+        self.codec = codec;
+        Ok(())
+    }
+
+    // Device specific codec first, then the one of its model and the default format at last
+    pub fn resolve_codec(&self, model_codec: Option<PayloadCodec>) -> PayloadCodec {
+        self.codec.or(model_codec).unwrap_or_default()
+    }
+
+    // Readings are identified by channel and `checked_at` within a device, so a re-sent batch is
+    // decoded as usual but every reading already stored is skipped rather than saved twice.
+    //
+    // Each frame in `temperatures` carries one value per channel, in the order of `channels`,
+    // and frames are `interval` seconds apart. See `resolve_codec` for how values are decoded.
+    pub fn save_temperatures(
+        &mut self,
+        cmd: SaveDeviceTemperature,
+        model_codec: Option<PayloadCodec>,
+    ) -> Result<Response, Error> {
+        self.ingest_temperatures(cmd, model_codec)
+            .map(|(response, _)| response)
+    }
+
+    // Same as `save_temperatures`, also returning the readings saved, in chronological order
+    pub fn ingest_temperatures(
+        &mut self,
+        cmd: SaveDeviceTemperature,
+        model_codec: Option<PayloadCodec>,
+    ) -> Result<(Response, Vec<DeviceTemperature>), Error> {
+        if self.status != DeviceStatus::Active {
+            return Err(Error::DeviceNotActive);
+        }
+        let channel_cnt = cmd.channels.len();
+        if channel_cnt == 0 || cmd.channels.iter().collect::<HashSet<_>>().len() != channel_cnt {
+            eprintln!("[ERROR] Channels must be given without duplicates");
+            return Err(Error::SchemaError);
+        }
+        let codec = self.resolve_codec(model_codec);
+        let hex_width = codec.hex_width();
+        if !cmd.temperatures.is_ascii()
+            || !cmd
+                .temperatures
+                .len()
+                .is_multiple_of(hex_width * channel_cnt)
+        {
+            eprintln!("[ERROR] Incomplete frame given");
+            return Err(Error::ConversionFailed);
+        }
+
         // To prevent frequent allocation
-        let mut temperatures =
-            Vec::with_capacity(f64::ceil(cmd.temperatures.len() as f64 / 4.0) as usize);
+        let mut temperatures = Vec::with_capacity(cmd.temperatures.len() / hex_width);
 
         let mut loop_cnt = 0;
-        while loop_cnt * 4 < cmd.temperatures.len() {
-            let chunk: String = cmd
-                .temperatures
-                .chars()
-                .skip(loop_cnt * 4)
-                .take(4)
-                .collect();
-            temperatures.push(DeviceTemperature::new(
+        while loop_cnt * hex_width < cmd.temperatures.len() {
+            let chunk = &cmd.temperatures[loop_cnt * hex_width..(loop_cnt + 1) * hex_width];
+            let channel = cmd.channels[loop_cnt % channel_cnt];
+            let checked_at = cmd
+                .interval
+                .checked_mul((loop_cnt / channel_cnt) as i64)
+                .and_then(Duration::try_seconds)
+                .and_then(|offset| cmd.registered_at.checked_add_signed(offset))
+                .ok_or_else(|| {
+                    eprintln!("[ERROR] Reading time out of range");
+                    Error::SchemaError
+                })?;
+            let integer = codec.decode_integer(chunk)?;
+            let mut temperature = DeviceTemperature::new(
                 self.device_id,
-                cmd.registered_at + Duration::seconds(cmd.interval) * loop_cnt as i32,
-                &chunk.to_string(),
-            )?);
+                channel,
+                checked_at,
+                codec.scale(integer),
+                self.calibration_for(channel, checked_at),
+            );
+            if codec.is_sentinel(integer) {
+                temperature.fault = Some(SensorFault::SentinelCode);
+            }
+            temperatures.push(temperature);
             loop_cnt += 1;
         }
-        self.temperatures.extend(temperatures);
+
+        if cmd.interval > 0 {
+            self.reporting_interval = Some(cmd.interval);
+        }
+        self.last_ingested_at = Some(Utc::now());
+        self.connectivity = DeviceConnectivity::Online;
+        let (saved, skipped) = self.store_temperatures(temperatures, Some(codec.resolution()));
+        Ok((
+            Response::TemperaturesSaved {
+                saved: saved.len(),
+                skipped,
+            },
+            saved,
+        ))
+    }
+
+    // Every row is validated before any is stored, so the valid rows of a device are stored all
+    // together in a single update. Returns how many were imported and skipped, and the rejected rows.
+    pub fn import_readings(
+        &mut self,
+        readings: Vec<ImportedReading>,
+    ) -> Result<(usize, usize, Vec<ImportRowError>), Error> {
+        if self.status != DeviceStatus::Active {
+            return Err(Error::DeviceNotActive);
+        }
+
+        let mut errors = vec![];
+        let mut temperatures = Vec::with_capacity(readings.len());
+        for reading in readings {
+            if !reading.value.is_finite() {
+                errors.push(ImportRowError {
+                    line: reading.line,
+                    error: Error::ConversionFailed,
+                });
+                continue;
+            }
+            temperatures.push(DeviceTemperature::new(
+                self.device_id,
+                reading.channel,
+                reading.checked_at,
+                reading.value,
+                self.calibration_for(reading.channel, reading.checked_at),
+            ));
+        }
+
+        let (imported, skipped) = self.store_temperatures(temperatures, None);
+        Ok((imported.len(), skipped, errors))
+    }
+
+    // Calibration certificates only cover the temperature sensor
+    fn calibration_for(
+        &self,
+        channel: MeasurementChannel,
+        checked_at: DateTime<Utc>,
+    ) -> Option<&CalibrationProfile> {
+        match channel {
+            MeasurementChannel::Temperature => self.calibration_at(checked_at),
+            _ => None,
+        }
+    }
+
+    // Readings are kept ordered by `checked_at` however late or overlapping a batch arrives.
+    // On overlap the first write wins: a reading for a channel and time already saved is skipped,
+    // as is a repeat of it within the same batch, so retried uploads never rewrite history.
+    // Returns the readings saved and how many were skipped.
+    fn store_temperatures(
+        &mut self,
+        mut temperatures: Vec<DeviceTemperature>,
+        resolution: Option<f32>,
+    ) -> (Vec<DeviceTemperature>, usize) {
+        let total = temperatures.len();
+        // Stable, so the first of the readings taken at the same time in a batch stays first
+        temperatures.sort_by_key(|temp| temp.checked_at);
+
+        let mut checked = HashSet::new();
+        temperatures.retain(|temp| {
+            checked.insert((temp.channel, temp.checked_at))
+                && !self
+                    .temperatures_at(temp.checked_at)
+                    .iter()
+                    .any(|saved| saved.channel == temp.channel)
+        });
+        let mut saved = temperatures.clone();
+
+        // Live ingestion only appends. Backfills are merged in a single pass.
+        match (self.temperatures.last(), temperatures.first()) {
+            (Some(last), Some(first)) if first.checked_at < last.checked_at => {
+                let mut merged = Vec::with_capacity(self.temperatures.len() + saved.len());
+                let mut existing = std::mem::take(&mut self.temperatures)
+                    .into_iter()
+                    .peekable();
+                for temp in temperatures {
+                    while let Some(earlier) =
+                        existing.next_if(|saved| saved.checked_at <= temp.checked_at)
+                    {
+                        merged.push(earlier);
+                    }
+                    merged.push(temp);
+                }
+                merged.extend(existing);
+                self.temperatures = merged;
+            }
+            _ => self.temperatures.extend(temperatures),
+        }
+        self.screen_readings(&mut saved, resolution);
+        self.last_reading_at = self.temperatures.last().map(|temp| temp.checked_at);
+        let skipped = total - saved.len();
+        (saved, skipped)
+    }
+
+    // Classifies the faults of the readings just saved, then screens the temperatures among them
+    // for anomalies. Both go by the readings taken before, in chronological order so each reading
+    // is screened knowing how the previous ones were. Readings saved earlier are left as they
+    // were even when a backfill lands before them. See `fault::classify` for `resolution`.
+    fn screen_readings(&mut self, saved: &mut [DeviceTemperature], resolution: Option<f32>) {
+        for temp in saved.iter_mut() {
+            let start = self
+                .temperatures
+                .partition_point(|stored| stored.checked_at < temp.checked_at);
+            let Some(idx) = self.temperatures[start..]
+                .iter()
+                .position(|stored| stored.channel == temp.channel)
+                .map(|offset| start + offset)
+            else {
+                continue;
+            };
+            let preceding = self.temperatures[..idx]
+                .iter()
+                .rev()
+                .filter(|stored| stored.channel == temp.channel);
+            temp.fault = temp
+                .fault
+                .or_else(|| fault::classify(temp, resolution, preceding.clone()));
+            if temp.fault.is_none() && temp.channel == MeasurementChannel::Temperature {
+                temp.anomalies =
+                    anomaly::detect(temp, preceding.filter(|stored| !stored.is_faulty()));
+            }
+            self.temperatures[idx].fault = temp.fault;
+            self.temperatures[idx].anomalies = temp.anomalies.clone();
+        }
+    }
+
+    fn temperatures_at(&self, checked_at: DateTime<Utc>) -> &[DeviceTemperature] {
+        let start = self
+            .temperatures
+            .partition_point(|temp| temp.checked_at < checked_at);
+        let end = self
+            .temperatures
+            .partition_point(|temp| temp.checked_at <= checked_at);
+        &self.temperatures[start..end]
+    }
+
+    // Applies to readings saved from now on and checked at or after `valid_from`. Readings already
+    // saved keep the version they were corrected with, so past reports stay reproducible.
+    pub fn calibrate(&mut self, cmd: CalibrateDevice) -> Result<CalibrationProfile, Error> {
+        if !cmd.offset.is_finite() || !cmd.multiplier.is_finite() || cmd.multiplier == 0.0 {
+            eprintln!("[ERROR] Invalid calibration given");
+            return Err(Error::SchemaError);
+        }
+
+        let profile = CalibrationProfile {
+            version: self.calibrations.len() as u32 + 1,
+            offset: cmd.offset,
+            multiplier: cmd.multiplier,
+            valid_from: cmd.valid_from,
+            created_at: Utc::now(),
+        };
+        self.calibrations.push(profile.clone());
+        Ok(profile)
+    }
+
+    // The latest version among the ones already valid at the given time
+    pub fn calibration_at(&self, checked_at: DateTime<Utc>) -> Option<&CalibrationProfile> {
+        self.calibrations
+            .iter()
+            .filter(|profile| profile.valid_from <= checked_at)
+            .max_by_key(|profile| profile.version)
+    }
+
+    // An active device goes offline when it hasn't uploaded for `interval_multiple` reporting
+    // intervals. Without an upload yet, there is no interval to go by.
+    // Returns whether it has just gone offline.
+    pub fn detect_offline(&mut self, now: DateTime<Utc>, interval_multiple: u32) -> bool {
+        let (Some(interval), Some(last_ingested_at)) =
+            (self.reporting_interval, self.last_ingested_at)
+        else {
+            return false;
+        };
+        // Silence too long to represent is never exceeded
+        let Some(allowed_silence) = interval
+            .checked_mul(i64::from(interval_multiple))
+            .and_then(Duration::try_seconds)
+        else {
+            return false;
+        };
+        if self.status != DeviceStatus::Active
+            || self.connectivity == DeviceConnectivity::Offline
+            || now - last_ingested_at <= allowed_silence
+        {
+            return false;
+        }
+        self.connectivity = DeviceConnectivity::Offline;
+        true
+    }
+
+    pub fn suspend(&mut self) -> Result<(), Error> {
+        self.transit_to(DeviceStatus::Suspended, &[DeviceStatus::Active])
+    }
+
+    pub fn reactivate(&mut self) -> Result<(), Error> {
+        self.transit_to(DeviceStatus::Active, &[DeviceStatus::Suspended])
+    }
+
+    pub fn decommission(&mut self) -> Result<(), Error> {
+        self.transit_to(
+            DeviceStatus::Decommissioned,
+            &[DeviceStatus::Active, DeviceStatus::Suspended],
+        )
+    }
+
+    fn transit_to(&mut self, to: DeviceStatus, allowed_from: &[DeviceStatus]) -> Result<(), Error> {
+        if !allowed_from.contains(&self.status) {
+            eprintln!(
+                "[ERROR] Device cannot go from {:?} to {:?}",
+                self.status, to
+            );
+            return Err(Error::InvalidStatusTransition);
+        }
+        self.status = to;
         Ok(())
     }
 
     pub fn get_average_temperature_during_period(
         &self,
+        channel: MeasurementChannel,
+        start_date: DateTime<Utc>,
+        end_date: DateTime<Utc>,
+        exclude_flagged: bool,
+    ) -> f32 {
+        Self::average_of(self.temperatures_during_period(
+            channel,
+            start_date,
+            end_date,
+            exclude_flagged,
+        ))
+    }
+
+    // Only readings taken while the device belonged to the given group are counted
+    pub fn get_average_temperature_in_group_during_period(
+        &self,
+        device_group_serial: &str,
+        channel: MeasurementChannel,
+        start_date: DateTime<Utc>,
+        end_date: DateTime<Utc>,
+        exclude_flagged: bool,
+    ) -> f32 {
+        Self::average_of(self.temperatures_in_group_during_period(
+            device_group_serial,
+            channel,
+            start_date,
+            end_date,
+            exclude_flagged,
+        ))
+    }
+
+    fn average_of<'a>(temperatures: impl Iterator<Item = &'a DeviceTemperature>) -> f32 {
+        let temperature_in_range = temperatures.collect::<Vec<_>>();
+
+        let average: f32 = temperature_in_range.iter().map(|f| f.value).sum::<f32>()
+            / temperature_in_range.len() as f32;
+
+        average
+    }
+
+    // The activation energy is in kJ/mol
+    pub fn get_mean_kinetic_temperature_during_period(
+        &self,
+        channel: MeasurementChannel,
+        start_date: DateTime<Utc>,
+        end_date: DateTime<Utc>,
+        activation_energy: f32,
+        exclude_flagged: bool,
+    ) -> f32 {
+        Self::mean_kinetic_of(
+            self.temperatures_during_period(channel, start_date, end_date, exclude_flagged),
+            activation_energy,
+        )
+    }
+
+    // Only readings taken while the device belonged to the given group are counted
+    pub fn get_mean_kinetic_temperature_in_group_during_period(
+        &self,
+        device_group_serial: &str,
+        channel: MeasurementChannel,
         start_date: DateTime<Utc>,
         end_date: DateTime<Utc>,
+        activation_energy: f32,
+        exclude_flagged: bool,
     ) -> f32 {
-        let temperature_in_range = self
+        Self::mean_kinetic_of(
+            self.temperatures_in_group_during_period(
+                device_group_serial,
+                channel,
+                start_date,
+                end_date,
+                exclude_flagged,
+            ),
+            activation_energy,
+        )
+    }
+
+    fn mean_kinetic_of<'a>(
+        temperatures: impl Iterator<Item = &'a DeviceTemperature>,
+        activation_energy: f32,
+    ) -> f32 {
+        mean_kinetic_temperature_of(
+            &temperatures.map(|temp| temp.value).collect::<Vec<_>>(),
+            activation_energy,
+        )
+    }
+
+    // Each value with the seconds it stands for, see `covered_until`, as `GroupTemperature::of`
+    // takes them. Only time weighting goes by those, so the others get 0 without working them
+    // out. Without a reporting interval the last reading stands until the end of the period
+    // rather than for no time. Only readings taken while the device belonged to the given group
+    // are counted, and they stand for no time past the present.
+    pub fn get_weighted_temperatures_in_group_during_period(
+        &self,
+        device_group_serial: &str,
+        channel: MeasurementChannel,
+        start_date: DateTime<Utc>,
+        end_date: DateTime<Utc>,
+        weighting: ReadingWeighting,
+        exclude_flagged: bool,
+    ) -> Vec<(f32, i64)> {
+        let temperatures = self.temperatures_in_group_during_period(
+            device_group_serial,
+            channel,
+            start_date,
+            end_date,
+            exclude_flagged,
+        );
+        if weighting != ReadingWeighting::TimeWeighted {
+            return temperatures.map(|temp| (temp.value, 0)).collect();
+        }
+
+        let readings = temperatures
+            .map(|temp| (temp.checked_at, temp.value))
+            .collect::<Vec<_>>();
+        let interval = self.reporting_duration();
+        let end_date = end_date.min(Utc::now());
+        readings
+            .iter()
+            .enumerate()
+            .map(|(index, &(checked_at, value))| {
+                let covered_until = match interval {
+                    None if index + 1 == readings.len() => end_date.max(checked_at),
+                    _ => covered_until(&readings, index, interval, end_date),
+                };
+                (value, (covered_until - checked_at).num_seconds())
+            })
+            .collect()
+    }
+
+    pub fn get_temperature_statistics_during_period(
+        &self,
+        channel: MeasurementChannel,
+        start_date: DateTime<Utc>,
+        end_date: DateTime<Utc>,
+        percentiles: &[f32],
+        exclude_flagged: bool,
+    ) -> TemperatureStatistics {
+        TemperatureStatistics::from_values(
+            self.temperatures_during_period(channel, start_date, end_date, exclude_flagged)
+                .map(|temp| temp.value)
+                .collect(),
+            percentiles,
+        )
+    }
+
+    pub fn get_temperature_series_during_period(
+        &self,
+        channel: MeasurementChannel,
+        start_date: DateTime<Utc>,
+        end_date: DateTime<Utc>,
+        bucket_size: Duration,
+        exclude_flagged: bool,
+    ) -> Vec<TemperatureBucket> {
+        TemperatureBucket::series(
+            start_date,
+            bucket_size,
+            self.temperatures_during_period(channel, start_date, end_date, exclude_flagged)
+                .map(|temp| (temp.checked_at, temp.value)),
+        )
+    }
+
+    pub fn get_smoothed_temperatures_during_period(
+        &self,
+        channel: MeasurementChannel,
+        start_date: DateTime<Utc>,
+        end_date: DateTime<Utc>,
+        smoothing: Smoothing,
+        exclude_flagged: bool,
+    ) -> Vec<SmoothedReading> {
+        SmoothedReading::series(
+            smoothing,
+            self.temperatures_during_period(channel, start_date, end_date, exclude_flagged)
+                .map(|temp| (temp.checked_at, temp.value)),
+        )
+    }
+
+    // Fitted over the latest readings of the channel however old they are, and projected from now
+    pub fn get_temperature_trend(
+        &self,
+        channel: MeasurementChannel,
+        reading_count: usize,
+        horizon: Duration,
+        threshold: Option<f32>,
+        exclude_flagged: bool,
+    ) -> Option<TemperatureTrend> {
+        let mut readings = self
+            .temperatures
+            .iter()
+            .rev()
+            .filter(|temp| temp.channel == channel && temp.is_usable(exclude_flagged))
+            .take(reading_count)
+            .map(|temp| (temp.checked_at, temp.value))
+            .collect::<Vec<_>>();
+        readings.reverse();
+        TemperatureTrend::fit(&readings, Utc::now(), horizon, threshold)
+    }
+
+    // Periods without readings relative to the reporting interval. A device that never declared
+    // one has no expected cadence, hence no gaps. The period is cut at the present.
+    pub fn get_gaps_during_period(
+        &self,
+        channel: MeasurementChannel,
+        start_date: DateTime<Utc>,
+        end_date: DateTime<Utc>,
+    ) -> Vec<ReadingGap> {
+        let Some(interval) = self.reporting_duration() else {
+            return vec![];
+        };
+        let end_date = end_date.min(Utc::now());
+        ReadingGap::detect(
+            start_date,
+            end_date,
+            interval,
+            self.readings_during_period(channel, start_date, end_date)
+                .map(|temp| temp.checked_at),
+        )
+    }
+
+    // The period is cut at the present. An excursion going on at its start is cut there too.
+    pub fn get_excursions_during_period(
+        &self,
+        channel: MeasurementChannel,
+        start_date: DateTime<Utc>,
+        end_date: DateTime<Utc>,
+        allowed_range: &AllowedTemperatureRange,
+        exclude_flagged: bool,
+    ) -> Vec<Excursion> {
+        let end_date = end_date.min(Utc::now());
+        let readings = self
+            .temperatures_during_period(channel, start_date, end_date, exclude_flagged)
+            .map(|temp| (temp.checked_at, temp.value))
+            .collect::<Vec<_>>();
+        Excursion::detect(
+            end_date,
+            self.reporting_duration(),
+            allowed_range,
+            &readings,
+        )
+    }
+
+    // Only readings taken while the device belonged to the given group are counted. The period
+    // is cut at the present.
+    pub fn get_compliance_in_group_during_period(
+        &self,
+        query: &GetDeviceGroupComplianceReportQuery,
+        allowed_range: &AllowedTemperatureRange,
+    ) -> ComplianceReport {
+        let end_date = query.end_date.min(Utc::now());
+        ComplianceReport::evaluate(
+            query.start_date,
+            end_date,
+            self.reporting_duration(),
+            allowed_range,
+            query.min_coverage,
+            self.temperatures_in_group_during_period(
+                &query.device_group_serial,
+                query.channel,
+                query.start_date,
+                end_date,
+                query.exclude_flagged,
+            )
+            .map(|temp| (temp.checked_at, temp.value)),
+        )
+    }
+
+    pub fn get_flagged_readings_during_period(
+        &self,
+        start_date: DateTime<Utc>,
+        end_date: DateTime<Utc>,
+    ) -> Vec<FlaggedReading> {
+        self.temperatures_during_period(
+            MeasurementChannel::Temperature,
+            start_date,
+            end_date,
+            false,
+        )
+        .filter(|temp| temp.is_flagged())
+        .map(FlaggedReading::from)
+        .collect()
+    }
+
+    pub fn get_diagnostics_during_period(
+        &self,
+        channel: MeasurementChannel,
+        start_date: DateTime<Utc>,
+        end_date: DateTime<Utc>,
+    ) -> SensorDiagnostics {
+        SensorDiagnostics::of(self.readings_during_period(channel, start_date, end_date))
+    }
+
+    // Gaps within each stretch of the period the device spent in the given group, see
+    // `get_gaps_during_period`
+    pub fn get_gaps_in_group_during_period(
+        &self,
+        device_group_serial: &str,
+        channel: MeasurementChannel,
+        start_date: DateTime<Utc>,
+        end_date: DateTime<Utc>,
+    ) -> Vec<ReadingGap> {
+        self.group_memberships
+            .iter()
+            .filter(|membership| membership.device_group_serial_number == device_group_serial)
+            .flat_map(|membership| {
+                let start_date = membership
+                    .joined_at
+                    .map_or(start_date, |joined_at| joined_at.max(start_date));
+                let end_date = membership
+                    .left_at
+                    .map_or(end_date, |left_at| left_at.min(end_date));
+                self.get_gaps_during_period(channel, start_date, end_date)
+            })
+            .collect()
+    }
+
+    // Excursions beyond the threshold of the rule for more than its duration, among the ones
+    // taking in readings checked at or after `since`. An excursion going on at `since` is
+    // followed back to where it started.
+    pub fn breaches_since(&self, rule: &AlertRule, since: DateTime<Utc>) -> Vec<Breach> {
+        let mut start = self
             .temperatures
+            .partition_point(|temp| temp.checked_at < since);
+        for (idx, temp) in self.temperatures[..start].iter().enumerate().rev() {
+            if temp.channel != rule.channel || temp.is_faulty() {
+                continue;
+            }
+            if !rule.is_breached_by(temp.value) {
+                break;
+            }
+            start = idx;
+        }
+
+        let readings = self.temperatures[start..]
             .iter()
-            .filter(|temp| start_date <= temp.checked_at && temp.checked_at <= end_date)
+            .filter(|temp| temp.channel == rule.channel && !temp.is_faulty())
+            .map(|temp| (temp.checked_at, temp.value))
             .collect::<Vec<_>>();
+        Excursion::detect(
+            DateTime::<Utc>::MAX_UTC,
+            None,
+            &rule.allowed_range(),
+            &readings,
+        )
+        .into_iter()
+        .filter(|excursion| rule.is_sustained_for(excursion.last_out_of_range_at - excursion.start))
+        .map(Breach::from)
+        .collect()
+    }
+
+    // `None` for an interval too long to tell, as if never declared
+    fn reporting_duration(&self) -> Option<Duration> {
+        self.reporting_interval.and_then(Duration::try_seconds)
+    }
+
+    // Faulty readings are always left out, flagged ones on demand
+    fn temperatures_during_period(
+        &self,
+        channel: MeasurementChannel,
+        start_date: DateTime<Utc>,
+        end_date: DateTime<Utc>,
+        exclude_flagged: bool,
+    ) -> impl Iterator<Item = &DeviceTemperature> {
+        self.readings_during_period(channel, start_date, end_date)
+            .filter(move |temp| temp.is_usable(exclude_flagged))
+    }
 
-        let average: f32 = temperature_in_range
+    // Faulty and flagged readings included, as they still tell the device reported
+    fn readings_during_period(
+        &self,
+        channel: MeasurementChannel,
+        start_date: DateTime<Utc>,
+        end_date: DateTime<Utc>,
+    ) -> impl Iterator<Item = &DeviceTemperature> {
+        // Readings are ordered by `checked_at`, so only the ones within the period are visited
+        let start = self
+            .temperatures
+            .partition_point(|temp| temp.checked_at < start_date);
+        let end = self
+            .temperatures
+            .partition_point(|temp| temp.checked_at <= end_date);
+        self.temperatures[start..end.max(start)]
             .iter()
-            .map(|f| f.temperature as f32)
-            .sum::<f32>()
-            / temperature_in_range.len() as f32;
+            .filter(move |temp| temp.channel == channel)
+    }
 
-        average
+    fn temperatures_in_group_during_period<'a>(
+        &'a self,
+        device_group_serial: &'a str,
+        channel: MeasurementChannel,
+        start_date: DateTime<Utc>,
+        end_date: DateTime<Utc>,
+        exclude_flagged: bool,
+    ) -> impl Iterator<Item = &'a DeviceTemperature> {
+        self.temperatures_during_period(channel, start_date, end_date, exclude_flagged)
+            .filter(move |temp| {
+                self.group_memberships
+                    .iter()
+                    .any(|membership| membership.covers(device_group_serial, temp.checked_at))
+            })
     }
 }
 
+#[derive(Default, Clone, Copy, Serialize, Deserialize, Debug, PartialEq, Eq, Hash)]
+#[serde(rename_all = "camelCase")]
+pub enum MeasurementChannel {
+    #[default]
+    Temperature,
+    Humidity,
+    BatteryVoltage,
+    DoorState,
+}
+
+// A single reading of one of the device's measurement channels. Named after the temperature
+// channel, which was the only one there was.
 #[derive(Clone, Debug)]
 pub struct DeviceTemperature {
     pub device_id: i64,
-    pub temperature: i16,
+    pub channel: MeasurementChannel,
+    // Calibrated value. Same as `raw_value` when no calibration applies.
+    pub value: f32,
+    // Decoded and scaled as the sensor reported it
+    pub raw_value: f32,
+    pub calibration_version: Option<u32>,
     pub checked_at: DateTime<Utc>,
+    // Set once when saved
+    pub fault: Option<SensorFault>,
+    // Set once when saved. Empty for faulty readings and the other channels, which aren't
+    // screened.
+    pub anomalies: Vec<AnomalyFlag>,
 }
 
 impl DeviceTemperature {
     pub fn new(
         device_id: i64,
+        channel: MeasurementChannel,
         checked_at: DateTime<Utc>,
-        tempature_in_hex: &str,
-    ) -> Result<Self, Error> {
-        Ok(Self {
+        raw_value: f32,
+        calibration: Option<&CalibrationProfile>,
+    ) -> Self {
+        Self {
             device_id,
+            channel,
             checked_at,
-            temperature: Self::hex_to_decimal(tempature_in_hex)?,
-        })
+            value: calibration.map_or(raw_value, |profile| profile.apply(raw_value)),
+            raw_value,
+            calibration_version: calibration.map(|profile| profile.version),
+            fault: None,
+            anomalies: vec![],
+        }
     }
 
-    pub(crate) fn hex_to_decimal(hex: &str) -> Result<i16, Error> {
-        if hex.len() != 4 {
-            eprintln!("[ERROR] Wrong length given ");
-            return Err(Error::ConversionFailed);
-        }
+    pub fn is_faulty(&self) -> bool {
+        self.fault.is_some()
+    }
+
+    pub fn is_flagged(&self) -> bool {
+        !self.anomalies.is_empty()
+    }
 
-        Ok(i32::from_str_radix(hex, 16).map_err(|err| {
-            eprintln!("[ERROR] Conversion failed {}", err);
-            Error::ConversionFailed
-        })? as i16)
+    // Faulty readings never are, flagged ones on demand
+    fn is_usable(&self, exclude_flagged: bool) -> bool {
+        !(self.is_faulty() || (exclude_flagged && self.is_flagged()))
     }
 }
 
@@ -118,9 +995,25 @@ impl DeviceTemperature {
 mod test_device {
     use chrono::{DateTime, Duration, Utc};
 
-    use crate::domain::device::{DeviceAggregate, DeviceTemperature};
+    use crate::domain::{
+        alert::Breach,
+        device::{
+            anomaly::AnomalyFlag,
+            codec::{Endianness, PayloadCodec},
+            fault::SensorFault,
+            statistics::ReadingWeighting,
+            DeviceAggregate, DeviceConnectivity, DeviceCounts, DeviceStatus, MeasurementChannel,
+        },
+        device_group::alert_rule::{AlertRule, ThresholdCondition},
+        response::{Error, Response},
+    };
+
+    use super::commands::{
+        default_channels, CalibrateDevice, ImportedReading, RegisterDevice, SaveDeviceTemperature,
+        UpdateDeviceMetadata,
+    };
 
-    use super::commands::{RegisterDevice, SaveDeviceTemperature};
+    const TEMP: MeasurementChannel = MeasurementChannel::Temperature;
 
     // Infallible operation which won't return error.
     #[test]
@@ -129,6 +1022,7 @@ mod test_device {
         let cmd = RegisterDevice {
             serial_number: "C48302DDL".to_string(),
             device_group_serial: "A1".to_string(),
+            ..Default::default()
         };
         //WHEN
 
@@ -149,6 +1043,7 @@ mod test_device {
         let cmd = RegisterDevice {
             serial_number: "C48302DDL".to_string(),
             device_group_serial: "A1".to_string(),
+            ..Default::default()
         };
 
         let mut device = DeviceAggregate::new(cmd);
@@ -161,8 +1056,10 @@ mod test_device {
             interval,
             temperatures: "FFFE00010003FFFE00010003FFFE00010003FFFE00010003".to_string(),
             registered_at,
+            channels: default_channels(),
+            idempotency_key: None,
         };
-        device.save_temperatures(cmd).unwrap();
+        device.save_temperatures(cmd, None).unwrap();
 
         //THEN
         assert!(!device.temperatures.is_empty());
@@ -170,9 +1067,9 @@ mod test_device {
             device
                 .temperatures
                 .iter()
-                .map(|t| t.temperature)
+                .map(|t| t.raw_value)
                 .collect::<Vec<_>>(),
-            vec![-2, 1, 3, -2, 1, 3, -2, 1, 3, -2, 1, 3]
+            vec![-2.0, 1.0, 3.0, -2.0, 1.0, 3.0, -2.0, 1.0, 3.0, -2.0, 1.0, 3.0]
         );
         assert_eq!(
             device
@@ -198,18 +1095,1017 @@ mod test_device {
     }
 
     #[test]
-    fn tempeature_conversion() {
+    fn save_temperatures_skips_already_saved_readings() {
         //GIVEN
-        // the following should become -2,1,3,-2,1,3,-2,1,3 ...
-        let hex_temperature = ["FFFE", "0001", "0003"];
+        let mut device = DeviceAggregate::new(RegisterDevice {
+            serial_number: "C48302DDI".to_string(),
+            device_group_serial: "A1".to_string(),
+            ..Default::default()
+        });
+        let registered_at = Utc::now();
+        let cmd = |temperatures: &str, registered_at| SaveDeviceTemperature {
+            serial_number: "C48302DDI".to_string(),
+            interval: 300,
+            temperatures: temperatures.to_string(),
+            registered_at,
+            channels: default_channels(),
+            idempotency_key: None,
+        };
+        device
+            .save_temperatures(cmd("FFFE00010003", registered_at), None)
+            .unwrap();
 
         //WHEN
-        let test_vec = hex_temperature
-            .into_iter()
-            .map(|ele| DeviceTemperature::hex_to_decimal(ele).unwrap())
-            .collect::<Vec<_>>();
+        // the batch is retried, then a batch overlapping the last reading arrives
+        let retried = device
+            .save_temperatures(cmd("FFFE00010003", registered_at), None)
+            .unwrap();
+        let overlapping = device
+            .save_temperatures(
+                cmd("00030005", registered_at + Duration::seconds(600)),
+                None,
+            )
+            .unwrap();
 
         //THEN
-        assert_eq!(test_vec, vec![-2, 1, 3]);
+        assert!(matches!(
+            retried,
+            Response::TemperaturesSaved {
+                saved: 0,
+                skipped: 3
+            }
+        ));
+        assert!(matches!(
+            overlapping,
+            Response::TemperaturesSaved {
+                saved: 1,
+                skipped: 1
+            }
+        ));
+        assert_eq!(
+            device
+                .temperatures
+                .iter()
+                .map(|t| t.value)
+                .collect::<Vec<_>>(),
+            vec![-2.0, 1.0, 3.0, 5.0]
+        );
+    }
+
+    #[test]
+    fn backfilled_temperatures_are_kept_in_order() {
+        //GIVEN
+        let mut device = DeviceAggregate::new(RegisterDevice {
+            serial_number: "C48302DDO".to_string(),
+            device_group_serial: "A1".to_string(),
+            ..Default::default()
+        });
+        let registered_at = Utc::now() - Duration::hours(1);
+        let cmd = |temperatures: &str, registered_at| SaveDeviceTemperature {
+            serial_number: "C48302DDO".to_string(),
+            interval: 300,
+            temperatures: temperatures.to_string(),
+            registered_at,
+            channels: default_channels(),
+            idempotency_key: None,
+        };
+        device
+            .save_temperatures(cmd("00010002", registered_at), None)
+            .unwrap();
+
+        //WHEN
+        // a batch from before the first one, then one overlapping saved readings with other values
+        device
+            .save_temperatures(
+                cmd("0005000600070008", registered_at - Duration::minutes(20)),
+                None,
+            )
+            .unwrap();
+        let overlapping = device
+            .save_temperatures(
+                cmd("0009000A000B000C", registered_at - Duration::minutes(5)),
+                None,
+            )
+            .unwrap();
+
+        //THEN
+        assert!(matches!(
+            overlapping,
+            Response::TemperaturesSaved {
+                saved: 1,
+                skipped: 3
+            }
+        ));
+        assert_eq!(
+            device
+                .temperatures
+                .iter()
+                .map(|t| ((t.checked_at - registered_at).num_minutes(), t.value))
+                .collect::<Vec<_>>(),
+            vec![
+                (-20, 5.0),
+                (-15, 6.0),
+                (-10, 7.0),
+                (-5, 8.0),
+                (0, 1.0),
+                (5, 2.0),
+                (10, 12.0)
+            ]
+        );
+        assert_eq!(
+            device.get_average_temperature_during_period(
+                TEMP,
+                registered_at - Duration::minutes(10),
+                registered_at + Duration::minutes(5),
+                false,
+            ),
+            4.5
+        );
+        assert!(device
+            .get_average_temperature_during_period(
+                TEMP,
+                registered_at + Duration::minutes(5),
+                registered_at - Duration::minutes(5),
+                false,
+            )
+            .is_nan());
+    }
+
+    #[test]
+    fn gaps_follow_reporting_interval() {
+        //GIVEN
+        let mut device = DeviceAggregate::new(RegisterDevice {
+            serial_number: "C48302DDG".to_string(),
+            device_group_serial: "A1".to_string(),
+            ..Default::default()
+        });
+        let registered_at = Utc::now() - Duration::hours(1);
+        let start_date = registered_at - Duration::minutes(10);
+        let end_date = registered_at + Duration::minutes(30);
+        assert!(device
+            .get_gaps_during_period(TEMP, start_date, end_date)
+            .is_empty());
+
+        //WHEN
+        device
+            .save_temperatures(
+                SaveDeviceTemperature {
+                    serial_number: "C48302DDG".to_string(),
+                    interval: 300,
+                    temperatures: "000100020003".to_string(),
+                    registered_at,
+                    channels: default_channels(),
+                    idempotency_key: None,
+                },
+                None,
+            )
+            .unwrap();
+        let gaps = device.get_gaps_during_period(TEMP, start_date, end_date);
+
+        //THEN
+        assert_eq!(device.reporting_interval, Some(300));
+        assert_eq!(
+            gaps.iter()
+                .map(|gap| (gap.start, gap.end, gap.missing_readings))
+                .collect::<Vec<_>>(),
+            vec![
+                (start_date, registered_at, 1),
+                (registered_at + Duration::minutes(10), end_date, 3)
+            ]
+        );
+    }
+
+    #[test]
+    fn offline_detection() {
+        //GIVEN
+        let mut device = DeviceAggregate::new(RegisterDevice {
+            serial_number: "C48302DDN".to_string(),
+            device_group_serial: "A1".to_string(),
+            ..Default::default()
+        });
+        let now = Utc::now();
+        assert!(!device.detect_offline(now + Duration::days(1), 3));
+        let registered_at = now - Duration::days(2);
+        let cmd = SaveDeviceTemperature {
+            serial_number: "C48302DDN".to_string(),
+            interval: 300,
+            temperatures: "00010002".to_string(),
+            registered_at,
+            channels: default_channels(),
+            idempotency_key: None,
+        };
+        device.save_temperatures(cmd, None).unwrap();
+        assert_eq!(
+            device.last_reading_at,
+            Some(registered_at + Duration::seconds(300))
+        );
+
+        //WHEN
+        let within_multiple = device.detect_offline(now + Duration::minutes(14), 3);
+        let within_huge_multiple = device.detect_offline(now + Duration::minutes(16), u32::MAX);
+        let beyond_multiple = device.detect_offline(now + Duration::minutes(16), 3);
+        let already_offline = device.detect_offline(now + Duration::minutes(16), 3);
+
+        //THEN
+        assert!(!within_multiple);
+        assert!(!within_huge_multiple);
+        assert!(beyond_multiple);
+        assert!(!already_offline);
+        assert_eq!(device.connectivity, DeviceConnectivity::Offline);
+        assert_eq!(
+            [&device].into_iter().collect::<DeviceCounts>(),
+            DeviceCounts {
+                total: 1,
+                online: 0,
+                offline: 1
+            }
+        );
+
+        let cmd = SaveDeviceTemperature {
+            serial_number: "C48302DDN".to_string(),
+            interval: 300,
+            temperatures: "0003".to_string(),
+            registered_at: now,
+            channels: default_channels(),
+            idempotency_key: None,
+        };
+        device.save_temperatures(cmd, None).unwrap();
+        assert_eq!(device.connectivity, DeviceConnectivity::Online);
+        assert_eq!(device.last_reading_at, Some(now));
+    }
+
+    #[test]
+    fn breaches_of_alert_rule() {
+        //GIVEN
+        let mut device = DeviceAggregate::new(RegisterDevice {
+            serial_number: "C48302DDR".to_string(),
+            device_group_serial: "A1".to_string(),
+            ..Default::default()
+        });
+        let registered_at = Utc::now() - Duration::hours(2);
+        let cmd = |temperatures: &str, registered_at| SaveDeviceTemperature {
+            serial_number: "C48302DDR".to_string(),
+            interval: 300,
+            temperatures: temperatures.to_string(),
+            registered_at,
+            channels: default_channels(),
+            idempotency_key: None,
+        };
+        // 9 at 5 minutes only, then 9 and 10 from 15 to 35 minutes
+        device
+            .save_temperatures(cmd("00050009000500090009000A00090009", registered_at), None)
+            .unwrap();
+        let above_8_for_15_minutes = AlertRule {
+            rule_id: 1,
+            channel: TEMP,
+            condition: ThresholdCondition::Above,
+            threshold: 8.0,
+            duration_in_secs: 900,
+            created_at: Utc::now(),
+        };
+
+        //WHEN
+        let breaches = device.breaches_since(&above_8_for_15_minutes, registered_at);
+        let going_on_since = device.breaches_since(
+            &above_8_for_15_minutes,
+            registered_at + Duration::minutes(30),
+        );
+        device
+            .save_temperatures(cmd("0002", registered_at + Duration::minutes(40)), None)
+            .unwrap();
+        let ended = device.breaches_since(
+            &above_8_for_15_minutes,
+            registered_at + Duration::minutes(40),
+        );
+
+        //THEN
+        let expected = Breach {
+            started_at: registered_at + Duration::minutes(15),
+            last_breached_at: registered_at + Duration::minutes(35),
+            peak_value: 10.0,
+            ended_at: None,
+        };
+        assert_eq!(breaches, vec![expected.clone()]);
+        assert_eq!(going_on_since, vec![expected.clone()]);
+        assert_eq!(
+            ended,
+            vec![Breach {
+                ended_at: Some(registered_at + Duration::minutes(40)),
+                ..expected
+            }]
+        );
+        // 9 and 10 span exactly 20 minutes
+        let above_8_for_20_minutes = AlertRule {
+            duration_in_secs: 1200,
+            ..above_8_for_15_minutes.clone()
+        };
+        assert!(device
+            .breaches_since(&above_8_for_20_minutes, registered_at)
+            .is_empty());
+        let below_6_for_any_reading = AlertRule {
+            condition: ThresholdCondition::Below,
+            threshold: 6.0,
+            duration_in_secs: 0,
+            ..above_8_for_15_minutes
+        };
+        assert_eq!(
+            device
+                .breaches_since(&below_6_for_any_reading, registered_at)
+                .len(),
+            3
+        );
+    }
+
+    #[test]
+    fn device_lifecycle() {
+        //GIVEN
+        let mut device = DeviceAggregate::new(RegisterDevice {
+            serial_number: "C48302DDL".to_string(),
+            device_group_serial: "A1".to_string(),
+            ..Default::default()
+        });
+        assert_eq!(device.status, DeviceStatus::Active);
+
+        //WHEN
+        device.suspend().unwrap();
+        let suspended_again = device.suspend();
+        device.reactivate().unwrap();
+        device.decommission().unwrap();
+        let reactivated_after_decommission = device.reactivate();
+
+        //THEN
+        assert!(matches!(
+            suspended_again,
+            Err(Error::InvalidStatusTransition)
+        ));
+        assert!(matches!(
+            reactivated_after_decommission,
+            Err(Error::InvalidStatusTransition)
+        ));
+        assert_eq!(device.status, DeviceStatus::Decommissioned);
+    }
+
+    #[test]
+    fn save_temperatures_of_non_active_device() {
+        //GIVEN
+        let mut device = DeviceAggregate::new(RegisterDevice {
+            serial_number: "C48302DDL".to_string(),
+            device_group_serial: "A1".to_string(),
+            ..Default::default()
+        });
+        device.suspend().unwrap();
+
+        //WHEN
+        let res = device.save_temperatures(
+            SaveDeviceTemperature {
+                serial_number: "C48302DDL".to_string(),
+                interval: 300,
+                temperatures: "FFFE".to_string(),
+                registered_at: Utc::now(),
+                channels: default_channels(),
+                idempotency_key: None,
+            },
+            None,
+        );
+
+        //THEN
+        assert!(matches!(res, Err(Error::DeviceNotActive)));
+        assert!(device.temperatures.is_empty());
+    }
+
+    #[test]
+    fn transfer_device() {
+        //GIVEN
+        let mut device = DeviceAggregate::new(RegisterDevice {
+            serial_number: "C48302DDT".to_string(),
+            device_group_serial: "A1".to_string(),
+            ..Default::default()
+        });
+        let registered_at = Utc::now() - Duration::minutes(10);
+        device
+            .save_temperatures(
+                SaveDeviceTemperature {
+                    serial_number: "C48302DDT".to_string(),
+                    interval: 300,
+                    temperatures: "FFFE0001".to_string(),
+                    registered_at,
+                    channels: default_channels(),
+                    idempotency_key: None,
+                },
+                None,
+            )
+            .unwrap();
+
+        //WHEN
+        device.transfer("A2".to_string()).unwrap();
+        device
+            .save_temperatures(
+                SaveDeviceTemperature {
+                    serial_number: "C48302DDT".to_string(),
+                    interval: 300,
+                    temperatures: "00050007".to_string(),
+                    registered_at: Utc::now() + Duration::seconds(1),
+                    channels: default_channels(),
+                    idempotency_key: None,
+                },
+                None,
+            )
+            .unwrap();
+
+        //THEN
+        assert_eq!(device.device_group_serial_number, "A2");
+        assert_eq!(device.group_memberships.len(), 2);
+        assert!(device.has_been_member_of("A1"));
+        let (start_date, end_date) = (registered_at, Utc::now() + Duration::minutes(30));
+        assert_eq!(
+            device.get_average_temperature_in_group_during_period(
+                "A1", TEMP, start_date, end_date, false
+            ),
+            -0.5
+        );
+        assert_eq!(
+            device.get_average_temperature_in_group_during_period(
+                "A2", TEMP, start_date, end_date, false
+            ),
+            6.0
+        );
+        assert_eq!(
+            device.get_average_temperature_during_period(TEMP, start_date, end_date, false),
+            2.75
+        );
+    }
+
+    #[test]
+    fn covered_temperatures_without_reporting_interval() {
+        //GIVEN
+        let mut device = DeviceAggregate::new(RegisterDevice {
+            serial_number: "C48302DDW".to_string(),
+            device_group_serial: "A1".to_string(),
+            ..Default::default()
+        });
+        let checked_at = Utc::now() - Duration::hours(1);
+        let reading = |line, minutes, value| ImportedReading {
+            line,
+            serial_number: "C48302DDW".to_string(),
+            channel: TEMP,
+            checked_at: checked_at + Duration::minutes(minutes),
+            value,
+        };
+        device
+            .import_readings(vec![reading(2, 0, 4.0), reading(3, 10, 6.0)])
+            .unwrap();
+
+        //WHEN
+        let covered = device.get_weighted_temperatures_in_group_during_period(
+            "A1",
+            TEMP,
+            checked_at,
+            checked_at + Duration::minutes(30),
+            ReadingWeighting::TimeWeighted,
+            false,
+        );
+
+        //THEN
+        // Imported readings leave the interval unknown, so the last stands until the period ends
+        assert_eq!(device.reporting_interval, None);
+        assert_eq!(covered, vec![(4.0, 600), (6.0, 1200)]);
+    }
+
+    #[test]
+    fn gaps_in_group() {
+        //GIVEN
+        let mut device = DeviceAggregate::new(RegisterDevice {
+            serial_number: "C48302DDX".to_string(),
+            device_group_serial: "A1".to_string(),
+            ..Default::default()
+        });
+        let registered_at = Utc::now() - Duration::hours(2);
+        device
+            .save_temperatures(
+                SaveDeviceTemperature {
+                    serial_number: "C48302DDX".to_string(),
+                    interval: 300,
+                    temperatures: "000500050005".to_string(),
+                    registered_at,
+                    channels: default_channels(),
+                    idempotency_key: None,
+                },
+                None,
+            )
+            .unwrap();
+
+        //WHEN
+        device.transfer("A2".to_string()).unwrap();
+
+        //THEN
+        let (start_date, end_date) = (registered_at, Utc::now() + Duration::minutes(30));
+        let gaps = device.get_gaps_in_group_during_period("A1", TEMP, start_date, end_date);
+        assert_eq!(gaps.len(), 1);
+        assert_eq!(gaps[0].start, registered_at + Duration::minutes(10));
+        // The silence before the transfer isn't the new group's
+        assert_eq!(
+            device
+                .get_gaps_during_period(TEMP, start_date, end_date)
+                .len(),
+            1
+        );
+        assert!(device
+            .get_gaps_in_group_during_period("A2", TEMP, start_date, end_date)
+            .is_empty());
+    }
+
+    #[test]
+    fn update_metadata() {
+        //GIVEN
+        let mut device = DeviceAggregate::new(RegisterDevice {
+            serial_number: "C48302DDM".to_string(),
+            device_group_serial: "A1".to_string(),
+            metadata: [
+                ("location".to_string(), "Busan".to_string()),
+                ("trailerId".to_string(), "TR-01".to_string()),
+            ]
+            .into(),
+            tags: ["frozen".to_string(), "reefer".to_string()].into(),
+            ..Default::default()
+        });
+
+        //WHEN
+        device.update_metadata(UpdateDeviceMetadata {
+            serial_number: "C48302DDM".to_string(),
+            metadata: [
+                ("location".to_string(), Some("Seoul".to_string())),
+                ("trailerId".to_string(), None),
+                ("productType".to_string(), Some("vaccine".to_string())),
+            ]
+            .into(),
+            add_tags: ["pharma".to_string()].into(),
+            remove_tags: ["frozen".to_string()].into(),
+        });
+
+        //THEN
+        assert_eq!(
+            device.metadata,
+            [
+                ("location".to_string(), "Seoul".to_string()),
+                ("productType".to_string(), "vaccine".to_string()),
+            ]
+            .into()
+        );
+        assert!(device.has_tags(&["pharma".to_string(), "reefer".to_string()]));
+        assert!(!device.has_tags(&["frozen".to_string()]));
+        assert!(device.has_tags(&[]));
+    }
+
+    #[test]
+    fn save_temperatures_with_calibration() {
+        //GIVEN
+        let mut device = DeviceAggregate::new(RegisterDevice {
+            serial_number: "C48302DDC".to_string(),
+            device_group_serial: "A1".to_string(),
+            ..Default::default()
+        });
+        let registered_at = Utc::now();
+        let calibrate = |offset, multiplier, valid_from| CalibrateDevice {
+            serial_number: "C48302DDC".to_string(),
+            offset,
+            multiplier,
+            valid_from,
+        };
+        device
+            .calibrate(calibrate(0.5, 1.0, registered_at - Duration::days(1)))
+            .unwrap();
+        // only valid from the second reading onwards
+        device
+            .calibrate(calibrate(-1.0, 2.0, registered_at + Duration::seconds(300)))
+            .unwrap();
+
+        //WHEN
+        device
+            .save_temperatures(
+                SaveDeviceTemperature {
+                    serial_number: "C48302DDC".to_string(),
+                    interval: 300,
+                    temperatures: "FFFE00010003".to_string(),
+                    registered_at,
+                    channels: default_channels(),
+                    idempotency_key: None,
+                },
+                None,
+            )
+            .unwrap();
+
+        //THEN
+        assert_eq!(
+            device
+                .temperatures
+                .iter()
+                .map(|t| (t.raw_value, t.value, t.calibration_version))
+                .collect::<Vec<_>>(),
+            vec![
+                (-2.0, -1.5, Some(1)),
+                (1.0, 1.0, Some(2)),
+                (3.0, 5.0, Some(2))
+            ]
+        );
+        assert!(device
+            .calibrate(calibrate(0.0, 0.0, registered_at))
+            .is_err());
+        assert_eq!(device.calibrations.len(), 2);
+    }
+
+    #[test]
+    fn save_multi_channel_temperatures() {
+        //GIVEN
+        let mut device = DeviceAggregate::new(RegisterDevice {
+            serial_number: "C48302DDH".to_string(),
+            device_group_serial: "A1".to_string(),
+            ..Default::default()
+        });
+        let registered_at = Utc::now();
+        let cmd = |temperatures: &str| SaveDeviceTemperature {
+            serial_number: "C48302DDH".to_string(),
+            interval: 300,
+            temperatures: temperatures.to_string(),
+            registered_at,
+            channels: vec![
+                MeasurementChannel::Temperature,
+                MeasurementChannel::Humidity,
+                MeasurementChannel::BatteryVoltage,
+            ],
+            idempotency_key: None,
+        };
+
+        //WHEN
+        // two frames of (temperature, humidity, battery voltage)
+        device
+            .save_temperatures(cmd("FFFE00370E740002003D0E6A"), None)
+            .unwrap();
+        let incomplete_frame = device.save_temperatures(cmd("FFFE0037"), None);
+
+        //THEN
+        assert!(matches!(incomplete_frame, Err(Error::ConversionFailed)));
+        let (start_date, end_date) = (registered_at, registered_at + Duration::minutes(5));
+        assert_eq!(
+            device.get_average_temperature_during_period(TEMP, start_date, end_date, false),
+            0.0
+        );
+        assert_eq!(
+            device.get_average_temperature_during_period(
+                MeasurementChannel::Humidity,
+                start_date,
+                end_date,
+                false,
+            ),
+            58.0
+        );
+        let battery = device.get_temperature_statistics_during_period(
+            MeasurementChannel::BatteryVoltage,
+            start_date,
+            end_date,
+            &[],
+            false,
+        );
+        assert_eq!(
+            (battery.count, battery.min, battery.max),
+            (2, Some(3690.0), Some(3700.0))
+        );
+    }
+
+    #[test]
+    fn save_temperatures_with_codec() {
+        //GIVEN
+        let mut device = DeviceAggregate::new(RegisterDevice {
+            serial_number: "C48302DDX".to_string(),
+            device_group_serial: "A1".to_string(),
+            ..Default::default()
+        });
+        let model_codec = PayloadCodec {
+            endianness: Endianness::Little,
+            scale_factor: 0.1,
+            ..Default::default()
+        };
+        let cmd = |temperatures: &str| SaveDeviceTemperature {
+            serial_number: "C48302DDX".to_string(),
+            interval: 300,
+            temperatures: temperatures.to_string(),
+            registered_at: Utc::now(),
+            channels: default_channels(),
+            idempotency_key: None,
+        };
+
+        //WHEN
+        device
+            .save_temperatures(cmd("4100ECFF"), Some(model_codec))
+            .unwrap();
+        // device specific codec takes precedence over the one of its model
+        device
+            .set_codec(Some(PayloadCodec {
+                width_in_bytes: 1,
+                ..Default::default()
+            }))
+            .unwrap();
+        let after_override = device.resolve_codec(Some(model_codec));
+
+        //THEN
+        assert_eq!(
+            device
+                .temperatures
+                .iter()
+                .map(|t| t.value)
+                .collect::<Vec<_>>(),
+            vec![6.5, -2.0]
+        );
+        assert_eq!(after_override.width_in_bytes, 1);
+        assert!(device
+            .set_codec(Some(PayloadCodec {
+                width_in_bytes: 3,
+                ..Default::default()
+            }))
+            .is_err());
+    }
+
+    #[test]
+    fn save_temperatures_flags_anomalies() {
+        //GIVEN
+        let mut device = DeviceAggregate::new(RegisterDevice {
+            serial_number: "F18302DDA".to_string(),
+            device_group_serial: "A1".to_string(),
+            ..Default::default()
+        });
+        let registered_at = Utc::now() - Duration::hours(2);
+        let (start_date, end_date) = (registered_at, registered_at + Duration::hours(2));
+
+        //WHEN
+        // a steady hour at 5 degrees, a glitch to 40, then back to 5 and 6
+        device
+            .save_temperatures(
+                SaveDeviceTemperature {
+                    serial_number: "F18302DDA".to_string(),
+                    interval: 300,
+                    temperatures: format!("{}002800050006", "0005".repeat(12)),
+                    registered_at,
+                    channels: default_channels(),
+                    idempotency_key: None,
+                },
+                None,
+            )
+            .unwrap();
+
+        //THEN
+        let flagged = device.get_flagged_readings_during_period(start_date, end_date);
+        assert_eq!(flagged.len(), 1);
+        assert_eq!(flagged[0].checked_at, registered_at + Duration::minutes(60));
+        assert_eq!(
+            flagged[0].anomalies,
+            vec![AnomalyFlag::Spike, AnomalyFlag::Jump]
+        );
+        assert_eq!(
+            device.get_average_temperature_during_period(TEMP, start_date, end_date, false),
+            111.0 / 15.0
+        );
+        assert_eq!(
+            device.get_average_temperature_during_period(TEMP, start_date, end_date, true),
+            71.0 / 14.0
+        );
+        // Flagged readings still tell the device reported
+        assert!(device
+            .get_gaps_during_period(TEMP, start_date, registered_at + Duration::minutes(70))
+            .is_empty());
+    }
+
+    #[test]
+    fn save_temperatures_with_out_of_range_interval() {
+        //GIVEN
+        let mut device = DeviceAggregate::new(RegisterDevice {
+            serial_number: "F18302DDD".to_string(),
+            device_group_serial: "A1".to_string(),
+            ..Default::default()
+        });
+        let cmd = |interval| SaveDeviceTemperature {
+            serial_number: "F18302DDD".to_string(),
+            interval,
+            temperatures: "00050006".to_string(),
+            registered_at: Utc::now() - Duration::hours(1),
+            channels: default_channels(),
+            idempotency_key: None,
+        };
+
+        //WHEN
+        let result = device.save_temperatures(cmd(i64::MAX), None);
+
+        //THEN
+        // The second frame would be taken beyond any date
+        assert!(matches!(result, Err(Error::SchemaError)));
+        assert!(device.temperatures.is_empty());
+        assert_eq!(device.reporting_interval, None);
+    }
+
+    #[test]
+    fn save_temperatures_classifies_faults() {
+        //GIVEN
+        let mut device = DeviceAggregate::new(RegisterDevice {
+            serial_number: "F18302DDB".to_string(),
+            device_group_serial: "A1".to_string(),
+            ..Default::default()
+        });
+        let registered_at = Utc::now() - Duration::hours(1);
+        let (start_date, end_date) = (registered_at, registered_at + Duration::hours(1));
+
+        //WHEN
+        // disconnected twice, and 200 degrees once
+        device
+            .save_temperatures(
+                SaveDeviceTemperature {
+                    serial_number: "F18302DDB".to_string(),
+                    interval: 300,
+                    temperatures: "00057FFF0006800000C80007".to_string(),
+                    registered_at,
+                    channels: default_channels(),
+                    idempotency_key: None,
+                },
+                None,
+            )
+            .unwrap();
+
+        //THEN
+        assert_eq!(
+            device
+                .temperatures
+                .iter()
+                .map(|temp| temp.fault)
+                .collect::<Vec<_>>(),
+            vec![
+                None,
+                Some(SensorFault::SentinelCode),
+                None,
+                Some(SensorFault::SentinelCode),
+                Some(SensorFault::OutOfPhysicalRange),
+                None
+            ]
+        );
+        assert!(device.temperatures.iter().all(|temp| !temp.is_flagged()));
+        assert_eq!(
+            device.get_average_temperature_during_period(TEMP, start_date, end_date, false),
+            6.0
+        );
+        let diagnostics = device.get_diagnostics_during_period(TEMP, start_date, end_date);
+        assert_eq!(diagnostics.faulty_ratio, 0.5);
+        // Faulty readings still tell the device reported
+        assert!(device
+            .get_gaps_during_period(TEMP, start_date, registered_at + Duration::minutes(25))
+            .is_empty());
+    }
+
+    #[test]
+    fn save_temperatures_keeps_steady_whole_degrees() {
+        //GIVEN
+        let mut device = DeviceAggregate::new(RegisterDevice {
+            serial_number: "F18302DDC".to_string(),
+            device_group_serial: "A1".to_string(),
+            ..Default::default()
+        });
+        let registered_at = Utc::now() - Duration::hours(3);
+        let (start_date, end_date) = (registered_at, registered_at + Duration::hours(3));
+
+        //WHEN
+        // a fridge holding 5 degrees for 3 hours, in whole degrees
+        device
+            .save_temperatures(
+                SaveDeviceTemperature {
+                    serial_number: "F18302DDC".to_string(),
+                    interval: 300,
+                    temperatures: "0005".repeat(36),
+                    registered_at,
+                    channels: default_channels(),
+                    idempotency_key: None,
+                },
+                None,
+            )
+            .unwrap();
+
+        //THEN
+        assert!(device.temperatures.iter().all(|temp| !temp.is_faulty()));
+        // Flagged as a flatline from the 24th reading on, left out only on demand
+        let flagged = device.get_flagged_readings_during_period(start_date, end_date);
+        assert_eq!(flagged.len(), 13);
+        assert!(flagged
+            .iter()
+            .all(|reading| reading.anomalies == vec![AnomalyFlag::Flatline]));
+        let diagnostics = device.get_diagnostics_during_period(TEMP, start_date, end_date);
+        assert_eq!(diagnostics.reading_count, 36);
+        assert_eq!(diagnostics.faulty_ratio, 0.0);
+        assert_eq!(
+            device
+                .get_temperature_statistics_during_period(TEMP, start_date, end_date, &[], false)
+                .count,
+            36
+        );
+    }
+
+    #[test]
+    fn import_readings() {
+        //GIVEN
+        let mut device = DeviceAggregate::new(RegisterDevice {
+            serial_number: "C48302DDV".to_string(),
+            device_group_serial: "A1".to_string(),
+            ..Default::default()
+        });
+        let checked_at = Utc::now() - Duration::days(3);
+        device
+            .calibrate(CalibrateDevice {
+                serial_number: "C48302DDV".to_string(),
+                offset: 1.0,
+                multiplier: 1.0,
+                valid_from: checked_at - Duration::days(1),
+            })
+            .unwrap();
+        let reading = |line, minutes, channel, value| ImportedReading {
+            line,
+            serial_number: "C48302DDV".to_string(),
+            channel,
+            checked_at: checked_at + Duration::minutes(minutes),
+            value,
+        };
+
+        //WHEN
+        let (imported, skipped, errors) = device
+            .import_readings(vec![
+                reading(2, 0, TEMP, 4.5),
+                reading(3, 0, MeasurementChannel::Humidity, 60.0),
+                reading(4, 5, TEMP, f32::NAN),
+                // same time as line 2
+                reading(5, 0, TEMP, 4.5),
+            ])
+            .unwrap();
+
+        //THEN
+        assert_eq!((imported, skipped), (2, 1));
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].line, 4);
+        assert!(matches!(errors[0].error, Error::ConversionFailed));
+        assert_eq!(
+            device
+                .temperatures
+                .iter()
+                .map(|t| (t.channel, t.raw_value, t.value))
+                .collect::<Vec<_>>(),
+            vec![(TEMP, 4.5, 5.5), (MeasurementChannel::Humidity, 60.0, 60.0)]
+        );
+    }
+
+    #[test]
+    fn tempeature_conversion() {
+        //GIVEN
+        // the following should become -2,1,3,-2,1,3,-2,1,3 ...
+        let hex_temperature = ["FFFE", "0001", "0003"];
+
+        //WHEN
+        let test_vec = hex_temperature
+            .into_iter()
+            .map(|ele| PayloadCodec::default().decode_integer(ele).unwrap())
+            .collect::<Vec<_>>();
+
+        //THEN
+        assert_eq!(test_vec, vec![-2, 1, 3]);
+    }
+
+    #[test]
+    fn temperature_statistics_during_period() {
+        //GIVEN
+        let mut device = DeviceAggregate::new(RegisterDevice {
+            serial_number: "C48302DDS".to_string(),
+            device_group_serial: "A1".to_string(),
+            ..Default::default()
+        });
+        let registered_at = Utc::now();
+        device
+            .save_temperatures(
+                SaveDeviceTemperature {
+                    serial_number: "C48302DDS".to_string(),
+                    interval: 300,
+                    temperatures: "FFFE000100030005".to_string(),
+                    registered_at,
+                    channels: default_channels(),
+                    idempotency_key: None,
+                },
+                None,
+            )
+            .unwrap();
+
+        //WHEN
+        // the last reading falls outside of the period
+        let stats = device.get_temperature_statistics_during_period(
+            TEMP,
+            registered_at,
+            registered_at + Duration::seconds(600),
+            &[50.0],
+            false,
+        );
+
+        //THEN
+        assert_eq!(stats.count, 3);
+        assert_eq!(stats.min, Some(-2.0));
+        assert_eq!(stats.max, Some(3.0));
+        assert_eq!(stats.percentiles.first().unwrap().value, 1.0);
     }
 }
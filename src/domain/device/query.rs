@@ -1,6 +1,77 @@
-use chrono::{DateTime, Utc};
+use chrono::{DateTime, Duration, Utc};
+
+use super::{
+    statistics::{ReadingWeighting, Smoothing, TemperatureMetric},
+    MeasurementChannel,
+};
+use crate::domain::device_group::AllowedTemperatureRange;
 
 pub struct GetDeviceAverageTemperatureDuringPeriodQuery {
+    pub serial_number: String,
+    pub channel: MeasurementChannel,
+    pub start_date: DateTime<Utc>,
+    pub end_date: DateTime<Utc>,
+    pub metric: TemperatureMetric,
+    // Leaves out the readings flagged as anomalies when saved
+    pub exclude_flagged: bool,
+}
+
+pub struct GetDeviceTemperatureStatisticsDuringPeriodQuery {
+    pub serial_number: String,
+    pub channel: MeasurementChannel,
+    pub start_date: DateTime<Utc>,
+    pub end_date: DateTime<Utc>,
+    pub percentiles: Vec<f32>,
+    pub exclude_flagged: bool,
+}
+
+pub struct GetDeviceTemperatureSeriesDuringPeriodQuery {
+    pub serial_number: String,
+    pub channel: MeasurementChannel,
+    pub start_date: DateTime<Utc>,
+    pub end_date: DateTime<Utc>,
+    pub bucket_size: Duration,
+    pub exclude_flagged: bool,
+}
+
+pub struct GetDeviceSmoothedTemperaturesDuringPeriodQuery {
+    pub serial_number: String,
+    pub channel: MeasurementChannel,
+    pub start_date: DateTime<Utc>,
+    pub end_date: DateTime<Utc>,
+    pub smoothing: Smoothing,
+    pub exclude_flagged: bool,
+}
+
+pub struct GetDeviceTemperatureTrendQuery {
+    pub serial_number: String,
+    pub channel: MeasurementChannel,
+    // The latest ones are fitted
+    pub reading_count: usize,
+    pub horizon: Duration,
+    pub threshold: Option<f32>,
+    pub exclude_flagged: bool,
+}
+
+pub struct GetDeviceGapsDuringPeriodQuery {
+    pub serial_number: String,
+    pub channel: MeasurementChannel,
+    pub start_date: DateTime<Utc>,
+    pub end_date: DateTime<Utc>,
+}
+
+pub struct GetDeviceExcursionsDuringPeriodQuery {
+    pub serial_number: String,
+    pub channel: MeasurementChannel,
+    pub start_date: DateTime<Utc>,
+    pub end_date: DateTime<Utc>,
+    // Defaults to the one of the group the device is in
+    pub allowed_range: Option<AllowedTemperatureRange>,
+    pub exclude_flagged: bool,
+}
+
+// Temperatures only, as the other channels aren't screened
+pub struct GetDeviceFlaggedReadingsDuringPeriodQuery {
     pub serial_number: String,
     pub start_date: DateTime<Utc>,
     pub end_date: DateTime<Utc>,
@@ -8,6 +79,41 @@ pub struct GetDeviceAverageTemperatureDuringPeriodQuery {
 
 pub struct GetDeviceGroupAverageTemperatureDuringPeriodQuery {
     pub device_group_serial: String,
+    pub channel: MeasurementChannel,
     pub start_date: DateTime<Utc>,
     pub end_date: DateTime<Utc>,
+    pub include_decommissioned: bool,
+    pub tags: Vec<String>,
+    pub metric: TemperatureMetric,
+    // Of the readings of every device, for the average of the group
+    pub weighting: ReadingWeighting,
+    pub exclude_flagged: bool,
+}
+
+pub struct GetDeviceGroupComplianceReportQuery {
+    pub device_group_serial: String,
+    pub channel: MeasurementChannel,
+    pub start_date: DateTime<Utc>,
+    pub end_date: DateTime<Utc>,
+    // Defaults to the one of the group
+    pub allowed_range: Option<AllowedTemperatureRange>,
+    pub min_coverage: f32,
+    pub include_decommissioned: bool,
+    pub tags: Vec<String>,
+    pub exclude_flagged: bool,
+}
+
+// Devices currently in the group, by faulty-reading ratio, highest first
+pub struct GetDeviceGroupDiagnosticsQuery {
+    pub device_group_serial: String,
+    pub channel: MeasurementChannel,
+    pub start_date: DateTime<Utc>,
+    pub end_date: DateTime<Utc>,
+    // Devices below it are left out
+    pub min_faulty_ratio: f32,
+}
+
+pub struct ListDevicesQuery {
+    pub device_group_serial: String,
+    pub tags: Vec<String>,
 }
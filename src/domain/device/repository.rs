@@ -1,6 +1,9 @@
-use crate::domain::{device_group::DeviceGroupAggregate, response::Error};
+use crate::domain::{
+    device_group::DeviceGroupAggregate,
+    response::{Error, Response},
+};
 
-use super::DeviceAggregate;
+use super::{codec::PayloadCodec, DeviceAggregate};
 
 pub trait TDeviceQuery {
     fn get(
@@ -8,6 +11,10 @@ pub trait TDeviceQuery {
         serial_number: &str,
     ) -> impl std::future::Future<Output = Result<DeviceAggregate, Error>> + Send;
 
+    fn list(&self)
+        -> impl std::future::Future<Output = Result<Vec<DeviceAggregate>, Error>> + Send;
+
+    // Devices that have ever been in the group, including the ones transferred out of it
     fn list_by_group(
         &self,
         device_group_serial_number: &str,
@@ -26,10 +33,43 @@ pub trait TDevicePersist {
     ) -> impl std::future::Future<Output = Result<(), Error>> + Send;
 }
 
+pub trait TDeviceModelCodecStore {
+    fn get_model_codec(
+        &self,
+        model: &str,
+    ) -> impl std::future::Future<Output = Result<Option<PayloadCodec>, Error>> + Send;
+
+    fn set_model_codec(
+        &self,
+        model: &str,
+        codec: PayloadCodec,
+    ) -> impl std::future::Future<Output = Result<(), Error>> + Send;
+}
+
+// Responses are stored per device so that the same key sent by different devices doesn't collide
+pub trait TIdempotencyKeyStore {
+    fn get_response(
+        &self,
+        serial_number: &str,
+        idempotency_key: &str,
+    ) -> impl std::future::Future<Output = Result<Option<Response>, Error>> + Send;
+
+    fn save_response(
+        &self,
+        serial_number: &str,
+        idempotency_key: &str,
+        response: &Response,
+    ) -> impl std::future::Future<Output = Result<(), Error>> + Send;
+}
+
 // For the following domain to work, it requires to query against device group
 pub trait TDeviceGroupQuery {
     fn get(
         &self,
         device_group_serial: &str,
     ) -> impl std::future::Future<Output = Result<DeviceGroupAggregate, Error>> + Send;
+
+    fn list(
+        &self,
+    ) -> impl std::future::Future<Output = Result<Vec<DeviceGroupAggregate>, Error>> + Send;
 }
@@ -0,0 +1,561 @@
+use std::collections::{BTreeMap, VecDeque};
+
+use chrono::{DateTime, Duration, Utc};
+use serde::Serialize;
+
+pub const DEFAULT_PERCENTILES: [f32; 4] = [50.0, 90.0, 95.0, 99.0];
+
+// In kJ/mol, as customarily used for pharmaceuticals
+pub const DEFAULT_ACTIVATION_ENERGY: f32 = 83.144;
+
+// In kJ/(mol*K)
+const GAS_CONSTANT: f64 = 8.314_462_618e-3;
+const ZERO_CELSIUS_IN_KELVIN: f64 = 273.15;
+
+// How the readings of a period are summed up into a single temperature
+#[derive(Default, Clone, Copy, Debug, PartialEq)]
+pub enum TemperatureMetric {
+    #[default]
+    Mean,
+    // Mean kinetic temperature, which weighs warm readings more as degradation speeds up with heat.
+    // The activation energy is in kJ/mol.
+    MeanKinetic {
+        activation_energy: f32,
+    },
+}
+
+// How the readings of several devices are weighed against each other into a single temperature
+#[derive(Default, Clone, Copy, Serialize, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub enum ReadingWeighting {
+    // Every reading counts the same, so devices reporting more often weigh more
+    #[default]
+    PerReading,
+    // Every device counts the same, however often it reports
+    PerDevice,
+    // Every reading counts for the time it stands for, see `covered_until`
+    TimeWeighted,
+}
+
+// Values in degrees Celsius. NaN without any value, as the arithmetic mean.
+pub(crate) fn mean_kinetic_temperature_of(values: &[f32], activation_energy: f32) -> f32 {
+    weighted_mean_kinetic_temperature_of(
+        &values.iter().map(|&value| (value, 1.0)).collect::<Vec<_>>(),
+        activation_energy,
+    )
+}
+
+// Values in degrees Celsius, each with its weight. NaN when the weights add up to nothing.
+fn weighted_mean_kinetic_temperature_of(values: &[(f32, f64)], activation_energy: f32) -> f32 {
+    let activation_temperature = activation_energy as f64 / GAS_CONSTANT;
+    let mean_of_exponentials = values
+        .iter()
+        .map(|&(value, weight)| {
+            weight * (-activation_temperature / (value as f64 + ZERO_CELSIUS_IN_KELVIN)).exp()
+        })
+        .sum::<f64>()
+        / values.iter().map(|(_, weight)| weight).sum::<f64>();
+
+    (-activation_temperature / mean_of_exponentials.ln() - ZERO_CELSIUS_IN_KELVIN) as f32
+}
+
+fn weighted_mean_of(values: &[(f32, f64)]) -> f32 {
+    (values
+        .iter()
+        .map(|&(value, weight)| value as f64 * weight)
+        .sum::<f64>()
+        / values.iter().map(|(_, weight)| weight).sum::<f64>()) as f32
+}
+
+// A single temperature over the readings of every device of a group, rather than an average of
+// the averages of each
+#[derive(Clone, Serialize, Debug, PartialEq)]
+pub struct GroupTemperature {
+    pub weighting: ReadingWeighting,
+    // By the metric asked for. NaN, i.e. null, without any reading.
+    #[serde(rename = "averageTemperature")]
+    pub average_temperature: f32,
+    #[serde(rename = "readingCount")]
+    pub reading_count: usize,
+    // Devices with at least one reading
+    #[serde(rename = "deviceCount")]
+    pub device_count: usize,
+}
+
+impl GroupTemperature {
+    // Readings of each device, each with the seconds it stands for. Only time weighting reads those.
+    pub(crate) fn of(
+        metric: TemperatureMetric,
+        weighting: ReadingWeighting,
+        devices: &[Vec<(f32, i64)>],
+    ) -> Self {
+        let devices = devices
+            .iter()
+            .filter(|readings| !readings.is_empty())
+            .collect::<Vec<_>>();
+        let values = devices
+            .iter()
+            .flat_map(|readings| {
+                readings.iter().map(|&(value, covered_secs)| {
+                    let weight = match weighting {
+                        ReadingWeighting::PerReading => 1.0,
+                        ReadingWeighting::PerDevice => 1.0 / readings.len() as f64,
+                        ReadingWeighting::TimeWeighted => covered_secs as f64,
+                    };
+                    (value, weight)
+                })
+            })
+            .collect::<Vec<_>>();
+
+        Self {
+            weighting,
+            average_temperature: match metric {
+                TemperatureMetric::Mean => weighted_mean_of(&values),
+                TemperatureMetric::MeanKinetic { activation_energy } => {
+                    weighted_mean_kinetic_temperature_of(&values, activation_energy)
+                }
+            },
+            reading_count: values.len(),
+            device_count: devices.len(),
+        }
+    }
+}
+
+#[derive(Default, Clone, Serialize, Debug, PartialEq)]
+pub struct TemperatureStatistics {
+    pub count: usize,
+    pub min: Option<f32>,
+    pub max: Option<f32>,
+    pub mean: Option<f32>,
+    #[serde(rename = "standardDeviation")]
+    pub standard_deviation: Option<f32>,
+    pub percentiles: Vec<TemperaturePercentile>,
+}
+
+#[derive(Clone, Serialize, Debug, PartialEq)]
+pub struct TemperaturePercentile {
+    pub percentile: f32,
+    pub value: f32,
+}
+
+impl TemperatureStatistics {
+    // Percentiles are interpolated linearly between the closest ranks.
+    // When there is no value, everything but `count` stays empty.
+    pub(crate) fn from_values(mut values: Vec<f32>, percentiles: &[f32]) -> Self {
+        if values.is_empty() {
+            return Self::default();
+        }
+        values.sort_by(|a, b| a.total_cmp(b));
+
+        let count = values.len();
+        let mean = values.iter().sum::<f32>() / count as f32;
+        let variance = values.iter().map(|v| (v - mean).powi(2)).sum::<f32>() / count as f32;
+
+        Self {
+            count,
+            min: values.first().copied(),
+            max: values.last().copied(),
+            mean: Some(mean),
+            standard_deviation: Some(variance.sqrt()),
+            percentiles: percentiles
+                .iter()
+                .map(|&percentile| TemperaturePercentile {
+                    percentile,
+                    value: Self::percentile_of_sorted(&values, percentile),
+                })
+                .collect(),
+        }
+    }
+
+    fn percentile_of_sorted(sorted: &[f32], percentile: f32) -> f32 {
+        let rank = (percentile / 100.0).clamp(0.0, 1.0) * (sorted.len() - 1) as f32;
+        let lower = rank.floor() as usize;
+        let upper = rank.ceil() as usize;
+        sorted[lower] + (sorted[upper] - sorted[lower]) * (rank - lower as f32)
+    }
+}
+
+#[derive(Clone, Serialize, Debug, PartialEq)]
+pub struct TemperatureBucket {
+    #[serde(rename = "bucketStart")]
+    pub bucket_start: DateTime<Utc>,
+    pub count: usize,
+    pub min: f32,
+    pub mean: f32,
+    pub max: f32,
+}
+
+impl TemperatureBucket {
+    // Buckets are aligned to `start_date` and only the ones holding at least one reading are
+    // returned, in chronological order. Readings are consumed in a single pass regardless of
+    // the order they come in.
+    pub(crate) fn series(
+        start_date: DateTime<Utc>,
+        bucket_size: Duration,
+        readings: impl Iterator<Item = (DateTime<Utc>, f32)>,
+    ) -> Vec<Self> {
+        let bucket_millis = bucket_size.num_milliseconds();
+        let mut buckets: BTreeMap<i64, (usize, f32, f32, f32)> = BTreeMap::new();
+
+        for (checked_at, value) in readings {
+            let index = (checked_at - start_date).num_milliseconds() / bucket_millis;
+            let (count, min, sum, max) =
+                buckets.entry(index).or_insert((0, f32::MAX, 0.0, f32::MIN));
+            *count += 1;
+            *min = min.min(value);
+            *sum += value;
+            *max = max.max(value);
+        }
+
+        // The series stops at the first bucket starting past what a date can hold
+        buckets
+            .into_iter()
+            .map_while(|(index, (count, min, sum, max))| {
+                let offset = index.checked_mul(bucket_millis)?;
+                Some(Self {
+                    bucket_start: start_date.checked_add_signed(Duration::milliseconds(offset))?,
+                    count,
+                    min,
+                    mean: sum / count as f32,
+                    max,
+                })
+            })
+            .collect()
+    }
+}
+
+// How readings are smoothed into a trend line. The window is a number of readings.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Smoothing {
+    // Mean of each reading and the ones before it within the window
+    MovingAverage { window: usize },
+    // Exponentially weighted moving average, weighing each reading by 2 / (window + 1)
+    Exponential { window: usize },
+}
+
+#[derive(Clone, Serialize, Debug, PartialEq)]
+pub struct SmoothedReading {
+    #[serde(rename = "checkedAt")]
+    pub checked_at: DateTime<Utc>,
+    pub value: f32,
+    pub smoothed: f32,
+}
+
+impl SmoothedReading {
+    // Readings must be in chronological order. Only the readings given are smoothed over, so the
+    // window fills up from the first one and the average starts off from it.
+    pub(crate) fn series(
+        smoothing: Smoothing,
+        readings: impl Iterator<Item = (DateTime<Utc>, f32)>,
+    ) -> Vec<Self> {
+        match smoothing {
+            Smoothing::MovingAverage { window } => {
+                let mut in_window = VecDeque::with_capacity(window);
+                let mut sum = 0.0;
+                readings
+                    .map(|(checked_at, value)| {
+                        if in_window.len() == window {
+                            sum -= in_window.pop_front().unwrap_or_default() as f64;
+                        }
+                        in_window.push_back(value);
+                        sum += value as f64;
+                        Self {
+                            checked_at,
+                            value,
+                            smoothed: (sum / in_window.len() as f64) as f32,
+                        }
+                    })
+                    .collect()
+            }
+            Smoothing::Exponential { window } => {
+                let weight = 2.0 / (window as f32 + 1.0);
+                let mut average: Option<f32> = None;
+                readings
+                    .map(|(checked_at, value)| {
+                        let smoothed = match average {
+                            Some(average) => average + weight * (value - average),
+                            None => value,
+                        };
+                        average = Some(smoothed);
+                        Self {
+                            checked_at,
+                            value,
+                            smoothed,
+                        }
+                    })
+                    .collect()
+            }
+        }
+    }
+}
+
+#[derive(Clone, Serialize, Debug, PartialEq)]
+pub struct ReadingGap {
+    // The last reading before the gap, or the start of the period
+    pub start: DateTime<Utc>,
+    // The first reading after the gap, or the end of the period
+    pub end: DateTime<Utc>,
+    #[serde(rename = "missingReadings")]
+    pub missing_readings: i64,
+}
+
+impl ReadingGap {
+    // The period boundaries count as readings, so missing ones at either end are reported too.
+    // Readings may drift from the cadence by up to half an interval before a gap is reported.
+    // `readings` must be in chronological order.
+    pub(crate) fn detect(
+        start_date: DateTime<Utc>,
+        end_date: DateTime<Utc>,
+        interval: Duration,
+        readings: impl Iterator<Item = DateTime<Utc>>,
+    ) -> Vec<Self> {
+        let interval_millis = interval.num_milliseconds();
+        if interval_millis <= 0 || end_date <= start_date {
+            return vec![];
+        }
+
+        let mut gaps = vec![];
+        let mut previous = start_date;
+        for checked_at in readings.chain([end_date]) {
+            // Widened, so that no interval however long overflows
+            let elapsed = (checked_at - previous).num_milliseconds() as i128;
+            let interval_millis = interval_millis as i128;
+            if 2 * elapsed > 3 * interval_millis {
+                gaps.push(Self {
+                    start: previous,
+                    end: checked_at,
+                    missing_readings: ((elapsed + interval_millis / 2) / interval_millis - 1)
+                        as i64,
+                });
+            }
+            previous = checked_at;
+        }
+        gaps
+    }
+}
+
+#[cfg(test)]
+mod test_statistics {
+    use chrono::{Duration, Utc};
+
+    use super::{
+        mean_kinetic_temperature_of, GroupTemperature, ReadingGap, ReadingWeighting,
+        SmoothedReading, Smoothing, TemperatureBucket, TemperatureMetric, TemperatureStatistics,
+        DEFAULT_ACTIVATION_ENERGY,
+    };
+
+    #[test]
+    fn reading_gaps() {
+        //GIVEN
+        let start = Utc::now();
+        let minutes = |minutes: i64| start + Duration::minutes(minutes);
+        // every 5 minutes, with a slightly late reading at 11 and nothing at 20, 25 nor 45
+        let readings = [0, 5, 11, 15, 30, 35, 40].map(minutes);
+
+        //WHEN
+        let gaps = ReadingGap::detect(
+            start,
+            minutes(50),
+            Duration::minutes(5),
+            readings.into_iter(),
+        );
+
+        //THEN
+        assert_eq!(
+            gaps,
+            vec![
+                ReadingGap {
+                    start: minutes(15),
+                    end: minutes(30),
+                    missing_readings: 2
+                },
+                ReadingGap {
+                    start: minutes(40),
+                    end: minutes(50),
+                    missing_readings: 1
+                }
+            ]
+        );
+        assert_eq!(
+            ReadingGap::detect(start, minutes(50), Duration::minutes(5), [].into_iter())[0]
+                .missing_readings,
+            9
+        );
+        assert!(
+            ReadingGap::detect(start, minutes(50), Duration::zero(), [].into_iter()).is_empty()
+        );
+        assert!(ReadingGap::detect(
+            start,
+            minutes(50),
+            Duration::milliseconds(i64::MAX),
+            [].into_iter()
+        )
+        .is_empty());
+    }
+
+    #[test]
+    fn mean_kinetic_temperature() {
+        let mkt = |values: &[f32], activation_energy| {
+            (mean_kinetic_temperature_of(values, activation_energy) * 1000.0).round() / 1000.0
+        };
+
+        // Warmer readings weigh more than in the arithmetic mean of 15
+        assert_eq!(mkt(&[5.0, 25.0], DEFAULT_ACTIVATION_ENERGY), 19.698);
+        assert_eq!(mkt(&[5.0, 25.0], 60.0), 18.594);
+        assert_eq!(mkt(&[-2.0, -2.0, -2.0], DEFAULT_ACTIVATION_ENERGY), -2.0);
+        assert!(mean_kinetic_temperature_of(&[], DEFAULT_ACTIVATION_ENERGY).is_nan());
+    }
+
+    #[test]
+    fn group_temperature_by_weighting() {
+        //GIVEN
+        // One device reporting every minute at 2 degrees, the other every 10 minutes at 8
+        let devices = vec![vec![(2.0, 60); 10], vec![(8.0, 600)], vec![]];
+
+        //WHEN
+        let group_temperature =
+            |weighting| GroupTemperature::of(TemperatureMetric::Mean, weighting, &devices);
+
+        //THEN
+        let per_reading = group_temperature(ReadingWeighting::PerReading);
+        assert!((per_reading.average_temperature - 28.0 / 11.0).abs() < 1e-5);
+        assert_eq!(per_reading.reading_count, 11);
+        assert_eq!(per_reading.device_count, 2);
+        assert_eq!(
+            group_temperature(ReadingWeighting::PerDevice).average_temperature,
+            5.0
+        );
+        assert_eq!(
+            group_temperature(ReadingWeighting::TimeWeighted).average_temperature,
+            5.0
+        );
+        let mean_kinetic = GroupTemperature::of(
+            TemperatureMetric::MeanKinetic {
+                activation_energy: DEFAULT_ACTIVATION_ENERGY,
+            },
+            ReadingWeighting::PerDevice,
+            &devices,
+        );
+        assert!(mean_kinetic.average_temperature > 5.0);
+        assert!(
+            GroupTemperature::of(TemperatureMetric::Mean, ReadingWeighting::PerReading, &[])
+                .average_temperature
+                .is_nan()
+        );
+    }
+
+    #[test]
+    fn statistics_of_values() {
+        //GIVEN
+        let values = vec![3.0, -2.0, 1.0, 3.0, -2.0, 1.0];
+
+        //WHEN
+        let stats = TemperatureStatistics::from_values(values, &[0.0, 50.0, 100.0]);
+
+        //THEN
+        assert_eq!(stats.count, 6);
+        assert_eq!(stats.min, Some(-2.0));
+        assert_eq!(stats.max, Some(3.0));
+        assert!((stats.mean.unwrap() - 0.6666667).abs() < 1e-5);
+        assert!((stats.standard_deviation.unwrap() - 2.0548046).abs() < 1e-5);
+        assert_eq!(
+            stats
+                .percentiles
+                .iter()
+                .map(|p| p.value)
+                .collect::<Vec<_>>(),
+            vec![-2.0, 1.0, 3.0]
+        );
+    }
+
+    #[test]
+    fn percentile_interpolation() {
+        //GIVEN
+        let values = vec![1.0, 2.0, 3.0, 4.0];
+
+        //WHEN
+        let stats = TemperatureStatistics::from_values(values, &[25.0, 90.0]);
+
+        //THEN
+        assert_eq!(stats.percentiles[0].value, 1.75);
+        assert!((stats.percentiles[1].value - 3.7).abs() < 1e-5);
+    }
+
+    #[test]
+    fn statistics_of_empty_period() {
+        let stats = TemperatureStatistics::from_values(vec![], &[50.0]);
+
+        assert_eq!(stats.count, 0);
+        assert!(stats.min.is_none());
+        assert!(stats.mean.is_none());
+        assert!(stats.percentiles.is_empty());
+    }
+
+    #[test]
+    fn series_of_readings() {
+        //GIVEN
+        let start_date = Utc::now();
+        let readings = vec![
+            (start_date + Duration::minutes(61), 4.0),
+            (start_date, 1.0),
+            (start_date + Duration::minutes(59), 3.0),
+            (start_date + Duration::minutes(200), 8.0),
+        ];
+
+        //WHEN
+        let series =
+            TemperatureBucket::series(start_date, Duration::hours(1), readings.into_iter());
+
+        //THEN
+        // the bucket between 2h and 3h is empty and therefore omitted
+        assert_eq!(
+            series
+                .iter()
+                .map(|b| (b.bucket_start, b.count, b.min, b.mean, b.max))
+                .collect::<Vec<_>>(),
+            vec![
+                (start_date, 2, 1.0, 2.0, 3.0),
+                (start_date + Duration::hours(1), 1, 4.0, 4.0, 4.0),
+                (start_date + Duration::hours(3), 1, 8.0, 8.0, 8.0),
+            ]
+        );
+
+        // More buckets than an i32 can count
+        let far = start_date + Duration::days(30);
+        let series = TemperatureBucket::series(
+            start_date,
+            Duration::milliseconds(1),
+            vec![(start_date, 1.0), (far, 2.0)].into_iter(),
+        );
+        assert_eq!(series.last().unwrap().bucket_start, far);
+    }
+
+    #[test]
+    fn smoothed_readings() {
+        //GIVEN
+        let start = Utc::now();
+        let readings = [4.0, 8.0, 6.0, 10.0]
+            .into_iter()
+            .enumerate()
+            .map(|(i, value)| (start + Duration::minutes(5 * i as i64), value));
+        let smoothed = |smoothing| {
+            SmoothedReading::series(smoothing, readings.clone())
+                .into_iter()
+                .map(|reading| reading.smoothed)
+                .collect::<Vec<_>>()
+        };
+
+        //WHEN
+        let moving_average = smoothed(Smoothing::MovingAverage { window: 2 });
+        let exponential = smoothed(Smoothing::Exponential { window: 3 });
+
+        //THEN
+        assert_eq!(moving_average, vec![4.0, 6.0, 7.0, 8.0]);
+        // weighing each reading by a half
+        assert_eq!(exponential, vec![4.0, 6.0, 6.0, 8.0]);
+        assert_eq!(
+            smoothed(Smoothing::MovingAverage { window: 1 }),
+            vec![4.0, 8.0, 6.0, 10.0]
+        );
+    }
+}
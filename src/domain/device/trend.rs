@@ -0,0 +1,148 @@
+use chrono::{DateTime, Duration, Utc};
+use serde::Serialize;
+
+// Readings a trend is fitted over, unless told otherwise
+pub const DEFAULT_TREND_READINGS: usize = 12;
+// Furthest a trend is projected
+pub const MAX_TREND_HORIZON_DAYS: i64 = 30;
+
+// Least squares line through the latest readings, projected from the present
+#[derive(Clone, Serialize, Debug, PartialEq)]
+pub struct TemperatureTrend {
+    #[serde(rename = "readingCount")]
+    pub reading_count: usize,
+    #[serde(rename = "lastReadingAt")]
+    pub last_reading_at: DateTime<Utc>,
+    // In degrees per minute
+    #[serde(rename = "slopePerMinute")]
+    pub slope_per_minute: f32,
+    // Of the line at present
+    #[serde(rename = "currentValue")]
+    pub current_value: f32,
+    #[serde(rename = "projectedAt")]
+    pub projected_at: DateTime<Utc>,
+    #[serde(rename = "projectedValue")]
+    pub projected_value: f32,
+    pub threshold: Option<f32>,
+    // Until the line reaches the threshold. `None` without a threshold, when the line stays flat
+    // or heads away from it, or reaches it further off than a date can tell.
+    #[serde(rename = "timeToThresholdSecs")]
+    pub time_to_threshold_secs: Option<i64>,
+    #[serde(rename = "thresholdCrossedAt")]
+    pub threshold_crossed_at: Option<DateTime<Utc>>,
+}
+
+impl TemperatureTrend {
+    // Readings must be in chronological order. `None` with fewer than two readings, or a horizon
+    // further off than a date can tell.
+    pub(crate) fn fit(
+        readings: &[(DateTime<Utc>, f32)],
+        now: DateTime<Utc>,
+        horizon: Duration,
+        threshold: Option<f32>,
+    ) -> Option<Self> {
+        let &(last_reading_at, _) = readings.last()?;
+        // Seconds relative to the last reading, in f64 so epoch-sized values don't lose precision
+        let points = readings
+            .iter()
+            .map(|&(checked_at, value)| {
+                (
+                    (checked_at - last_reading_at).num_milliseconds() as f64 / 1000.0,
+                    value as f64,
+                )
+            })
+            .collect::<Vec<_>>();
+        let count = points.len() as f64;
+        let mean_time = points.iter().map(|(time, _)| time).sum::<f64>() / count;
+        let mean_value = points.iter().map(|(_, value)| value).sum::<f64>() / count;
+        let spread = points
+            .iter()
+            .map(|(time, _)| (time - mean_time).powi(2))
+            .sum::<f64>();
+        if spread == 0.0 {
+            return None;
+        }
+        let slope = points
+            .iter()
+            .map(|(time, value)| (time - mean_time) * (value - mean_value))
+            .sum::<f64>()
+            / spread;
+        let value_at = |at: DateTime<Utc>| {
+            let time = (at - last_reading_at).num_milliseconds() as f64 / 1000.0;
+            mean_value + slope * (time - mean_time)
+        };
+
+        let projected_at = now.checked_add_signed(horizon)?;
+        let current_value = value_at(now);
+        let (time_to_threshold_secs, threshold_crossed_at) = threshold
+            .map(|threshold| (threshold as f64 - current_value) / slope)
+            .filter(|secs| secs.is_finite() && *secs >= 0.0)
+            .map(|secs| secs.round() as i64)
+            .and_then(|secs| {
+                let crossed_at = now.checked_add_signed(Duration::try_seconds(secs)?)?;
+                Some((secs, crossed_at))
+            })
+            .unzip();
+        Some(Self {
+            reading_count: readings.len(),
+            last_reading_at,
+            slope_per_minute: (slope * 60.0) as f32,
+            current_value: current_value as f32,
+            projected_at,
+            projected_value: value_at(projected_at) as f32,
+            threshold,
+            time_to_threshold_secs,
+            threshold_crossed_at,
+        })
+    }
+}
+
+#[cfg(test)]
+mod test_trend {
+    use chrono::{Duration, Utc};
+
+    use super::TemperatureTrend;
+
+    #[test]
+    fn trend_of_readings() {
+        //GIVEN
+        let now = Utc::now();
+        // warming up by half a degree every 5 minutes, up to 5 degrees 10 minutes ago
+        let readings = [3.0, 3.5, 4.0, 4.5, 5.0]
+            .into_iter()
+            .enumerate()
+            .map(|(i, value)| (now - Duration::minutes(30 - 5 * i as i64), value))
+            .collect::<Vec<_>>();
+
+        //WHEN
+        let trend =
+            TemperatureTrend::fit(&readings, now, Duration::minutes(20), Some(8.0)).unwrap();
+
+        //THEN
+        assert_eq!(trend.reading_count, 5);
+        assert!((trend.slope_per_minute - 0.1).abs() < 1e-5);
+        assert!((trend.current_value - 6.0).abs() < 1e-4);
+        assert_eq!(trend.projected_at, now + Duration::minutes(20));
+        assert!((trend.projected_value - 8.0).abs() < 1e-4);
+        assert_eq!(trend.time_to_threshold_secs, Some(20 * 60));
+        assert_eq!(
+            trend.threshold_crossed_at,
+            Some(now + Duration::minutes(20))
+        );
+
+        // Heading away from the threshold
+        let trend =
+            TemperatureTrend::fit(&readings, now, Duration::minutes(20), Some(2.0)).unwrap();
+        assert_eq!(trend.time_to_threshold_secs, None);
+        // Too far off to tell when
+        let trend =
+            TemperatureTrend::fit(&readings, now, Duration::minutes(20), Some(1e30)).unwrap();
+        assert_eq!(trend.time_to_threshold_secs, None);
+        assert_eq!(trend.threshold_crossed_at, None);
+        assert!(
+            TemperatureTrend::fit(&readings, now, Duration::milliseconds(i64::MAX), Some(8.0))
+                .is_none()
+        );
+        assert!(TemperatureTrend::fit(&readings[..1], now, Duration::minutes(20), None).is_none());
+    }
+}
@@ -0,0 +1,66 @@
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::domain::device::MeasurementChannel;
+
+use super::AllowedTemperatureRange;
+
+#[derive(Clone, Copy, Serialize, Deserialize, Debug, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum ThresholdCondition {
+    Above,
+    Below,
+}
+
+// e.g. "above 8°C for more than 15 minutes", or "below 2°C for any reading" with no duration.
+// Rules are never edited in place so that alerts keep pointing at what triggered them.
+#[derive(Clone, Serialize, Debug, PartialEq)]
+pub struct AlertRule {
+    #[serde(rename = "ruleId")]
+    pub rule_id: u32,
+    pub channel: MeasurementChannel,
+    pub condition: ThresholdCondition,
+    pub threshold: f32,
+    // How long consecutive readings must breach the threshold, in seconds. 0 for any reading.
+    #[serde(rename = "durationInSecs")]
+    pub duration_in_secs: i64,
+    #[serde(rename = "createdAt")]
+    pub created_at: DateTime<Utc>,
+}
+
+impl AlertRule {
+    pub fn is_breached_by(&self, value: f32) -> bool {
+        !self.allowed_range().contains(value)
+    }
+
+    // Readings breaching the rule are the ones outside of this range
+    pub fn allowed_range(&self) -> AllowedTemperatureRange {
+        match self.condition {
+            ThresholdCondition::Above => AllowedTemperatureRange {
+                min: f32::NEG_INFINITY,
+                max: self.threshold,
+            },
+            ThresholdCondition::Below => AllowedTemperatureRange {
+                min: self.threshold,
+                max: f32::INFINITY,
+            },
+        }
+    }
+
+    pub fn worse_of(&self, a: f32, b: f32) -> f32 {
+        match self.condition {
+            ThresholdCondition::Above => a.max(b),
+            ThresholdCondition::Below => a.min(b),
+        }
+    }
+
+    pub fn duration(&self) -> Duration {
+        Duration::seconds(self.duration_in_secs)
+    }
+
+    // Whether breaching readings spanning the given time set the rule off. They must span more
+    // than its duration, unless it has none.
+    pub fn is_sustained_for(&self, span: Duration) -> bool {
+        self.duration_in_secs == 0 || span > self.duration()
+    }
+}
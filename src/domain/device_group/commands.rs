@@ -1,7 +1,35 @@
 use serde::Deserialize;
 
+use crate::domain::device::MeasurementChannel;
+
+use super::alert_rule::ThresholdCondition;
+
 #[derive(Deserialize)]
 pub struct RegisterDeviceGroup {
     #[serde(rename = "deviceGroupSerial")]
     pub device_group_serial: String,
 }
+
+pub struct AddAlertRule {
+    pub device_group_serial: String,
+    pub channel: MeasurementChannel,
+    pub condition: ThresholdCondition,
+    pub threshold: f32,
+    pub duration_in_secs: i64,
+}
+
+#[derive(Deserialize)]
+pub struct RemoveAlertRule {
+    #[serde(rename = "deviceGroupSerial")]
+    pub device_group_serial: String,
+    #[serde(rename = "ruleId")]
+    pub rule_id: u32,
+}
+
+#[derive(Deserialize)]
+pub struct SetAllowedTemperatureRange {
+    #[serde(rename = "deviceGroupSerial")]
+    pub device_group_serial: String,
+    pub min: f32,
+    pub max: f32,
+}
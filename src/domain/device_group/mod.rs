@@ -1,9 +1,14 @@
+pub mod alert_rule;
 pub mod commands;
+pub mod query;
 pub mod repository;
 use chrono::{DateTime, Utc};
 use serde::Serialize;
 
-use self::commands::RegisterDeviceGroup;
+use crate::domain::{incident::IncidentSilence, response::Error};
+
+use self::alert_rule::AlertRule;
+use self::commands::{AddAlertRule, RegisterDeviceGroup, SetAllowedTemperatureRange};
 
 #[derive(Default, Clone, Debug, Serialize)]
 pub struct DeviceGroupAggregate {
@@ -14,6 +19,32 @@ pub struct DeviceGroupAggregate {
     pub serial_number: String,
     #[serde(rename = "createdAt")]
     pub created_at: DateTime<Utc>,
+    // Evaluated against the readings of the devices currently in the group
+    #[serde(rename = "alertRules")]
+    pub alert_rules: Vec<AlertRule>,
+    #[serde(skip_serializing)]
+    pub last_rule_id: u32,
+    // Batches of temperatures with readings outside of it raise incidents
+    #[serde(rename = "allowedRange")]
+    pub allowed_range: Option<AllowedTemperatureRange>,
+    pub silences: Vec<IncidentSilence>,
+}
+
+#[derive(Clone, Serialize, Debug, PartialEq)]
+pub struct AllowedTemperatureRange {
+    pub min: f32,
+    pub max: f32,
+}
+
+impl AllowedTemperatureRange {
+    pub fn contains(&self, value: f32) -> bool {
+        (self.min..=self.max).contains(&value)
+    }
+
+    // How far the value is beyond either bound. Zero within the range.
+    pub fn deviation_of(&self, value: f32) -> f32 {
+        (self.min - value).max(value - self.max).max(0.0)
+    }
 }
 
 impl DeviceGroupAggregate {
@@ -25,13 +56,82 @@ impl DeviceGroupAggregate {
             ..Default::default()
         }
     }
+
+    pub fn add_alert_rule(&mut self, cmd: AddAlertRule) -> Result<AlertRule, Error> {
+        if !cmd.threshold.is_finite() || cmd.duration_in_secs < 0 {
+            eprintln!("[ERROR] Invalid alert rule given");
+            return Err(Error::SchemaError);
+        }
+        // Ids of removed rules are not reused, as alerts may still refer to them
+        let rule_id = self.last_rule_id + 1;
+        self.last_rule_id = rule_id;
+
+        let rule = AlertRule {
+            rule_id,
+            channel: cmd.channel,
+            condition: cmd.condition,
+            threshold: cmd.threshold,
+            duration_in_secs: cmd.duration_in_secs,
+            created_at: Utc::now(),
+        };
+        self.alert_rules.push(rule.clone());
+        Ok(rule)
+    }
+
+    pub fn set_allowed_range(&mut self, cmd: SetAllowedTemperatureRange) -> Result<(), Error> {
+        if !cmd.min.is_finite() || !cmd.max.is_finite() || cmd.min > cmd.max {
+            eprintln!("[ERROR] Invalid range given {} ~ {}", cmd.min, cmd.max);
+            return Err(Error::SchemaError);
+        }
+        self.allowed_range = Some(AllowedTemperatureRange {
+            min: cmd.min,
+            max: cmd.max,
+        });
+        Ok(())
+    }
+
+    // Expired silences are kept, for audit
+    pub fn silence(
+        &mut self,
+        serial_number: Option<String>,
+        until: DateTime<Utc>,
+        actor: String,
+    ) -> Result<IncidentSilence, Error> {
+        let now = Utc::now();
+        if until <= now {
+            eprintln!("[ERROR] Silence must end in the future");
+            return Err(Error::SchemaError);
+        }
+        let silence = IncidentSilence {
+            serial_number,
+            until,
+            actor,
+            created_at: now,
+        };
+        self.silences.push(silence.clone());
+        Ok(silence)
+    }
+
+    pub fn remove_alert_rule(&mut self, rule_id: u32) -> Result<(), Error> {
+        let before = self.alert_rules.len();
+        self.alert_rules.retain(|rule| rule.rule_id != rule_id);
+        if self.alert_rules.len() == before {
+            return Err(Error::NotFound);
+        }
+        Ok(())
+    }
 }
 
 #[cfg(test)]
 mod test_device_group {
     use chrono::{DateTime, Utc};
 
-    use super::{commands::RegisterDeviceGroup, DeviceGroupAggregate};
+    use super::{
+        alert_rule::ThresholdCondition,
+        commands::{AddAlertRule, RegisterDeviceGroup},
+        DeviceGroupAggregate,
+    };
+    use crate::domain::{device::MeasurementChannel, response::Error};
 
     // Infallible operation which won't return error.
     #[test]
@@ -49,4 +149,33 @@ mod test_device_group {
 
         assert_ne!(group.created_at, DateTime::<Utc>::default());
     }
+
+    #[test]
+    fn add_and_remove_alert_rules() {
+        //GIVEN
+        let mut group = DeviceGroupAggregate::new(RegisterDeviceGroup {
+            device_group_serial: "A1".to_string(),
+        });
+        let cmd = |threshold| AddAlertRule {
+            device_group_serial: "A1".to_string(),
+            channel: MeasurementChannel::Temperature,
+            condition: ThresholdCondition::Above,
+            threshold,
+            duration_in_secs: 900,
+        };
+
+        //WHEN
+        let first = group.add_alert_rule(cmd(8.0)).unwrap();
+        group.remove_alert_rule(first.rule_id).unwrap();
+        let second = group.add_alert_rule(cmd(10.0)).unwrap();
+
+        //THEN
+        assert_eq!((first.rule_id, second.rule_id), (1, 2));
+        assert_eq!(group.alert_rules, vec![second]);
+        assert!(matches!(group.remove_alert_rule(1), Err(Error::NotFound)));
+        assert!(matches!(
+            group.add_alert_rule(cmd(f32::NAN)),
+            Err(Error::SchemaError)
+        ));
+    }
 }
@@ -7,4 +7,9 @@ pub trait TDeviceGroupPersist {
         &self,
         device: &mut DeviceGroupAggregate,
     ) -> impl std::future::Future<Output = Result<(), Error>> + Send;
+
+    fn update(
+        &self,
+        device: &mut DeviceGroupAggregate,
+    ) -> impl std::future::Future<Output = Result<(), Error>> + Send;
 }
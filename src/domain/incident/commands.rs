@@ -0,0 +1,34 @@
+use chrono::{DateTime, Utc};
+use serde::Deserialize;
+
+// Operators are identified by whatever name they give as `actor`
+#[derive(Deserialize)]
+pub struct AcknowledgeIncident {
+    #[serde(rename = "incidentId")]
+    pub incident_id: i64,
+    pub actor: String,
+}
+
+#[derive(Deserialize)]
+pub struct AssignIncident {
+    #[serde(rename = "incidentId")]
+    pub incident_id: i64,
+    pub actor: String,
+    pub assignee: String,
+}
+
+#[derive(Deserialize)]
+pub struct AnnotateIncident {
+    #[serde(rename = "incidentId")]
+    pub incident_id: i64,
+    pub actor: String,
+    pub note: String,
+}
+
+pub struct SilenceIncidents {
+    pub device_group_serial: String,
+    // `None` to silence the whole group
+    pub serial_number: Option<String>,
+    pub until: DateTime<Utc>,
+    pub actor: String,
+}
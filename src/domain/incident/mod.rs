@@ -0,0 +1,408 @@
+pub mod commands;
+pub mod query;
+pub mod repository;
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use super::{
+    device::{DeviceTemperature, MeasurementChannel},
+    device_group::{AllowedTemperatureRange, DeviceGroupAggregate},
+    response::Error,
+};
+
+// Open -> Acknowledged, and Open -> Silenced -> Acknowledged. A silence runs out by itself, back
+// to Open.
+#[derive(Default, Clone, Copy, Serialize, Deserialize, Debug, PartialEq, Eq)]
+pub enum IncidentStatus {
+    #[default]
+    Open,
+    Acknowledged,
+    Silenced,
+}
+
+#[derive(Clone, Serialize, Debug, PartialEq)]
+pub enum IncidentAction {
+    Opened {
+        #[serde(rename = "outOfRangeCount")]
+        out_of_range_count: usize,
+    },
+    Acknowledged,
+    Assigned {
+        assignee: String,
+    },
+    Annotated {
+        note: String,
+    },
+    Silenced {
+        until: DateTime<Utc>,
+    },
+}
+
+#[derive(Clone, Serialize, Debug)]
+pub struct IncidentTimelineEntry {
+    pub at: DateTime<Utc>,
+    // `None` for what the system did by itself
+    pub actor: Option<String>,
+    pub action: IncidentAction,
+}
+
+// Stops incidents from bothering operators until `until`. Incidents are still recorded meanwhile,
+// only as `Silenced`.
+#[derive(Clone, Serialize, Debug)]
+pub struct IncidentSilence {
+    // `None` to silence the whole group
+    #[serde(rename = "serialNumber")]
+    pub serial_number: Option<String>,
+    pub until: DateTime<Utc>,
+    pub actor: String,
+    #[serde(rename = "createdAt")]
+    pub created_at: DateTime<Utc>,
+}
+
+impl IncidentSilence {
+    pub fn covers(&self, serial_number: &str, at: DateTime<Utc>) -> bool {
+        at < self.until && !matches!(&self.serial_number, Some(serial) if serial != serial_number)
+    }
+}
+
+// Raised for a batch of temperatures with readings outside the allowed range of the group.
+// Nothing is ever removed from the timeline, for audit.
+#[derive(Clone, Serialize, Debug)]
+pub struct IncidentAggregate {
+    #[serde(rename = "incidentId")]
+    pub incident_id: i64,
+    #[serde(rename = "deviceGroupSerial")]
+    pub device_group_serial: String,
+    #[serde(rename = "serialNumber")]
+    pub serial_number: String,
+    pub status: IncidentStatus,
+    // As it was when the batch came in
+    #[serde(rename = "allowedRange")]
+    pub allowed_range: AllowedTemperatureRange,
+    #[serde(rename = "outOfRangeCount")]
+    pub out_of_range_count: usize,
+    #[serde(rename = "minValue")]
+    pub min_value: f32,
+    #[serde(rename = "maxValue")]
+    pub max_value: f32,
+    // Of the first and last readings out of range
+    #[serde(rename = "firstCheckedAt")]
+    pub first_checked_at: DateTime<Utc>,
+    #[serde(rename = "lastCheckedAt")]
+    pub last_checked_at: DateTime<Utc>,
+    pub assignee: Option<String>,
+    // End of the latest silence, if any
+    #[serde(rename = "silencedUntil")]
+    pub silenced_until: Option<DateTime<Utc>>,
+    pub timeline: Vec<IncidentTimelineEntry>,
+    #[serde(rename = "createdAt")]
+    pub created_at: DateTime<Utc>,
+}
+
+impl IncidentAggregate {
+    // `None` when the group has no allowed range or every reading of the batch is within it.
+    // Faulty readings don't measure anything, so are never out of range.
+    pub fn open_for_batch(
+        group: &DeviceGroupAggregate,
+        serial_number: &str,
+        readings: &[DeviceTemperature],
+    ) -> Option<Self> {
+        let allowed_range = group.allowed_range.clone()?;
+        let out_of_range = readings
+            .iter()
+            .filter(|temp| {
+                temp.channel == MeasurementChannel::Temperature
+                    && !temp.is_faulty()
+                    && !allowed_range.contains(temp.value)
+            })
+            .collect::<Vec<_>>();
+        let (first, last) = (out_of_range.first()?, out_of_range.last()?);
+
+        let now = Utc::now();
+        let mut incident = Self {
+            incident_id: 0,
+            device_group_serial: group.serial_number.clone(),
+            serial_number: serial_number.to_string(),
+            status: IncidentStatus::Open,
+            out_of_range_count: out_of_range.len(),
+            min_value: out_of_range
+                .iter()
+                .map(|temp| temp.value)
+                .fold(f32::MAX, f32::min),
+            max_value: out_of_range
+                .iter()
+                .map(|temp| temp.value)
+                .fold(f32::MIN, f32::max),
+            first_checked_at: first.checked_at,
+            last_checked_at: last.checked_at,
+            allowed_range,
+            assignee: None,
+            silenced_until: None,
+            timeline: vec![IncidentTimelineEntry {
+                at: now,
+                actor: None,
+                action: IncidentAction::Opened {
+                    out_of_range_count: out_of_range.len(),
+                },
+            }],
+            created_at: now,
+        };
+        if let Some(silence) = group
+            .silences
+            .iter()
+            .filter(|silence| silence.covers(serial_number, now))
+            .max_by_key(|silence| silence.until)
+        {
+            incident.silence(None, silence.until);
+        }
+        Some(incident)
+    }
+
+    // The status is stored as it was last set, so a silence which ran out since still reads as
+    // `Silenced` until brought up to date here
+    pub fn status_at(&self, at: DateTime<Utc>) -> IncidentStatus {
+        match (self.status, self.silenced_until) {
+            (IncidentStatus::Silenced, Some(until)) if until <= at => IncidentStatus::Open,
+            (status, _) => status,
+        }
+    }
+
+    // Called whenever incidents are read
+    pub fn refresh_status(&mut self, at: DateTime<Utc>) {
+        self.status = self.status_at(at);
+    }
+
+    // Not acknowledged yet, whether silenced or not
+    pub fn is_open(&self) -> bool {
+        self.status != IncidentStatus::Acknowledged
+    }
+
+    pub fn acknowledge(&mut self, actor: String) -> Result<(), Error> {
+        if self.status == IncidentStatus::Acknowledged {
+            eprintln!("[ERROR] Incident already acknowledged {}", self.incident_id);
+            return Err(Error::InvalidStatusTransition);
+        }
+        self.status = IncidentStatus::Acknowledged;
+        self.record(Some(actor), IncidentAction::Acknowledged);
+        Ok(())
+    }
+
+    pub fn assign(&mut self, actor: String, assignee: String) -> Result<(), Error> {
+        if assignee.trim().is_empty() {
+            return Err(Error::SchemaError);
+        }
+        self.assignee = Some(assignee.clone());
+        self.record(Some(actor), IncidentAction::Assigned { assignee });
+        Ok(())
+    }
+
+    pub fn annotate(&mut self, actor: String, note: String) -> Result<(), Error> {
+        if note.trim().is_empty() {
+            return Err(Error::SchemaError);
+        }
+        self.record(Some(actor), IncidentAction::Annotated { note });
+        Ok(())
+    }
+
+    // An incident already acknowledged stays so; the silence is only recorded. A shorter silence
+    // doesn't cut a longer one short.
+    pub fn silence(&mut self, actor: Option<String>, until: DateTime<Utc>) {
+        if self.status_at(Utc::now()) == IncidentStatus::Open {
+            self.status = IncidentStatus::Silenced;
+        }
+        self.silenced_until = Some(match self.silenced_until {
+            Some(silenced_until) if self.status == IncidentStatus::Silenced => {
+                silenced_until.max(until)
+            }
+            _ => until,
+        });
+        self.record(actor, IncidentAction::Silenced { until });
+    }
+
+    fn record(&mut self, actor: Option<String>, action: IncidentAction) {
+        self.timeline.push(IncidentTimelineEntry {
+            at: Utc::now(),
+            actor,
+            action,
+        });
+    }
+}
+
+#[cfg(test)]
+mod test_incident {
+    use chrono::{Duration, Utc};
+
+    use super::{IncidentAction, IncidentAggregate, IncidentStatus};
+    use crate::domain::{
+        device::{DeviceTemperature, MeasurementChannel},
+        device_group::{
+            commands::{RegisterDeviceGroup, SetAllowedTemperatureRange},
+            DeviceGroupAggregate,
+        },
+        response::Error,
+    };
+
+    fn group_with_range() -> DeviceGroupAggregate {
+        let mut group = DeviceGroupAggregate::new(RegisterDeviceGroup {
+            device_group_serial: "A1".to_string(),
+        });
+        group
+            .set_allowed_range(SetAllowedTemperatureRange {
+                device_group_serial: "A1".to_string(),
+                min: 2.0,
+                max: 8.0,
+            })
+            .unwrap();
+        group
+    }
+
+    fn readings(values: &[f32]) -> Vec<DeviceTemperature> {
+        let registered_at = Utc::now();
+        values
+            .iter()
+            .enumerate()
+            .map(|(idx, value)| {
+                DeviceTemperature::new(
+                    1,
+                    MeasurementChannel::Temperature,
+                    registered_at + Duration::minutes(5 * idx as i64),
+                    *value,
+                    None,
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn open_for_batch_out_of_range() {
+        //GIVEN
+        let group = group_with_range();
+        let batch = readings(&[4.0, 9.5, 5.0, 1.0]);
+
+        //WHEN
+        let incident = IncidentAggregate::open_for_batch(&group, "C48302DDI", &batch).unwrap();
+
+        //THEN
+        assert_eq!(incident.status, IncidentStatus::Open);
+        assert_eq!(incident.out_of_range_count, 2);
+        assert_eq!((incident.min_value, incident.max_value), (1.0, 9.5));
+        assert_eq!(incident.first_checked_at, batch[1].checked_at);
+        assert_eq!(incident.last_checked_at, batch[3].checked_at);
+        assert!(
+            IncidentAggregate::open_for_batch(&group, "C48302DDI", &readings(&[4.0])).is_none()
+        );
+        assert!(IncidentAggregate::open_for_batch(
+            &DeviceGroupAggregate::default(),
+            "C48302DDI",
+            &batch
+        )
+        .is_none());
+    }
+
+    #[test]
+    fn open_for_batch_while_silenced() {
+        //GIVEN
+        let mut group = group_with_range();
+        let until = Utc::now() + Duration::hours(1);
+        group
+            .silence(Some("C48302DDJ".to_string()), until, "kim".to_string())
+            .unwrap();
+
+        //WHEN
+        let silenced =
+            IncidentAggregate::open_for_batch(&group, "C48302DDJ", &readings(&[9.0])).unwrap();
+        let other_device =
+            IncidentAggregate::open_for_batch(&group, "C48302DDK", &readings(&[9.0])).unwrap();
+
+        //THEN
+        assert_eq!(silenced.status, IncidentStatus::Silenced);
+        assert_eq!(
+            silenced.timeline.last().unwrap().action,
+            IncidentAction::Silenced { until }
+        );
+        assert_eq!(other_device.status, IncidentStatus::Open);
+        assert!(group
+            .silence(None, Utc::now() - Duration::hours(1), "kim".to_string())
+            .is_err());
+    }
+
+    #[test]
+    fn silence_runs_out() {
+        //GIVEN
+        let mut incident =
+            IncidentAggregate::open_for_batch(&group_with_range(), "C48302DDI", &readings(&[9.0]))
+                .unwrap();
+        let until = Utc::now() + Duration::hours(1);
+
+        //WHEN
+        incident.silence(Some("kim".to_string()), until);
+        incident.silence(Some("kim".to_string()), until - Duration::minutes(30));
+
+        //THEN
+        assert_eq!(incident.silenced_until, Some(until));
+        assert_eq!(incident.status_at(Utc::now()), IncidentStatus::Silenced);
+        assert_eq!(incident.status_at(until), IncidentStatus::Open);
+        incident.refresh_status(until + Duration::minutes(1));
+        assert_eq!(incident.status, IncidentStatus::Open);
+        incident.acknowledge("kim".to_string()).unwrap();
+        assert_eq!(incident.status_at(until), IncidentStatus::Acknowledged);
+    }
+
+    #[test]
+    fn incident_workflow() {
+        //GIVEN
+        let mut incident =
+            IncidentAggregate::open_for_batch(&group_with_range(), "C48302DDI", &readings(&[9.0]))
+                .unwrap();
+
+        //WHEN
+        incident
+            .assign("kim".to_string(), "lee".to_string())
+            .unwrap();
+        incident
+            .annotate("lee".to_string(), "door left open".to_string())
+            .unwrap();
+        incident.acknowledge("lee".to_string()).unwrap();
+        let acknowledged_again = incident.acknowledge("lee".to_string());
+
+        //THEN
+        assert!(matches!(
+            acknowledged_again,
+            Err(Error::InvalidStatusTransition)
+        ));
+        assert_eq!(incident.status, IncidentStatus::Acknowledged);
+        assert_eq!(incident.assignee.as_deref(), Some("lee"));
+        assert_eq!(
+            incident
+                .timeline
+                .iter()
+                .map(|entry| (entry.actor.as_deref(), &entry.action))
+                .collect::<Vec<_>>(),
+            vec![
+                (
+                    None,
+                    &IncidentAction::Opened {
+                        out_of_range_count: 1
+                    }
+                ),
+                (
+                    Some("kim"),
+                    &IncidentAction::Assigned {
+                        assignee: "lee".to_string()
+                    }
+                ),
+                (
+                    Some("lee"),
+                    &IncidentAction::Annotated {
+                        note: "door left open".to_string()
+                    }
+                ),
+                (Some("lee"), &IncidentAction::Acknowledged),
+            ]
+        );
+        assert!(incident
+            .annotate("lee".to_string(), " ".to_string())
+            .is_err());
+    }
+}
@@ -0,0 +1,11 @@
+use super::IncidentStatus;
+
+pub struct GetIncidentQuery {
+    pub incident_id: i64,
+}
+
+pub struct ListIncidentsQuery {
+    pub device_group_serial: Option<String>,
+    pub serial_number: Option<String>,
+    pub status: Option<IncidentStatus>,
+}
@@ -0,0 +1,26 @@
+use crate::domain::response::Error;
+
+use super::IncidentAggregate;
+
+pub trait TIncidentQuery {
+    fn get(
+        &self,
+        incident_id: i64,
+    ) -> impl std::future::Future<Output = Result<IncidentAggregate, Error>> + Send;
+
+    fn list(
+        &self,
+    ) -> impl std::future::Future<Output = Result<Vec<IncidentAggregate>, Error>> + Send;
+}
+
+pub trait TIncidentPersist {
+    fn add(
+        &self,
+        incident: &mut IncidentAggregate,
+    ) -> impl std::future::Future<Output = Result<(), Error>> + Send;
+
+    fn update(
+        &self,
+        incident: &mut IncidentAggregate,
+    ) -> impl std::future::Future<Output = Result<(), Error>> + Send;
+}
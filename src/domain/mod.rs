@@ -1,3 +1,6 @@
+pub mod alert;
 pub mod device;
 pub mod device_group;
+pub mod incident;
+pub mod notification;
 pub mod response;
@@ -0,0 +1,74 @@
+pub mod query;
+pub mod repository;
+
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+
+// What is published to webhooks, tagged with its `type`
+#[derive(Clone, Serialize, Debug, PartialEq)]
+#[serde(tag = "type")]
+pub enum DeviceEvent {
+    GroupRegistered {
+        #[serde(rename = "deviceGroupSerial")]
+        device_group_serial: String,
+    },
+    DeviceRegistered {
+        #[serde(rename = "serialNumber")]
+        serial_number: String,
+        #[serde(rename = "deviceGroupSerial")]
+        device_group_serial: String,
+    },
+    TemperaturesSaved {
+        #[serde(rename = "serialNumber")]
+        serial_number: String,
+        #[serde(rename = "deviceGroupSerial")]
+        device_group_serial: String,
+        saved: usize,
+        skipped: usize,
+    },
+}
+
+// An event waiting to be delivered. It is also the body webhooks receive.
+#[derive(Clone, Serialize, Debug)]
+pub struct Notification {
+    #[serde(rename = "notificationId")]
+    pub notification_id: i64,
+    #[serde(flatten)]
+    pub event: DeviceEvent,
+    #[serde(rename = "occurredAt")]
+    pub occurred_at: DateTime<Utc>,
+}
+
+impl Notification {
+    pub fn new(event: DeviceEvent) -> Self {
+        Self {
+            notification_id: 0,
+            event,
+            occurred_at: Utc::now(),
+        }
+    }
+}
+
+// A notification on its way to one webhook. It stays in the outbox until the webhook takes it or
+// the attempts run out.
+#[derive(Clone, Debug)]
+pub struct Delivery {
+    pub delivery_id: i64,
+    pub notification: Notification,
+    pub url: String,
+    // Failed so far
+    pub attempts: u32,
+    pub next_attempt_at: DateTime<Utc>,
+}
+
+// A notification a webhook could not receive, kept once every attempt failed
+#[derive(Clone, Serialize, Debug)]
+pub struct DeadLetter {
+    pub notification: Notification,
+    pub url: String,
+    pub attempts: u32,
+    #[serde(rename = "lastError")]
+    pub last_error: String,
+    #[serde(rename = "failedAt")]
+    pub failed_at: DateTime<Utc>,
+}
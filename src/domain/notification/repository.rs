@@ -0,0 +1,46 @@
+use chrono::{DateTime, Utc};
+
+use crate::domain::response::Error;
+
+use super::{DeadLetter, Delivery, DeviceEvent};
+
+pub trait TNotificationOutbox {
+    fn publish(
+        &self,
+        event: DeviceEvent,
+    ) -> impl std::future::Future<Output = Result<(), Error>> + Send;
+
+    // Turns the notifications published since the last call into a delivery to each of `urls`,
+    // due at once
+    fn schedule_deliveries(
+        &self,
+        urls: &[String],
+    ) -> impl std::future::Future<Output = Result<(), Error>> + Send;
+
+    // In the order they were scheduled
+    fn list_due_deliveries(
+        &self,
+        now: DateTime<Utc>,
+    ) -> impl std::future::Future<Output = Result<Vec<Delivery>, Error>> + Send;
+
+    // Puts back a delivery which failed, to be attempted again at `next_attempt_at`
+    fn reschedule_delivery(
+        &self,
+        delivery: &Delivery,
+    ) -> impl std::future::Future<Output = Result<(), Error>> + Send;
+
+    // Once the webhook took the notification, or it was dead-lettered
+    fn remove_delivery(
+        &self,
+        delivery_id: i64,
+    ) -> impl std::future::Future<Output = Result<(), Error>> + Send;
+}
+
+pub trait TDeadLetterStore {
+    fn add(
+        &self,
+        dead_letter: &DeadLetter,
+    ) -> impl std::future::Future<Output = Result<(), Error>> + Send;
+
+    fn list(&self) -> impl std::future::Future<Output = Result<Vec<DeadLetter>, Error>> + Send;
+}
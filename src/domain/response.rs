@@ -1,16 +1,34 @@
 use serde::Serialize;
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Clone, Serialize)]
 pub enum Error {
     ConversionFailed,
     NotFound,
     DuplicateKeyError,
     SchemaError,
+    InvalidStatusTransition,
+    DeviceNotActive,
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Clone, Serialize)]
 pub enum Response {
     Empty,
+    TemperaturesSaved {
+        saved: usize,
+        skipped: usize,
+    },
+    ReadingsImported {
+        imported: usize,
+        skipped: usize,
+        errors: Vec<ImportRowError>,
+    },
+}
+
+// Points at the line of an imported file that was rejected
+#[derive(Debug, Clone, Serialize)]
+pub struct ImportRowError {
+    pub line: usize,
+    pub error: Error,
 }
 
 impl From<()> for Response {
@@ -0,0 +1,41 @@
+use crate::domain::{
+    alert::{
+        query::{GetAlertQuery, ListAlertsQuery},
+        repository::TAlertQuery,
+        AlertAggregate,
+    },
+    response::Error,
+};
+
+use super::{matches_filter, QueryHandler};
+
+impl<R> QueryHandler<GetAlertQuery, R>
+where
+    R: TAlertQuery,
+{
+    pub async fn handle(self) -> Result<AlertAggregate, Error> {
+        self.repo.get(self.query.alert_id).await
+    }
+}
+
+impl<R> QueryHandler<ListAlertsQuery, R>
+where
+    R: TAlertQuery,
+{
+    // Latest first
+    pub async fn handle(self) -> Result<Vec<AlertAggregate>, Error> {
+        let mut alerts = self
+            .repo
+            .list()
+            .await?
+            .into_iter()
+            .filter(|alert| {
+                matches_filter(&self.query.device_group_serial, &alert.device_group_serial)
+                    && matches_filter(&self.query.serial_number, &alert.serial_number)
+                    && matches_filter(&self.query.status, &alert.status)
+            })
+            .collect::<Vec<_>>();
+        alerts.sort_by_key(|alert| std::cmp::Reverse(alert.triggered_at));
+        Ok(alerts)
+    }
+}
@@ -1,22 +1,53 @@
+use chrono::{DateTime, Utc};
+
 use crate::domain::{
+    alert::{
+        repository::{TAlertPersist, TAlertQuery},
+        AlertAggregate,
+    },
     device::{
-        commands::{RegisterDevice, SaveDeviceTemperature},
+        anomaly::FlaggedReading,
+        commands::{
+            CalibrateDevice, DecommissionDevice, DetectOfflineDevices, ImportDeviceReadings,
+            ImportedReading, ReactivateDevice, RegisterDevice, SaveDeviceTemperature,
+            SetDeviceCodec, SetDeviceModelCodec, SuspendDevice, TransferDevice,
+            UpdateDeviceMetadata,
+        },
+        compliance::ComplianceReport,
+        excursion::Excursion,
+        fault::SensorDiagnostics,
         query::{
-            GetDeviceAverageTemperatureDuringPeriodQuery,
-            GetDeviceGroupAverageTemperatureDuringPeriodQuery,
+            GetDeviceAverageTemperatureDuringPeriodQuery, GetDeviceExcursionsDuringPeriodQuery,
+            GetDeviceFlaggedReadingsDuringPeriodQuery, GetDeviceGapsDuringPeriodQuery,
+            GetDeviceGroupAverageTemperatureDuringPeriodQuery, GetDeviceGroupComplianceReportQuery,
+            GetDeviceGroupDiagnosticsQuery, GetDeviceSmoothedTemperaturesDuringPeriodQuery,
+            GetDeviceTemperatureSeriesDuringPeriodQuery,
+            GetDeviceTemperatureStatisticsDuringPeriodQuery, GetDeviceTemperatureTrendQuery,
+            ListDevicesQuery,
+        },
+        repository::{
+            TDeviceGroupQuery, TDeviceModelCodecStore, TDevicePersist, TDeviceQuery,
+            TIdempotencyKeyStore,
         },
-        repository::{TDeviceGroupQuery, TDevicePersist, TDeviceQuery},
-        DeviceAggregate,
+        statistics::{
+            GroupTemperature, ReadingGap, SmoothedReading, TemperatureBucket, TemperatureMetric,
+            TemperatureStatistics,
+        },
+        trend::TemperatureTrend,
+        DeviceAggregate, DeviceStatus, DeviceTemperature,
     },
-    device_group::DeviceGroupAggregate,
-    response::{Error, Response},
+    device_group::{AllowedTemperatureRange, DeviceGroupAggregate},
+    incident::{repository::TIncidentPersist, IncidentAggregate},
+    notification::{repository::TNotificationOutbox, DeviceEvent},
+    response::{Error, ImportRowError, Response},
 };
+use std::collections::BTreeMap;
 
 use super::{CommandHandler, QueryHandler};
 
 impl<R> CommandHandler<RegisterDevice, R>
 where
-    R: TDevicePersist + TDeviceGroupQuery,
+    R: TDevicePersist + TDeviceGroupQuery + TNotificationOutbox,
 {
     pub async fn handle(self) -> Result<(DeviceAggregate, DeviceGroupAggregate), Error> {
         // Validate if group actually exists
@@ -24,18 +55,329 @@ where
 
         let mut aggregate = DeviceAggregate::new(self.command);
         self.repo.add(&mut aggregate).await?;
+        self.repo
+            .publish(DeviceEvent::DeviceRegistered {
+                serial_number: aggregate.serial_number.clone(),
+                device_group_serial: group.serial_number.clone(),
+            })
+            .await?;
         Ok((aggregate, group))
     }
 }
 
 impl<R> CommandHandler<SaveDeviceTemperature, R>
+where
+    R: TDevicePersist
+        + TDeviceQuery
+        + TIdempotencyKeyStore
+        + TDeviceModelCodecStore
+        + TDeviceGroupQuery
+        + TAlertQuery
+        + TAlertPersist
+        + TIncidentPersist
+        + TNotificationOutbox,
+{
+    pub async fn handle(self) -> Result<Response, Error> {
+        let serial_number = self.command.serial_number.clone();
+        let idempotency_key = self.command.idempotency_key.clone();
+        if let Some(key) = &idempotency_key {
+            if let Some(response) = self.repo.get_response(&serial_number, key).await? {
+                return Ok(response);
+            }
+        }
+
+        let mut aggregate = TDeviceQuery::get(&self.repo, &serial_number).await?;
+        let model_codec = match &aggregate.model {
+            Some(model) => self.repo.get_model_codec(model).await?,
+            None => None,
+        };
+        let since = self.command.registered_at;
+        let (response, saved) = aggregate.ingest_temperatures(self.command, model_codec)?;
+        TDevicePersist::update(&self.repo, &mut aggregate).await?;
+        if let Some(key) = &idempotency_key {
+            self.repo
+                .save_response(&serial_number, key, &response)
+                .await?;
+        }
+
+        // The readings are stored by now, so what follows up on them doesn't fail the save
+        if let Err(err) =
+            follow_up_saved_temperatures(&self.repo, &aggregate, since, &saved, &response).await
+        {
+            eprintln!(
+                "[ERROR] Follow-up of temperatures saved for {} failed {:?}",
+                serial_number, err
+            );
+        }
+        Ok(response)
+    }
+}
+
+// Evaluates the alert rules of the group, opens an incident if the batch calls for one, and
+// publishes the event
+async fn follow_up_saved_temperatures<R>(
+    repo: &R,
+    aggregate: &DeviceAggregate,
+    since: DateTime<Utc>,
+    saved: &[DeviceTemperature],
+    response: &Response,
+) -> Result<(), Error>
+where
+    R: TDeviceGroupQuery + TAlertQuery + TAlertPersist + TIncidentPersist + TNotificationOutbox,
+{
+    let group = TDeviceGroupQuery::get(repo, &aggregate.device_group_serial_number).await?;
+    evaluate_alert_rules(repo, &group, aggregate, since).await?;
+    if let Some(mut incident) =
+        IncidentAggregate::open_for_batch(&group, &aggregate.serial_number, saved)
+    {
+        TIncidentPersist::add(repo, &mut incident).await?;
+    }
+    if let Response::TemperaturesSaved { saved, skipped } = *response {
+        repo.publish(DeviceEvent::TemperaturesSaved {
+            serial_number: aggregate.serial_number.clone(),
+            device_group_serial: group.serial_number.clone(),
+            saved,
+            skipped,
+        })
+        .await?;
+    }
+    Ok(())
+}
+
+// Raises an alert for each new breach of the rules of the group the device is in, and follows
+// up on the ones already raised, resolving them once readings are back within the threshold
+async fn evaluate_alert_rules<R>(
+    repo: &R,
+    group: &DeviceGroupAggregate,
+    aggregate: &DeviceAggregate,
+    since: DateTime<Utc>,
+) -> Result<(), Error>
+where
+    R: TAlertQuery + TAlertPersist,
+{
+    if group.alert_rules.is_empty() {
+        return Ok(());
+    }
+
+    let mut alerts = repo.list_by_device(&aggregate.serial_number).await?;
+    for rule in &group.alert_rules {
+        for breach in aggregate.breaches_since(rule, since) {
+            match alerts
+                .iter_mut()
+                .find(|alert| alert.is_raised_for(rule.rule_id, &breach))
+            {
+                Some(alert) => {
+                    alert.follow(breach);
+                    TAlertPersist::update(repo, alert).await?;
+                }
+                None => {
+                    let mut alert = AlertAggregate::new(
+                        &group.serial_number,
+                        &aggregate.serial_number,
+                        rule,
+                        breach,
+                    );
+                    TAlertPersist::add(repo, &mut alert).await?;
+                    alerts.push(alert);
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+impl<R> CommandHandler<ImportDeviceReadings, R>
+where
+    R: TDevicePersist + TDeviceQuery,
+{
+    // Each device is updated once with all of its valid rows. A device that cannot take readings
+    // rejects all of its rows without affecting the other devices in the import.
+    pub async fn handle(self) -> Result<Response, Error> {
+        let mut readings_by_device: BTreeMap<String, Vec<ImportedReading>> = BTreeMap::new();
+        for reading in self.command.readings {
+            readings_by_device
+                .entry(reading.serial_number.clone())
+                .or_default()
+                .push(reading);
+        }
+
+        let (mut imported, mut skipped) = (0, 0);
+        let mut errors = self.command.rejected_rows;
+        for (serial_number, readings) in readings_by_device {
+            let lines = readings.iter().map(|r| r.line).collect::<Vec<_>>();
+            let outcome = async {
+                let mut aggregate = self.repo.get(&serial_number).await?;
+                let outcome = aggregate.import_readings(readings)?;
+                self.repo.update(&mut aggregate).await?;
+                Ok::<_, Error>(outcome)
+            }
+            .await;
+
+            match outcome {
+                Ok((device_imported, device_skipped, device_errors)) => {
+                    imported += device_imported;
+                    skipped += device_skipped;
+                    errors.extend(device_errors);
+                }
+                Err(error) => errors.extend(lines.into_iter().map(|line| ImportRowError {
+                    line,
+                    error: error.clone(),
+                })),
+            }
+        }
+        errors.sort_by_key(|error| error.line);
+
+        Ok(Response::ReadingsImported {
+            imported,
+            skipped,
+            errors,
+        })
+    }
+}
+
+impl<R> CommandHandler<DetectOfflineDevices, R>
+where
+    R: TDevicePersist + TDeviceQuery,
+{
+    // Returns the devices that have just gone offline
+    pub async fn handle(self) -> Result<Vec<DeviceAggregate>, Error> {
+        let mut offline = vec![];
+        for candidate in self.repo.list().await? {
+            if !candidate
+                .clone()
+                .detect_offline(self.command.now, self.command.interval_multiple)
+            {
+                continue;
+            }
+            // Fetched again so that readings saved since the listing are not overwritten
+            let mut aggregate = self.repo.get(&candidate.serial_number).await?;
+            if aggregate.detect_offline(self.command.now, self.command.interval_multiple) {
+                self.repo.update(&mut aggregate).await?;
+                offline.push(aggregate);
+            }
+        }
+        Ok(offline)
+    }
+}
+
+impl<R> CommandHandler<TransferDevice, R>
+where
+    R: TDevicePersist + TDeviceQuery + TDeviceGroupQuery,
+{
+    pub async fn handle(self) -> Result<(DeviceAggregate, DeviceGroupAggregate), Error> {
+        // Validate if group actually exists
+        let group = TDeviceGroupQuery::get(&self.repo, &self.command.device_group_serial).await?;
+
+        let mut aggregate = TDeviceQuery::get(&self.repo, &self.command.serial_number).await?;
+        aggregate.transfer(self.command.device_group_serial)?;
+        self.repo.update(&mut aggregate).await?;
+        Ok((aggregate, group))
+    }
+}
+
+impl<R> CommandHandler<UpdateDeviceMetadata, R>
+where
+    R: TDevicePersist + TDeviceQuery,
+{
+    pub async fn handle(self) -> Result<DeviceAggregate, Error> {
+        let mut aggregate = self.repo.get(&self.command.serial_number).await?;
+        aggregate.update_metadata(self.command);
+        self.repo.update(&mut aggregate).await?;
+        Ok(aggregate)
+    }
+}
+
+impl<R> CommandHandler<CalibrateDevice, R>
+where
+    R: TDevicePersist + TDeviceQuery,
+{
+    pub async fn handle(self) -> Result<DeviceAggregate, Error> {
+        let mut aggregate = self.repo.get(&self.command.serial_number).await?;
+        aggregate.calibrate(self.command)?;
+        self.repo.update(&mut aggregate).await?;
+        Ok(aggregate)
+    }
+}
+
+impl<R> CommandHandler<SetDeviceCodec, R>
 where
     R: TDevicePersist + TDeviceQuery,
+{
+    pub async fn handle(self) -> Result<DeviceAggregate, Error> {
+        let mut aggregate = self.repo.get(&self.command.serial_number).await?;
+        aggregate.set_codec(self.command.codec)?;
+        self.repo.update(&mut aggregate).await?;
+        Ok(aggregate)
+    }
+}
+
+impl<R> CommandHandler<SetDeviceModelCodec, R>
+where
+    R: TDeviceModelCodecStore,
 {
     pub async fn handle(self) -> Result<Response, Error> {
+        self.command.codec.validate()?;
+        Ok(self
+            .repo
+            .set_model_codec(&self.command.model, self.command.codec)
+            .await?
+            .into())
+    }
+}
+
+impl<R> CommandHandler<SuspendDevice, R>
+where
+    R: TDevicePersist + TDeviceQuery,
+{
+    pub async fn handle(self) -> Result<DeviceAggregate, Error> {
+        let mut aggregate = self.repo.get(&self.command.serial_number).await?;
+        aggregate.suspend()?;
+        self.repo.update(&mut aggregate).await?;
+        Ok(aggregate)
+    }
+}
+
+impl<R> CommandHandler<ReactivateDevice, R>
+where
+    R: TDevicePersist + TDeviceQuery,
+{
+    pub async fn handle(self) -> Result<DeviceAggregate, Error> {
         let mut aggregate = self.repo.get(&self.command.serial_number).await?;
-        aggregate.save_temperatures(self.command)?;
-        Ok(self.repo.update(&mut aggregate).await?.into())
+        aggregate.reactivate()?;
+        self.repo.update(&mut aggregate).await?;
+        Ok(aggregate)
+    }
+}
+
+impl<R> CommandHandler<DecommissionDevice, R>
+where
+    R: TDevicePersist + TDeviceQuery,
+{
+    pub async fn handle(self) -> Result<DeviceAggregate, Error> {
+        let mut aggregate = self.repo.get(&self.command.serial_number).await?;
+        aggregate.decommission()?;
+        self.repo.update(&mut aggregate).await?;
+        Ok(aggregate)
+    }
+}
+
+impl<R> QueryHandler<ListDevicesQuery, R>
+where
+    R: TDeviceQuery,
+{
+    // Lists devices currently in the group
+    pub async fn handle(self) -> Result<Vec<DeviceAggregate>, Error> {
+        let aggregates = self
+            .repo
+            .list_by_group(&self.query.device_group_serial)
+            .await?;
+        Ok(aggregates
+            .into_iter()
+            .filter(|aggregate| {
+                aggregate.device_group_serial_number == self.query.device_group_serial
+                    && aggregate.has_tags(&self.query.tags)
+            })
+            .collect())
     }
 }
 
@@ -45,35 +387,309 @@ where
 {
     pub async fn handle(self) -> Result<(DeviceAggregate, f32), Error> {
         let aggregate = self.repo.get(&self.query.serial_number).await?;
-        let average = aggregate
-            .get_average_temperature_during_period(self.query.start_date, self.query.end_date);
+        let average = match self.query.metric {
+            TemperatureMetric::Mean => aggregate.get_average_temperature_during_period(
+                self.query.channel,
+                self.query.start_date,
+                self.query.end_date,
+                self.query.exclude_flagged,
+            ),
+            TemperatureMetric::MeanKinetic { activation_energy } => aggregate
+                .get_mean_kinetic_temperature_during_period(
+                    self.query.channel,
+                    self.query.start_date,
+                    self.query.end_date,
+                    activation_energy,
+                    self.query.exclude_flagged,
+                ),
+        };
 
         Ok((aggregate, average))
     }
 }
 
+impl<R> QueryHandler<GetDeviceTemperatureStatisticsDuringPeriodQuery, R>
+where
+    R: TDeviceQuery,
+{
+    pub async fn handle(self) -> Result<(DeviceAggregate, TemperatureStatistics), Error> {
+        let aggregate = self.repo.get(&self.query.serial_number).await?;
+        let statistics = aggregate.get_temperature_statistics_during_period(
+            self.query.channel,
+            self.query.start_date,
+            self.query.end_date,
+            &self.query.percentiles,
+            self.query.exclude_flagged,
+        );
+
+        Ok((aggregate, statistics))
+    }
+}
+
+impl<R> QueryHandler<GetDeviceTemperatureSeriesDuringPeriodQuery, R>
+where
+    R: TDeviceQuery,
+{
+    pub async fn handle(self) -> Result<(DeviceAggregate, Vec<TemperatureBucket>), Error> {
+        let aggregate = self.repo.get(&self.query.serial_number).await?;
+        let series = aggregate.get_temperature_series_during_period(
+            self.query.channel,
+            self.query.start_date,
+            self.query.end_date,
+            self.query.bucket_size,
+            self.query.exclude_flagged,
+        );
+
+        Ok((aggregate, series))
+    }
+}
+
+impl<R> QueryHandler<GetDeviceSmoothedTemperaturesDuringPeriodQuery, R>
+where
+    R: TDeviceQuery,
+{
+    pub async fn handle(self) -> Result<(DeviceAggregate, Vec<SmoothedReading>), Error> {
+        let aggregate = self.repo.get(&self.query.serial_number).await?;
+        let readings = aggregate.get_smoothed_temperatures_during_period(
+            self.query.channel,
+            self.query.start_date,
+            self.query.end_date,
+            self.query.smoothing,
+            self.query.exclude_flagged,
+        );
+
+        Ok((aggregate, readings))
+    }
+}
+
+impl<R> QueryHandler<GetDeviceTemperatureTrendQuery, R>
+where
+    R: TDeviceQuery,
+{
+    pub async fn handle(self) -> Result<(DeviceAggregate, Option<TemperatureTrend>), Error> {
+        let aggregate = self.repo.get(&self.query.serial_number).await?;
+        let trend = aggregate.get_temperature_trend(
+            self.query.channel,
+            self.query.reading_count,
+            self.query.horizon,
+            self.query.threshold,
+            self.query.exclude_flagged,
+        );
+
+        Ok((aggregate, trend))
+    }
+}
+
+impl<R> QueryHandler<GetDeviceGapsDuringPeriodQuery, R>
+where
+    R: TDeviceQuery,
+{
+    pub async fn handle(self) -> Result<(DeviceAggregate, Vec<ReadingGap>), Error> {
+        let aggregate = self.repo.get(&self.query.serial_number).await?;
+        let gaps = aggregate.get_gaps_during_period(
+            self.query.channel,
+            self.query.start_date,
+            self.query.end_date,
+        );
+        Ok((aggregate, gaps))
+    }
+}
+
+impl<R> QueryHandler<GetDeviceExcursionsDuringPeriodQuery, R>
+where
+    R: TDeviceQuery + TDeviceGroupQuery,
+{
+    pub async fn handle(
+        self,
+    ) -> Result<(DeviceAggregate, AllowedTemperatureRange, Vec<Excursion>), Error> {
+        let aggregate = TDeviceQuery::get(&self.repo, &self.query.serial_number).await?;
+        let allowed_range = match self.query.allowed_range {
+            Some(allowed_range) => allowed_range,
+            None => {
+                let group =
+                    TDeviceGroupQuery::get(&self.repo, &aggregate.device_group_serial_number)
+                        .await?;
+                allowed_range_of(&group)?
+            }
+        };
+        let excursions = aggregate.get_excursions_during_period(
+            self.query.channel,
+            self.query.start_date,
+            self.query.end_date,
+            &allowed_range,
+            self.query.exclude_flagged,
+        );
+        Ok((aggregate, allowed_range, excursions))
+    }
+}
+
+impl<R> QueryHandler<GetDeviceFlaggedReadingsDuringPeriodQuery, R>
+where
+    R: TDeviceQuery,
+{
+    pub async fn handle(self) -> Result<(DeviceAggregate, Vec<FlaggedReading>), Error> {
+        let aggregate = self.repo.get(&self.query.serial_number).await?;
+        let readings = aggregate
+            .get_flagged_readings_during_period(self.query.start_date, self.query.end_date);
+        Ok((aggregate, readings))
+    }
+}
+
+// Returns each device with its average temperature, by the given metric, and number of gaps,
+// along with the average of the group over the readings of every device
 impl<R> QueryHandler<GetDeviceGroupAverageTemperatureDuringPeriodQuery, R>
 where
     R: TDeviceQuery,
 {
-    pub async fn handle(self) -> Result<Vec<(DeviceAggregate, f32)>, Error> {
+    pub async fn handle(
+        self,
+    ) -> Result<(Vec<(DeviceAggregate, f32, usize)>, GroupTemperature), Error> {
         let aggregates = self
             .repo
             .list_by_group(&self.query.device_group_serial)
-            .await?;
-        Ok(aggregates
+            .await?
+            .into_iter()
+            .filter(|aggregate| {
+                self.query.include_decommissioned
+                    || aggregate.status != DeviceStatus::Decommissioned
+            })
+            .filter(|aggregate| aggregate.has_tags(&self.query.tags))
+            .collect::<Vec<_>>();
+
+        let group_temperature = GroupTemperature::of(
+            self.query.metric,
+            self.query.weighting,
+            &aggregates
+                .iter()
+                .map(|aggregate| {
+                    aggregate.get_weighted_temperatures_in_group_during_period(
+                        &self.query.device_group_serial,
+                        self.query.channel,
+                        self.query.start_date,
+                        self.query.end_date,
+                        self.query.weighting,
+                        self.query.exclude_flagged,
+                    )
+                })
+                .collect::<Vec<_>>(),
+        );
+        let devices = aggregates
+            .into_iter()
+            .map(|aggregate| {
+                let average = match self.query.metric {
+                    TemperatureMetric::Mean => aggregate
+                        .get_average_temperature_in_group_during_period(
+                            &self.query.device_group_serial,
+                            self.query.channel,
+                            self.query.start_date,
+                            self.query.end_date,
+                            self.query.exclude_flagged,
+                        ),
+                    TemperatureMetric::MeanKinetic { activation_energy } => aggregate
+                        .get_mean_kinetic_temperature_in_group_during_period(
+                            &self.query.device_group_serial,
+                            self.query.channel,
+                            self.query.start_date,
+                            self.query.end_date,
+                            activation_energy,
+                            self.query.exclude_flagged,
+                        ),
+                };
+                let gap_count = aggregate
+                    .get_gaps_in_group_during_period(
+                        &self.query.device_group_serial,
+                        self.query.channel,
+                        self.query.start_date,
+                        self.query.end_date,
+                    )
+                    .len();
+                (aggregate, average, gap_count)
+            })
+            .collect();
+        Ok((devices, group_temperature))
+    }
+}
+
+// Evaluates each device against the given range, or else the one of the group. The group passes
+// when every device does.
+impl<R> QueryHandler<GetDeviceGroupComplianceReportQuery, R>
+where
+    R: TDeviceQuery + TDeviceGroupQuery,
+{
+    pub async fn handle(
+        self,
+    ) -> Result<
+        (
+            DeviceGroupAggregate,
+            AllowedTemperatureRange,
+            Vec<(DeviceAggregate, ComplianceReport)>,
+        ),
+        Error,
+    > {
+        let group = TDeviceGroupQuery::get(&self.repo, &self.query.device_group_serial).await?;
+        let allowed_range = match self.query.allowed_range.clone() {
+            Some(allowed_range) => allowed_range,
+            None => allowed_range_of(&group)?,
+        };
+
+        let reports = self
+            .repo
+            .list_by_group(&self.query.device_group_serial)
+            .await?
+            .into_iter()
+            .filter(|aggregate| {
+                self.query.include_decommissioned
+                    || aggregate.status != DeviceStatus::Decommissioned
+            })
+            .filter(|aggregate| aggregate.has_tags(&self.query.tags))
+            .map(|aggregate| {
+                let report =
+                    aggregate.get_compliance_in_group_during_period(&self.query, &allowed_range);
+                (aggregate, report)
+            })
+            .collect();
+        Ok((group, allowed_range, reports))
+    }
+}
+
+impl<R> QueryHandler<GetDeviceGroupDiagnosticsQuery, R>
+where
+    R: TDeviceQuery,
+{
+    pub async fn handle(self) -> Result<Vec<(DeviceAggregate, SensorDiagnostics)>, Error> {
+        let mut diagnostics = self
+            .repo
+            .list_by_group(&self.query.device_group_serial)
+            .await?
             .into_iter()
+            .filter(|aggregate| {
+                aggregate.device_group_serial_number == self.query.device_group_serial
+            })
             .map(|aggregate| {
-                let average = aggregate.get_average_temperature_during_period(
+                let diagnostics = aggregate.get_diagnostics_during_period(
+                    self.query.channel,
                     self.query.start_date,
                     self.query.end_date,
                 );
-                (aggregate, average)
+                (aggregate, diagnostics)
             })
-            .collect())
+            .filter(|(_, diagnostics)| diagnostics.faulty_ratio >= self.query.min_faulty_ratio)
+            .collect::<Vec<_>>();
+        diagnostics.sort_by(|(_, a), (_, b)| b.faulty_ratio.total_cmp(&a.faulty_ratio));
+        Ok(diagnostics)
     }
 }
 
+fn allowed_range_of(group: &DeviceGroupAggregate) -> Result<AllowedTemperatureRange, Error> {
+    group.allowed_range.clone().ok_or_else(|| {
+        eprintln!(
+            "[ERROR] No allowed range given for group {}",
+            group.serial_number
+        );
+        Error::SchemaError
+    })
+}
+
 #[cfg(test)]
 mod test_device_handler {
     use chrono::{Duration, Utc};
@@ -81,14 +697,42 @@ mod test_device_handler {
     use crate::{
         adapters::database::mock_db::MockDb,
         domain::{
+            alert::{query::ListAlertsQuery, AlertStatus},
             device::{
-                commands::{RegisterDevice, SaveDeviceTemperature},
+                codec::{Endianness, PayloadCodec},
+                commands::{
+                    default_channels, CalibrateDevice, DecommissionDevice, DetectOfflineDevices,
+                    ImportDeviceReadings, ImportedReading, RegisterDevice, SaveDeviceTemperature,
+                    SetDeviceModelCodec, SuspendDevice, TransferDevice, UpdateDeviceMetadata,
+                },
                 query::{
                     GetDeviceAverageTemperatureDuringPeriodQuery,
+                    GetDeviceExcursionsDuringPeriodQuery,
+                    GetDeviceFlaggedReadingsDuringPeriodQuery, GetDeviceGapsDuringPeriodQuery,
                     GetDeviceGroupAverageTemperatureDuringPeriodQuery,
+                    GetDeviceGroupComplianceReportQuery, GetDeviceGroupDiagnosticsQuery,
+                    GetDeviceSmoothedTemperaturesDuringPeriodQuery,
+                    GetDeviceTemperatureSeriesDuringPeriodQuery,
+                    GetDeviceTemperatureStatisticsDuringPeriodQuery,
+                    GetDeviceTemperatureTrendQuery, ListDevicesQuery,
+                },
+                statistics::{ReadingWeighting, Smoothing, TemperatureMetric},
+                DeviceCounts, MeasurementChannel,
+            },
+            device_group::{
+                alert_rule::ThresholdCondition,
+                commands::{AddAlertRule, SetAllowedTemperatureRange},
+                query::ListDeviceGroupsQuery,
+                AllowedTemperatureRange,
+            },
+            incident::{
+                commands::{
+                    AcknowledgeIncident, AnnotateIncident, AssignIncident, SilenceIncidents,
                 },
+                query::ListIncidentsQuery,
+                IncidentStatus,
             },
-            response::Error,
+            response::{Error, ImportRowError, Response},
         },
         services::handlers::{
             device_group::test_device_handler::group_creating_helper, CommandHandler, QueryHandler,
@@ -99,6 +743,7 @@ mod test_device_handler {
         let cmd = RegisterDevice {
             serial_number: serial_number.to_string(),
             device_group_serial: device_group_serial.to_string(),
+            ..Default::default()
         };
         let handler = CommandHandler::new(cmd, MockDb);
         handler.handle().await.unwrap();
@@ -111,6 +756,8 @@ mod test_device_handler {
             interval: 300,
             temperatures: temperatures.to_string(),
             registered_at: Utc::now(),
+            channels: default_channels(),
+            idempotency_key: None,
         };
         let handler = CommandHandler::new(cmd, db.clone());
         handler.handle().await.unwrap();
@@ -126,6 +773,7 @@ mod test_device_handler {
         let cmd = RegisterDevice {
             serial_number: "C48302DDL".to_string(),
             device_group_serial: "A6".to_string(),
+            ..Default::default()
         };
         let handler = CommandHandler::new(cmd, db.clone());
         let res = handler.handle().await;
@@ -147,6 +795,7 @@ mod test_device_handler {
         let cmd = RegisterDevice {
             serial_number: "C8302DDF".to_string(),
             device_group_serial: "B1".to_string(),
+            ..Default::default()
         };
         let handler = CommandHandler::new(cmd, db.clone());
         handler.handle().await.unwrap();
@@ -171,6 +820,8 @@ mod test_device_handler {
             interval: 300,
             temperatures: "FFFE00010003FFFE00010003FFFE00010003FFFE00010003".to_string(),
             registered_at: Utc::now() - Duration::minutes(10),
+            channels: default_channels(),
+            idempotency_key: None,
         };
         let handler = CommandHandler::new(cmd, db.clone());
         handler.handle().await.unwrap();
@@ -180,6 +831,87 @@ mod test_device_handler {
         assert_eq!(aggregate.temperatures.len(), 12);
     }
 
+    #[tokio::test]
+    async fn test_save_temperatures_with_idempotency_key() {
+        use crate::domain::device::repository::TDeviceQuery;
+        //GIVEN
+        group_creating_helper("A4").await;
+        device_create_helper("A4", "C48302DDI").await;
+        let db = MockDb;
+        let registered_at = Utc::now() - Duration::minutes(10);
+        let cmd = |temperatures: &str| SaveDeviceTemperature {
+            serial_number: "C48302DDI".to_string(),
+            interval: 300,
+            temperatures: temperatures.to_string(),
+            registered_at,
+            channels: default_channels(),
+            idempotency_key: Some("retry-1".to_string()),
+        };
+        CommandHandler::new(cmd("FFFE00010003"), db.clone())
+            .handle()
+            .await
+            .unwrap();
+
+        //WHEN
+        let replayed = CommandHandler::new(cmd("FFFE00010003"), db.clone())
+            .handle()
+            .await
+            .unwrap();
+
+        //THEN
+        // the original response is returned as if it were saved for the first time
+        assert!(matches!(
+            replayed,
+            Response::TemperaturesSaved {
+                saved: 3,
+                skipped: 0
+            }
+        ));
+        let aggregate = db.get("C48302DDI").await.unwrap();
+        assert_eq!(aggregate.temperatures.len(), 3);
+    }
+
+    #[tokio::test]
+    async fn test_save_temperatures_with_failing_follow_up() {
+        use crate::domain::device::{
+            repository::{TDevicePersist, TDeviceQuery},
+            DeviceAggregate,
+        };
+        //GIVEN
+        // A device whose group is gone, so the alert rules can't be looked up
+        let db = MockDb;
+        let mut aggregate = DeviceAggregate::new(RegisterDevice {
+            serial_number: "C48302DDJ".to_string(),
+            device_group_serial: "A5-GONE".to_string(),
+            ..Default::default()
+        });
+        db.add(&mut aggregate).await.unwrap();
+        let cmd = || SaveDeviceTemperature {
+            serial_number: "C48302DDJ".to_string(),
+            interval: 300,
+            temperatures: "00010003".to_string(),
+            registered_at: Utc::now() - Duration::minutes(10),
+            channels: default_channels(),
+            idempotency_key: Some("retry-2".to_string()),
+        };
+
+        //WHEN
+        let saved = CommandHandler::new(cmd(), db.clone()).handle().await;
+        let replayed = CommandHandler::new(cmd(), db.clone()).handle().await;
+
+        //THEN
+        for response in [saved, replayed] {
+            assert!(matches!(
+                response,
+                Ok(Response::TemperaturesSaved {
+                    saved: 2,
+                    skipped: 0
+                })
+            ));
+        }
+        assert_eq!(db.get("C48302DDJ").await.unwrap().temperatures.len(), 2);
+    }
+
     #[tokio::test]
     async fn test_get_device_average_temperature() {
         //GIVEN
@@ -192,8 +924,11 @@ mod test_device_handler {
         //WHEN
         let query = GetDeviceAverageTemperatureDuringPeriodQuery {
             serial_number: "R48302DDK".to_string(),
+            channel: MeasurementChannel::Temperature,
             start_date: Utc::now() - Duration::minutes(300),
             end_date: Utc::now() + Duration::minutes(300),
+            metric: TemperatureMetric::Mean,
+            exclude_flagged: false,
         };
         let handler = QueryHandler::new(query, db);
         let (device, average) = handler.handle().await.unwrap();
@@ -203,33 +938,1145 @@ mod test_device_handler {
     }
 
     #[tokio::test]
-    async fn test_get_device_group_average_temperature() {
+    async fn test_get_mean_kinetic_temperature() {
         //GIVEN
-        group_creating_helper("R1").await;
-        device_create_helper("R1", "R18302DDK").await;
-        device_create_helper("R1", "R28302DDK").await;
-        save_temperatures_helper("R18302DDK", "FFFE00010003FFFE").await;
-        save_temperatures_helper("R28302DDK", "FFFE000100030001").await;
+        group_creating_helper("MK1").await;
+        device_create_helper("MK1", "MK18302DDK").await;
+        save_temperatures_helper("MK18302DDK", "00050019").await;
+        let metric = TemperatureMetric::MeanKinetic {
+            activation_energy: 83.144,
+        };
+
+        //WHEN
+        let query = GetDeviceAverageTemperatureDuringPeriodQuery {
+            serial_number: "MK18302DDK".to_string(),
+            channel: MeasurementChannel::Temperature,
+            start_date: Utc::now() - Duration::minutes(300),
+            end_date: Utc::now() + Duration::minutes(300),
+            metric,
+            exclude_flagged: false,
+        };
+        let (_, device_mkt) = QueryHandler::new(query, MockDb).handle().await.unwrap();
+
+        let query = GetDeviceGroupAverageTemperatureDuringPeriodQuery {
+            device_group_serial: "MK1".to_string(),
+            channel: MeasurementChannel::Temperature,
+            start_date: Utc::now() - Duration::minutes(300),
+            end_date: Utc::now() + Duration::minutes(300),
+            include_decommissioned: false,
+            tags: vec![],
+            metric,
+            weighting: ReadingWeighting::PerReading,
+            exclude_flagged: false,
+        };
+        let devices = QueryHandler::new(query, MockDb).handle().await.unwrap().0;
+
+        //THEN
+        assert_eq!((device_mkt * 100.0).round(), 1970.0);
+        assert_eq!(devices.len(), 1);
+        assert_eq!(devices[0].1, device_mkt);
+    }
+
+    #[tokio::test]
+    async fn test_get_device_temperature_statistics() {
+        //GIVEN
+        group_creating_helper("S1").await;
+        device_create_helper("S1", "S48302DDK").await;
+        save_temperatures_helper("S48302DDK", "FFFE000100030005").await;
 
         let db = MockDb;
 
         //WHEN
-        let query = GetDeviceGroupAverageTemperatureDuringPeriodQuery {
-            device_group_serial: "R1".to_string(),
+        let query = GetDeviceTemperatureStatisticsDuringPeriodQuery {
+            serial_number: "S48302DDK".to_string(),
+            channel: MeasurementChannel::Temperature,
             start_date: Utc::now() - Duration::minutes(300),
             end_date: Utc::now() + Duration::minutes(300),
+            percentiles: vec![50.0, 100.0],
+            exclude_flagged: false,
         };
         let handler = QueryHandler::new(query, db);
-        let result = handler.handle().await.unwrap();
+        let (device, stats) = handler.handle().await.unwrap();
 
         //THEN
-        assert_eq!(result.len(), 2);
-        let first_device = result.first().unwrap();
-        assert_eq!(first_device.0.serial_number, "R18302DDK");
-        assert_eq!(first_device.1, 0.0);
+        assert_eq!(device.serial_number, "S48302DDK".to_string());
+        assert_eq!(stats.count, 4);
+        assert_eq!(stats.min, Some(-2.0));
+        assert_eq!(stats.max, Some(5.0));
+        assert_eq!(stats.mean, Some(1.75));
+        assert_eq!(
+            stats
+                .percentiles
+                .iter()
+                .map(|p| p.value)
+                .collect::<Vec<_>>(),
+            vec![2.0, 5.0]
+        );
+    }
 
-        let second_device = result.last().unwrap();
+    #[tokio::test]
+    async fn test_get_device_temperature_series() {
+        //GIVEN
+        group_creating_helper("S2").await;
+        device_create_helper("S2", "S58302DDK").await;
+        // readings are 5 minutes apart
+        save_temperatures_helper("S58302DDK", "FFFE000100030005").await;
+
+        let db = MockDb;
+
+        //WHEN
+        let start_date = Utc::now() - Duration::minutes(1);
+        let query = GetDeviceTemperatureSeriesDuringPeriodQuery {
+            serial_number: "S58302DDK".to_string(),
+            channel: MeasurementChannel::Temperature,
+            start_date,
+            end_date: Utc::now() + Duration::minutes(300),
+            bucket_size: Duration::minutes(10),
+            exclude_flagged: false,
+        };
+        let handler = QueryHandler::new(query, db);
+        let (_, series) = handler.handle().await.unwrap();
+
+        //THEN
+        assert_eq!(series.len(), 2);
+        assert_eq!(series[0].bucket_start, start_date);
+        assert_eq!(
+            (series[0].count, series[0].min, series[0].max),
+            (2, -2.0, 1.0)
+        );
+        assert_eq!(series[0].mean, -0.5);
+        assert_eq!(
+            (series[1].count, series[1].min, series[1].max),
+            (2, 3.0, 5.0)
+        );
+        assert_eq!(series[1].mean, 4.0);
+    }
+
+    #[tokio::test]
+    async fn test_get_device_smoothed_temperatures() {
+        //GIVEN
+        group_creating_helper("SM1").await;
+        device_create_helper("SM1", "SM18302DDK").await;
+        save_temperatures_helper("SM18302DDK", "0004000800060028000A").await;
+
+        //WHEN
+        let query = GetDeviceSmoothedTemperaturesDuringPeriodQuery {
+            serial_number: "SM18302DDK".to_string(),
+            channel: MeasurementChannel::Temperature,
+            start_date: Utc::now() - Duration::minutes(1),
+            end_date: Utc::now() + Duration::minutes(300),
+            smoothing: Smoothing::MovingAverage { window: 2 },
+            exclude_flagged: true,
+        };
+        let (_, readings) = QueryHandler::new(query, MockDb).handle().await.unwrap();
+
+        //THEN
+        // The jump to 40 is left out
+        assert_eq!(
+            readings
+                .iter()
+                .map(|reading| (reading.value, reading.smoothed))
+                .collect::<Vec<_>>(),
+            vec![(4.0, 4.0), (8.0, 6.0), (6.0, 7.0), (10.0, 8.0)]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_get_device_temperature_trend() {
+        //GIVEN
+        group_creating_helper("TR1").await;
+        device_create_helper("TR1", "TR18302DDK").await;
+        // warming up by a degree every 5 minutes, from now on
+        save_temperatures_helper("TR18302DDK", "00000003000400050006").await;
+
+        //WHEN
+        let query = GetDeviceTemperatureTrendQuery {
+            serial_number: "TR18302DDK".to_string(),
+            channel: MeasurementChannel::Temperature,
+            reading_count: 4,
+            horizon: Duration::minutes(10),
+            threshold: Some(8.0),
+            exclude_flagged: false,
+        };
+        let (_, trend) = QueryHandler::new(query, MockDb).handle().await.unwrap();
+
+        //THEN
+        // The first reading is left out of the fit
+        let trend = trend.unwrap();
+        assert_eq!(trend.reading_count, 4);
+        assert!((trend.slope_per_minute - 0.2).abs() < 1e-4);
+        assert!((trend.current_value - 2.0).abs() < 1e-2);
+        assert!((trend.projected_value - 4.0).abs() < 1e-2);
+        assert!((trend.time_to_threshold_secs.unwrap() - 30 * 60).abs() <= 1);
+    }
+
+    #[tokio::test]
+    async fn test_get_device_group_average_temperature() {
+        //GIVEN
+        group_creating_helper("R2").await;
+        device_create_helper("R2", "R18302DDK").await;
+        device_create_helper("R2", "R28302DDK").await;
+        save_temperatures_helper("R18302DDK", "FFFE00010003FFFE").await;
+        save_temperatures_helper("R28302DDK", "FFFE000100030001").await;
+
+        let db = MockDb;
+
+        //WHEN
+        let query = GetDeviceGroupAverageTemperatureDuringPeriodQuery {
+            device_group_serial: "R2".to_string(),
+            channel: MeasurementChannel::Temperature,
+            start_date: Utc::now() - Duration::minutes(300),
+            end_date: Utc::now() + Duration::minutes(300),
+            include_decommissioned: false,
+            tags: vec![],
+            metric: TemperatureMetric::Mean,
+            weighting: ReadingWeighting::PerReading,
+            exclude_flagged: false,
+        };
+        let handler = QueryHandler::new(query, db);
+        let result = handler.handle().await.unwrap().0;
+
+        //THEN
+        assert_eq!(result.len(), 2);
+        let first_device = result.first().unwrap();
+        assert_eq!(first_device.0.serial_number, "R18302DDK");
+        assert_eq!(first_device.1, 0.0);
+
+        let second_device = result.last().unwrap();
         assert_eq!(second_device.0.serial_number, "R28302DDK");
         assert_eq!(second_device.1, 0.75);
+        // Nothing was saved before the readings of the helper
+        assert!(result.iter().all(|device| device.2 == 1));
+    }
+
+    #[tokio::test]
+    async fn test_get_device_gaps() {
+        //GIVEN
+        group_creating_helper("P1").await;
+        device_create_helper("P1", "P18302DDK").await;
+        let registered_at = Utc::now() - Duration::hours(2);
+        for (temperatures, minutes) in [("00010002", 0), ("0003", 30)] {
+            let cmd = SaveDeviceTemperature {
+                serial_number: "P18302DDK".to_string(),
+                interval: 300,
+                temperatures: temperatures.to_string(),
+                registered_at: registered_at + Duration::minutes(minutes),
+                channels: default_channels(),
+                idempotency_key: None,
+            };
+            CommandHandler::new(cmd, MockDb).handle().await.unwrap();
+        }
+
+        //WHEN
+        let query = GetDeviceGapsDuringPeriodQuery {
+            serial_number: "P18302DDK".to_string(),
+            channel: MeasurementChannel::Temperature,
+            start_date: registered_at,
+            end_date: registered_at + Duration::minutes(30),
+        };
+        let (aggregate, gaps) = QueryHandler::new(query, MockDb).handle().await.unwrap();
+
+        //THEN
+        assert_eq!(aggregate.reporting_interval, Some(300));
+        assert_eq!(gaps.len(), 1);
+        assert_eq!(gaps[0].start, registered_at + Duration::minutes(5));
+        assert_eq!(gaps[0].end, registered_at + Duration::minutes(30));
+        assert_eq!(gaps[0].missing_readings, 4);
+    }
+
+    #[tokio::test]
+    async fn test_get_device_group_average_temperature_by_weighting() {
+        //GIVEN
+        group_creating_helper("GW1").await;
+        let registered_at = Utc::now() - Duration::hours(1);
+        // One device reporting every minute at 2 degrees, the other every 10 minutes at 8
+        for (serial_number, interval, temperatures) in [
+            ("GW18302DDA", 60, "0002".repeat(10)),
+            ("GW18302DDB", 600, "0008".to_string()),
+        ] {
+            device_create_helper("GW1", serial_number).await;
+            let cmd = SaveDeviceTemperature {
+                serial_number: serial_number.to_string(),
+                interval,
+                temperatures,
+                registered_at,
+                channels: default_channels(),
+                idempotency_key: None,
+            };
+            CommandHandler::new(cmd, MockDb).handle().await.unwrap();
+        }
+        let query = |weighting| GetDeviceGroupAverageTemperatureDuringPeriodQuery {
+            device_group_serial: "GW1".to_string(),
+            channel: MeasurementChannel::Temperature,
+            start_date: registered_at,
+            end_date: registered_at + Duration::minutes(10),
+            include_decommissioned: false,
+            tags: vec![],
+            metric: TemperatureMetric::Mean,
+            weighting,
+            exclude_flagged: false,
+        };
+
+        //WHEN
+        let mut group_averages = vec![];
+        for weighting in [
+            ReadingWeighting::PerReading,
+            ReadingWeighting::PerDevice,
+            ReadingWeighting::TimeWeighted,
+        ] {
+            let (devices, group) = QueryHandler::new(query(weighting), MockDb)
+                .handle()
+                .await
+                .unwrap();
+            assert_eq!(devices.len(), 2);
+            assert_eq!(group.reading_count, 11);
+            assert_eq!(group.device_count, 2);
+            group_averages.push(group.average_temperature);
+        }
+
+        //THEN
+        // Averaging the averages of each device only holds when weighing devices equally
+        assert!((group_averages[0] - 28.0 / 11.0).abs() < 1e-5);
+        assert_eq!(group_averages[1], 5.0);
+        assert_eq!(group_averages[2], 5.0);
+    }
+
+    #[tokio::test]
+    async fn test_get_device_group_compliance_report() {
+        //GIVEN
+        group_creating_helper("CP1").await;
+        let registered_at = Utc::now() - Duration::hours(1);
+        let query = |allowed_range| GetDeviceGroupComplianceReportQuery {
+            device_group_serial: "CP1".to_string(),
+            channel: MeasurementChannel::Temperature,
+            start_date: registered_at,
+            end_date: registered_at + Duration::minutes(20),
+            allowed_range,
+            min_coverage: 0.9,
+            include_decommissioned: false,
+            tags: vec![],
+            exclude_flagged: false,
+        };
+        for (serial_number, temperatures) in [
+            ("CP18302DDA", "0004000500060007"),
+            ("CP18302DDB", "00040009000A0005"),
+        ] {
+            device_create_helper("CP1", serial_number).await;
+            let cmd = SaveDeviceTemperature {
+                serial_number: serial_number.to_string(),
+                interval: 300,
+                temperatures: temperatures.to_string(),
+                registered_at,
+                channels: default_channels(),
+                idempotency_key: None,
+            };
+            CommandHandler::new(cmd, MockDb).handle().await.unwrap();
+        }
+        let without_range = QueryHandler::new(query(None), MockDb).handle().await;
+        assert!(matches!(without_range, Err(Error::SchemaError)));
+
+        //WHEN
+        let cmd = SetAllowedTemperatureRange {
+            device_group_serial: "CP1".to_string(),
+            min: 2.0,
+            max: 8.0,
+        };
+        CommandHandler::new(cmd, MockDb).handle().await.unwrap();
+        let (_, allowed_range, reports) = QueryHandler::new(query(None), MockDb)
+            .handle()
+            .await
+            .unwrap();
+
+        //THEN
+        assert_eq!(allowed_range.max, 8.0);
+        let (_, in_range) = &reports[0];
+        assert_eq!(in_range.time_in_range_secs, 20 * 60);
+        assert_eq!(in_range.coverage, 1.0);
+        assert!(in_range.passed);
+
+        let (_, with_excursion) = &reports[1];
+        assert_eq!(with_excursion.time_out_of_range_secs, 10 * 60);
+        assert_eq!(with_excursion.excursion_count, 1);
+        let excursion = with_excursion.worst_excursion.as_ref().unwrap();
+        assert_eq!(excursion.start, registered_at + Duration::minutes(5));
+        assert_eq!(excursion.end, registered_at + Duration::minutes(15));
+        assert_eq!(excursion.peak_value, 10.0);
+        assert!(!with_excursion.passed);
+
+        // A wider range given with the query prevails
+        let (_, _, reports) = QueryHandler::new(
+            query(Some(AllowedTemperatureRange {
+                min: 2.0,
+                max: 10.0,
+            })),
+            MockDb,
+        )
+        .handle()
+        .await
+        .unwrap();
+        assert!(reports.iter().all(|(_, report)| report.passed));
+    }
+
+    #[tokio::test]
+    async fn test_get_device_excursions() {
+        //GIVEN
+        group_creating_helper("EX1").await;
+        device_create_helper("EX1", "EX18302DDK").await;
+        let registered_at = Utc::now() - Duration::hours(1);
+        let cmd = SaveDeviceTemperature {
+            serial_number: "EX18302DDK".to_string(),
+            interval: 300,
+            temperatures: "00040009000A00050001".to_string(),
+            registered_at,
+            channels: default_channels(),
+            idempotency_key: None,
+        };
+        CommandHandler::new(cmd, MockDb).handle().await.unwrap();
+        let query = |allowed_range| GetDeviceExcursionsDuringPeriodQuery {
+            serial_number: "EX18302DDK".to_string(),
+            channel: MeasurementChannel::Temperature,
+            start_date: registered_at,
+            end_date: registered_at + Duration::minutes(30),
+            allowed_range,
+            exclude_flagged: false,
+        };
+        let without_range = QueryHandler::new(query(None), MockDb).handle().await;
+        assert!(matches!(without_range, Err(Error::SchemaError)));
+
+        //WHEN
+        let cmd = SetAllowedTemperatureRange {
+            device_group_serial: "EX1".to_string(),
+            min: 2.0,
+            max: 8.0,
+        };
+        CommandHandler::new(cmd, MockDb).handle().await.unwrap();
+        let (_, _, excursions) = QueryHandler::new(query(None), MockDb)
+            .handle()
+            .await
+            .unwrap();
+
+        //THEN
+        assert_eq!(excursions.len(), 2);
+        assert_eq!(excursions[0].start, registered_at + Duration::minutes(5));
+        assert_eq!(excursions[0].duration_in_secs, 10 * 60);
+        assert_eq!(excursions[0].peak_value, 10.0);
+        assert_eq!(excursions[0].area_under_threshold, 15.0);
+        assert!(!excursions[0].ongoing);
+        // Still going on with the last reading, which stands for one reporting interval
+        assert_eq!(excursions[1].end, registered_at + Duration::minutes(25));
+        assert!(excursions[1].ongoing);
+
+        let (_, allowed_range, excursions) = QueryHandler::new(
+            query(Some(AllowedTemperatureRange { min: 0.0, max: 9.0 })),
+            MockDb,
+        )
+        .handle()
+        .await
+        .unwrap();
+        assert_eq!(allowed_range.max, 9.0);
+        assert_eq!(excursions.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_exclude_flagged_readings_from_group_average() {
+        //GIVEN
+        group_creating_helper("AN1").await;
+        device_create_helper("AN1", "AN18302DDA").await;
+        device_create_helper("AN1", "AN18302DDB").await;
+        save_temperatures_helper("AN18302DDA", &"0005".repeat(14)).await;
+        // glitches to 40 once
+        save_temperatures_helper("AN18302DDB", &format!("{}00280005", "0005".repeat(12))).await;
+        let query = |exclude_flagged| GetDeviceGroupAverageTemperatureDuringPeriodQuery {
+            device_group_serial: "AN1".to_string(),
+            channel: MeasurementChannel::Temperature,
+            start_date: Utc::now() - Duration::minutes(300),
+            end_date: Utc::now() + Duration::minutes(300),
+            include_decommissioned: false,
+            tags: vec![],
+            metric: TemperatureMetric::Mean,
+            weighting: ReadingWeighting::PerReading,
+            exclude_flagged,
+        };
+
+        //WHEN
+        let averages = QueryHandler::new(query(true), MockDb)
+            .handle()
+            .await
+            .unwrap()
+            .0;
+
+        //THEN
+        assert!(averages.iter().all(|(_, average, _)| *average == 5.0));
+
+        let averages = QueryHandler::new(query(false), MockDb)
+            .handle()
+            .await
+            .unwrap()
+            .0;
+        let (_, glitched, _) = averages
+            .iter()
+            .find(|(device, _, _)| device.serial_number == "AN18302DDB")
+            .unwrap();
+        assert_eq!(*glitched, 5.0 + 35.0 / 14.0);
+
+        let query = GetDeviceFlaggedReadingsDuringPeriodQuery {
+            serial_number: "AN18302DDB".to_string(),
+            start_date: Utc::now() - Duration::minutes(300),
+            end_date: Utc::now() + Duration::minutes(300),
+        };
+        let (_, flagged) = QueryHandler::new(query, MockDb).handle().await.unwrap();
+        assert_eq!(flagged.len(), 1);
+        assert_eq!(flagged[0].value, 40.0);
+    }
+
+    #[tokio::test]
+    async fn test_get_device_group_diagnostics() {
+        //GIVEN
+        group_creating_helper("FD1").await;
+        device_create_helper("FD1", "FD18302DDA").await;
+        device_create_helper("FD1", "FD18302DDB").await;
+        // disconnected once
+        save_temperatures_helper("FD18302DDA", "000500057FFF0005").await;
+        save_temperatures_helper("FD18302DDB", "0005000500050005").await;
+        let query = |min_faulty_ratio| GetDeviceGroupDiagnosticsQuery {
+            device_group_serial: "FD1".to_string(),
+            channel: MeasurementChannel::Temperature,
+            start_date: Utc::now() - Duration::minutes(300),
+            end_date: Utc::now() + Duration::minutes(300),
+            min_faulty_ratio,
+        };
+
+        //WHEN
+        let diagnostics = QueryHandler::new(query(0.1), MockDb)
+            .handle()
+            .await
+            .unwrap();
+
+        //THEN
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].0.serial_number, "FD18302DDA");
+        assert_eq!(diagnostics[0].1.faulty_ratio, 0.25);
+
+        let diagnostics = QueryHandler::new(query(0.0), MockDb)
+            .handle()
+            .await
+            .unwrap();
+        assert_eq!(
+            diagnostics
+                .iter()
+                .map(|(device, _)| device.serial_number.as_str())
+                .collect::<Vec<_>>(),
+            vec!["FD18302DDA", "FD18302DDB"]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_suspended_device_rejects_temperatures() {
+        //GIVEN
+        group_creating_helper("L1").await;
+        device_create_helper("L1", "L18302DDK").await;
+        let db = MockDb;
+
+        //WHEN
+        let cmd = SuspendDevice {
+            serial_number: "L18302DDK".to_string(),
+        };
+        CommandHandler::new(cmd, db.clone()).handle().await.unwrap();
+
+        let cmd = SaveDeviceTemperature {
+            serial_number: "L18302DDK".to_string(),
+            interval: 300,
+            temperatures: "FFFE".to_string(),
+            registered_at: Utc::now(),
+            channels: default_channels(),
+            idempotency_key: None,
+        };
+        let res = CommandHandler::new(cmd, db.clone()).handle().await;
+
+        //THEN
+        assert!(matches!(res.err().unwrap(), Error::DeviceNotActive));
+    }
+
+    #[tokio::test]
+    async fn test_get_device_group_average_temperature_with_decommissioned_device() {
+        //GIVEN
+        group_creating_helper("L2").await;
+        device_create_helper("L2", "L28302DDK").await;
+        device_create_helper("L2", "L38302DDK").await;
+        save_temperatures_helper("L28302DDK", "FFFE").await;
+        save_temperatures_helper("L38302DDK", "0001").await;
+        let cmd = DecommissionDevice {
+            serial_number: "L38302DDK".to_string(),
+        };
+        CommandHandler::new(cmd, MockDb).handle().await.unwrap();
+
+        let query = |include_decommissioned| GetDeviceGroupAverageTemperatureDuringPeriodQuery {
+            device_group_serial: "L2".to_string(),
+            channel: MeasurementChannel::Temperature,
+            start_date: Utc::now() - Duration::minutes(300),
+            end_date: Utc::now() + Duration::minutes(300),
+            include_decommissioned,
+            tags: vec![],
+            metric: TemperatureMetric::Mean,
+            weighting: ReadingWeighting::PerReading,
+            exclude_flagged: false,
+        };
+
+        //WHEN
+        let excluded = QueryHandler::new(query(false), MockDb)
+            .handle()
+            .await
+            .unwrap()
+            .0;
+        let included = QueryHandler::new(query(true), MockDb)
+            .handle()
+            .await
+            .unwrap()
+            .0;
+
+        //THEN
+        assert_eq!(excluded.len(), 1);
+        assert_eq!(excluded[0].0.serial_number, "L28302DDK");
+        assert_eq!(included.len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_transfer_device() {
+        //GIVEN
+        group_creating_helper("T1").await;
+        group_creating_helper("T2").await;
+        device_create_helper("T1", "T18302DDK").await;
+        device_create_helper("T2", "T28302DDK").await;
+        save_temperatures_helper("T18302DDK", "FFFE").await;
+        save_temperatures_helper("T28302DDK", "0003").await;
+
+        //WHEN
+        let cmd = TransferDevice {
+            serial_number: "T18302DDK".to_string(),
+            device_group_serial: "T2".to_string(),
+        };
+        let (device, group) = CommandHandler::new(cmd, MockDb).handle().await.unwrap();
+        let cmd = SaveDeviceTemperature {
+            serial_number: "T18302DDK".to_string(),
+            interval: 300,
+            temperatures: "0005".to_string(),
+            registered_at: Utc::now() + Duration::seconds(1),
+            channels: default_channels(),
+            idempotency_key: None,
+        };
+        CommandHandler::new(cmd, MockDb).handle().await.unwrap();
+
+        let query = |device_group_serial: &str| GetDeviceGroupAverageTemperatureDuringPeriodQuery {
+            device_group_serial: device_group_serial.to_string(),
+            channel: MeasurementChannel::Temperature,
+            start_date: Utc::now() - Duration::minutes(300),
+            end_date: Utc::now() + Duration::minutes(300),
+            include_decommissioned: false,
+            tags: vec![],
+            metric: TemperatureMetric::Mean,
+            weighting: ReadingWeighting::PerReading,
+            exclude_flagged: false,
+        };
+        let previous_group = QueryHandler::new(query("T1"), MockDb)
+            .handle()
+            .await
+            .unwrap()
+            .0;
+        let current_group = QueryHandler::new(query("T2"), MockDb)
+            .handle()
+            .await
+            .unwrap()
+            .0;
+
+        //THEN
+        assert_eq!(device.device_group_serial_number, "T2");
+        assert_eq!(group.serial_number, "T2");
+        // readings are only counted for the group the device was in at the time
+        assert_eq!(previous_group.len(), 1);
+        assert_eq!(previous_group[0].1, -2.0);
+        assert_eq!(current_group.len(), 2);
+        assert_eq!(current_group[0].0.serial_number, "T18302DDK");
+        assert_eq!(current_group[0].1, 5.0);
+        assert_eq!(current_group[1].1, 3.0);
+    }
+
+    #[tokio::test]
+    async fn test_transfer_device_to_unknown_group() {
+        //GIVEN
+        group_creating_helper("T3").await;
+        device_create_helper("T3", "T38302DDK").await;
+
+        //WHEN
+        let cmd = TransferDevice {
+            serial_number: "T38302DDK".to_string(),
+            device_group_serial: "T404".to_string(),
+        };
+        let res = CommandHandler::new(cmd, MockDb).handle().await;
+
+        //THEN
+        assert!(matches!(res.err().unwrap(), Error::NotFound));
+    }
+
+    #[tokio::test]
+    async fn test_transfer_suspended_device() {
+        use crate::domain::device::repository::TDeviceQuery;
+        //GIVEN
+        group_creating_helper("T4").await;
+        group_creating_helper("T5").await;
+        device_create_helper("T4", "T48302DDK").await;
+        let cmd = SuspendDevice {
+            serial_number: "T48302DDK".to_string(),
+        };
+        CommandHandler::new(cmd, MockDb).handle().await.unwrap();
+
+        //WHEN
+        let cmd = TransferDevice {
+            serial_number: "T48302DDK".to_string(),
+            device_group_serial: "T5".to_string(),
+        };
+        let res = CommandHandler::new(cmd, MockDb).handle().await;
+
+        //THEN
+        assert!(matches!(res.err().unwrap(), Error::DeviceNotActive));
+        let device = TDeviceQuery::get(&MockDb, "T48302DDK").await.unwrap();
+        assert_eq!(device.device_group_serial_number, "T4");
+    }
+
+    #[tokio::test]
+    async fn test_filter_devices_by_tags() {
+        //GIVEN
+        group_creating_helper("M1").await;
+        let cmd = RegisterDevice {
+            serial_number: "M18302DDK".to_string(),
+            device_group_serial: "M1".to_string(),
+            tags: ["reefer".to_string()].into(),
+            ..Default::default()
+        };
+        CommandHandler::new(cmd, MockDb).handle().await.unwrap();
+        device_create_helper("M1", "M28302DDK").await;
+        save_temperatures_helper("M18302DDK", "0003").await;
+        save_temperatures_helper("M28302DDK", "FFFE").await;
+
+        //WHEN
+        let cmd = UpdateDeviceMetadata {
+            serial_number: "M28302DDK".to_string(),
+            metadata: [("location".to_string(), Some("Busan".to_string()))].into(),
+            add_tags: ["reefer".to_string(), "pharma".to_string()].into(),
+            ..Default::default()
+        };
+        let updated = CommandHandler::new(cmd, MockDb).handle().await.unwrap();
+
+        let query = ListDevicesQuery {
+            device_group_serial: "M1".to_string(),
+            tags: vec!["pharma".to_string()],
+        };
+        let listed = QueryHandler::new(query, MockDb).handle().await.unwrap();
+
+        let query = GetDeviceGroupAverageTemperatureDuringPeriodQuery {
+            device_group_serial: "M1".to_string(),
+            channel: MeasurementChannel::Temperature,
+            start_date: Utc::now() - Duration::minutes(300),
+            end_date: Utc::now() + Duration::minutes(300),
+            include_decommissioned: false,
+            tags: vec!["reefer".to_string()],
+            metric: TemperatureMetric::Mean,
+            weighting: ReadingWeighting::PerReading,
+            exclude_flagged: false,
+        };
+        let averages = QueryHandler::new(query, MockDb).handle().await.unwrap().0;
+
+        //THEN
+        assert_eq!(updated.metadata.get("location").unwrap(), "Busan");
+        assert_eq!(listed.len(), 1);
+        assert_eq!(listed[0].serial_number, "M28302DDK");
+        assert_eq!(averages.len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_calibrate_device() {
+        use crate::domain::device::repository::TDeviceQuery;
+        //GIVEN
+        group_creating_helper("C1").await;
+        device_create_helper("C1", "C18302DDK").await;
+
+        //WHEN
+        let cmd = CalibrateDevice {
+            serial_number: "C18302DDK".to_string(),
+            offset: 0.5,
+            multiplier: 1.0,
+            valid_from: Utc::now() - Duration::days(1),
+        };
+        CommandHandler::new(cmd, MockDb).handle().await.unwrap();
+        save_temperatures_helper("C18302DDK", "0003").await;
+
+        //THEN
+        let aggregate = MockDb.get("C18302DDK").await.unwrap();
+        assert_eq!(aggregate.calibrations.len(), 1);
+        assert_eq!(aggregate.temperatures[0].raw_value, 3.0);
+        assert_eq!(aggregate.temperatures[0].value, 3.5);
+        assert_eq!(aggregate.temperatures[0].calibration_version, Some(1));
+    }
+
+    #[tokio::test]
+    async fn test_save_temperatures_with_model_codec() {
+        use crate::domain::device::repository::TDeviceQuery;
+        //GIVEN
+        group_creating_helper("X1").await;
+        let cmd = SetDeviceModelCodec {
+            model: "LE-TENTHS-100".to_string(),
+            codec: PayloadCodec {
+                endianness: Endianness::Little,
+                scale_factor: 0.1,
+                ..Default::default()
+            },
+        };
+        CommandHandler::new(cmd, MockDb).handle().await.unwrap();
+        let cmd = RegisterDevice {
+            serial_number: "X18302DDK".to_string(),
+            device_group_serial: "X1".to_string(),
+            model: Some("LE-TENTHS-100".to_string()),
+            ..Default::default()
+        };
+        CommandHandler::new(cmd, MockDb).handle().await.unwrap();
+
+        //WHEN
+        save_temperatures_helper("X18302DDK", "4100ECFF").await;
+
+        //THEN
+        let aggregate = MockDb.get("X18302DDK").await.unwrap();
+        assert_eq!(
+            aggregate
+                .temperatures
+                .iter()
+                .map(|t| t.value)
+                .collect::<Vec<_>>(),
+            vec![6.5, -2.0]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_import_device_readings() {
+        use crate::domain::device::repository::TDeviceQuery;
+        //GIVEN
+        group_creating_helper("I1").await;
+        device_create_helper("I1", "I18302DDK").await;
+        device_create_helper("I1", "I28302DDK").await;
+        let cmd = SuspendDevice {
+            serial_number: "I28302DDK".to_string(),
+        };
+        CommandHandler::new(cmd, MockDb).handle().await.unwrap();
+        let checked_at = Utc::now() - Duration::days(30);
+        let reading = |line, serial_number: &str, minutes| ImportedReading {
+            line,
+            serial_number: serial_number.to_string(),
+            channel: MeasurementChannel::Temperature,
+            checked_at: checked_at + Duration::minutes(minutes),
+            value: 4.0,
+        };
+        let cmd = ImportDeviceReadings {
+            readings: vec![
+                reading(2, "I18302DDK", 0),
+                reading(3, "I28302DDK", 0),
+                reading(4, "I18302DDK", 5),
+                reading(6, "UNKNOWN", 0),
+            ],
+            rejected_rows: vec![ImportRowError {
+                line: 5,
+                error: Error::SchemaError,
+            }],
+        };
+
+        //WHEN
+        let res = CommandHandler::new(cmd, MockDb).handle().await.unwrap();
+
+        //THEN
+        let Response::ReadingsImported {
+            imported,
+            skipped,
+            errors,
+        } = res
+        else {
+            panic!("unexpected response {:?}", res)
+        };
+        assert_eq!((imported, skipped), (2, 0));
+        assert_eq!(
+            errors
+                .iter()
+                .map(|error| (error.line, format!("{:?}", error.error)))
+                .collect::<Vec<_>>(),
+            vec![
+                (3, "DeviceNotActive".to_string()),
+                (5, "SchemaError".to_string()),
+                (6, "NotFound".to_string()),
+            ]
+        );
+        assert_eq!(MockDb.get("I18302DDK").await.unwrap().temperatures.len(), 2);
+        assert!(MockDb
+            .get("I28302DDK")
+            .await
+            .unwrap()
+            .temperatures
+            .is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_detect_offline_devices() {
+        //GIVEN
+        group_creating_helper("O1").await;
+        for (serial_number, interval) in [("O18302DDK", 60), ("O28302DDK", 300)] {
+            device_create_helper("O1", serial_number).await;
+            let cmd = SaveDeviceTemperature {
+                serial_number: serial_number.to_string(),
+                interval,
+                temperatures: "0001".to_string(),
+                registered_at: Utc::now(),
+                channels: default_channels(),
+                idempotency_key: None,
+            };
+            CommandHandler::new(cmd, MockDb).handle().await.unwrap();
+        }
+        device_create_helper("O1", "O38302DDK").await;
+        let cmd = SuspendDevice {
+            serial_number: "O38302DDK".to_string(),
+        };
+        CommandHandler::new(cmd, MockDb).handle().await.unwrap();
+
+        //WHEN
+        // long enough for 3 intervals of a minute but not of 5 minutes
+        let cmd = DetectOfflineDevices {
+            now: Utc::now() + Duration::minutes(10),
+            interval_multiple: 3,
+        };
+        let offline = CommandHandler::new(cmd, MockDb).handle().await.unwrap();
+
+        //THEN
+        assert_eq!(
+            offline
+                .iter()
+                .map(|device| device.serial_number.as_str())
+                .collect::<Vec<_>>(),
+            vec!["O18302DDK"]
+        );
+        let groups = QueryHandler::new(ListDeviceGroupsQuery, MockDb)
+            .handle()
+            .await
+            .unwrap();
+        let (_, counts) = groups
+            .iter()
+            .find(|(group, _)| group.serial_number == "O1")
+            .unwrap();
+        assert_eq!(
+            *counts,
+            DeviceCounts {
+                total: 3,
+                online: 1,
+                offline: 1
+            }
+        );
+    }
+
+    #[tokio::test]
+    async fn test_alerts_raised_and_resolved_on_saving_temperatures() {
+        //GIVEN
+        group_creating_helper("AL1").await;
+        device_create_helper("AL1", "AL18302DDK").await;
+        for (condition, threshold, duration_in_secs) in [
+            (ThresholdCondition::Above, 8.0, 900),
+            (ThresholdCondition::Below, 2.0, 0),
+        ] {
+            let cmd = AddAlertRule {
+                device_group_serial: "AL1".to_string(),
+                channel: MeasurementChannel::Temperature,
+                condition,
+                threshold,
+                duration_in_secs,
+            };
+            CommandHandler::new(cmd, MockDb).handle().await.unwrap();
+        }
+        let registered_at = Utc::now() - Duration::hours(1);
+        let save = |temperatures: &str, minutes| SaveDeviceTemperature {
+            serial_number: "AL18302DDK".to_string(),
+            interval: 300,
+            temperatures: temperatures.to_string(),
+            registered_at: registered_at + Duration::minutes(minutes),
+            channels: default_channels(),
+            idempotency_key: None,
+        };
+        let alerts = || async {
+            QueryHandler::new(
+                ListAlertsQuery {
+                    device_group_serial: None,
+                    serial_number: Some("AL18302DDK".to_string()),
+                    status: None,
+                },
+                MockDb,
+            )
+            .handle()
+            .await
+            .unwrap()
+        };
+
+        //WHEN
+        // above 8 from 5 to 25 minutes, then once below 2 at 30 minutes
+        CommandHandler::new(save("0005000900090009", 0), MockDb)
+            .handle()
+            .await
+            .unwrap();
+        let breaching_for_10_minutes = alerts().await;
+        CommandHandler::new(save("000A", 20), MockDb)
+            .handle()
+            .await
+            .unwrap();
+        let breaching_for_15_minutes = alerts().await;
+        CommandHandler::new(save("000A0001", 25), MockDb)
+            .handle()
+            .await
+            .unwrap();
+        let after_back_within_threshold = alerts().await;
+
+        //THEN
+        assert!(breaching_for_10_minutes.is_empty());
+        // The duration must be exceeded, not just reached
+        assert!(breaching_for_15_minutes.is_empty());
+        assert_eq!(after_back_within_threshold.len(), 2);
+        let below = &after_back_within_threshold[0];
+        assert_eq!(below.rule.condition, ThresholdCondition::Below);
+        assert_eq!(below.status, AlertStatus::Open);
+        assert_eq!(below.peak_value, 1.0);
+        let above = &after_back_within_threshold[1];
+        assert_eq!(above.rule.rule_id, 1);
+        assert_eq!(above.status, AlertStatus::Resolved);
+        assert_eq!(above.triggered_at, registered_at + Duration::minutes(5));
+        assert_eq!(
+            above.last_breached_at,
+            registered_at + Duration::minutes(25)
+        );
+        assert_eq!(above.peak_value, 10.0);
+        assert_eq!(
+            above.resolved_at,
+            Some(registered_at + Duration::minutes(30))
+        );
+    }
+
+    #[tokio::test]
+    async fn test_incident_workflow_on_out_of_range_batch() {
+        //GIVEN
+        group_creating_helper("IN1").await;
+        device_create_helper("IN1", "IN18302DDK").await;
+        let cmd = SetAllowedTemperatureRange {
+            device_group_serial: "IN1".to_string(),
+            min: 2.0,
+            max: 8.0,
+        };
+        CommandHandler::new(cmd, MockDb).handle().await.unwrap();
+        let incidents = || async {
+            QueryHandler::new(
+                ListIncidentsQuery {
+                    device_group_serial: Some("IN1".to_string()),
+                    serial_number: None,
+                    status: None,
+                },
+                MockDb,
+            )
+            .handle()
+            .await
+            .unwrap()
+        };
+
+        //WHEN
+        save_temperatures_helper("IN18302DDK", "00040005").await;
+        let within_range = incidents().await;
+        save_temperatures_helper("IN18302DDK", "0004000500090001").await;
+        let opened = incidents().await;
+        let incident_id = opened[0].incident_id;
+        let cmd = AssignIncident {
+            incident_id,
+            actor: "kim".to_string(),
+            assignee: "lee".to_string(),
+        };
+        CommandHandler::new(cmd, MockDb).handle().await.unwrap();
+        let cmd = AnnotateIncident {
+            incident_id,
+            actor: "lee".to_string(),
+            note: "door left open".to_string(),
+        };
+        CommandHandler::new(cmd, MockDb).handle().await.unwrap();
+        let cmd = SilenceIncidents {
+            device_group_serial: "IN1".to_string(),
+            serial_number: None,
+            until: Utc::now() + Duration::hours(1),
+            actor: "lee".to_string(),
+        };
+        CommandHandler::new(cmd, MockDb).handle().await.unwrap();
+        save_temperatures_helper("IN18302DDK", "00040004000A").await;
+        let cmd = AcknowledgeIncident {
+            incident_id,
+            actor: "lee".to_string(),
+        };
+        let acknowledged = CommandHandler::new(cmd, MockDb).handle().await.unwrap();
+
+        //THEN
+        assert!(within_range.is_empty());
+        assert_eq!(opened.len(), 1);
+        assert_eq!(opened[0].status, IncidentStatus::Open);
+        assert_eq!(opened[0].out_of_range_count, 2);
+        assert_eq!(acknowledged.status, IncidentStatus::Acknowledged);
+        assert_eq!(acknowledged.assignee.as_deref(), Some("lee"));
+        assert_eq!(acknowledged.timeline.len(), 5);
+        let latest = &incidents().await[0];
+        assert_ne!(latest.incident_id, incident_id);
+        assert_eq!(latest.status, IncidentStatus::Silenced);
+        assert_eq!(latest.out_of_range_count, 1);
+    }
+
+    #[tokio::test]
+    async fn test_events_published_for_notification() {
+        use crate::adapters::database::mock_db::notification_outbox_table;
+        use crate::domain::notification::DeviceEvent;
+        //GIVEN
+        group_creating_helper("N1").await;
+        device_create_helper("N1", "N18302DDK").await;
+
+        //WHEN
+        save_temperatures_helper("N18302DDK", "000100020003").await;
+
+        //THEN
+        let events = notification_outbox_table()
+            .read()
+            .await
+            .iter()
+            .map(|notification| notification.event.clone())
+            .filter(|event| match event {
+                DeviceEvent::GroupRegistered {
+                    device_group_serial,
+                }
+                | DeviceEvent::DeviceRegistered {
+                    device_group_serial,
+                    ..
+                }
+                | DeviceEvent::TemperaturesSaved {
+                    device_group_serial,
+                    ..
+                } => device_group_serial == "N1",
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            events,
+            vec![
+                DeviceEvent::GroupRegistered {
+                    device_group_serial: "N1".to_string(),
+                },
+                DeviceEvent::DeviceRegistered {
+                    serial_number: "N18302DDK".to_string(),
+                    device_group_serial: "N1".to_string(),
+                },
+                DeviceEvent::TemperaturesSaved {
+                    serial_number: "N18302DDK".to_string(),
+                    device_group_serial: "N1".to_string(),
+                    saved: 3,
+                    skipped: 0,
+                },
+            ]
+        );
     }
 }
@@ -1,23 +1,96 @@
 use crate::domain::{
+    device::{
+        repository::{TDeviceGroupQuery, TDeviceQuery},
+        DeviceCounts,
+    },
     device_group::{
-        commands::RegisterDeviceGroup, repository::TDeviceGroupPersist, DeviceGroupAggregate,
+        commands::{
+            AddAlertRule, RegisterDeviceGroup, RemoveAlertRule, SetAllowedTemperatureRange,
+        },
+        query::ListDeviceGroupsQuery,
+        repository::TDeviceGroupPersist,
+        DeviceGroupAggregate,
     },
+    notification::{repository::TNotificationOutbox, DeviceEvent},
     response::Error,
 };
 
-use super::CommandHandler;
+use super::{CommandHandler, QueryHandler};
 
 impl<R> CommandHandler<RegisterDeviceGroup, R>
 where
-    R: TDeviceGroupPersist,
+    R: TDeviceGroupPersist + TNotificationOutbox,
 {
     pub async fn handle(self) -> Result<DeviceGroupAggregate, Error> {
         let mut aggregate = DeviceGroupAggregate::new(self.command);
         self.repo.add(&mut aggregate).await?;
+        self.repo
+            .publish(DeviceEvent::GroupRegistered {
+                device_group_serial: aggregate.serial_number.clone(),
+            })
+            .await?;
+        Ok(aggregate)
+    }
+}
+
+impl<R> CommandHandler<AddAlertRule, R>
+where
+    R: TDeviceGroupPersist + TDeviceGroupQuery,
+{
+    pub async fn handle(self) -> Result<DeviceGroupAggregate, Error> {
+        let mut aggregate = self.repo.get(&self.command.device_group_serial).await?;
+        aggregate.add_alert_rule(self.command)?;
+        self.repo.update(&mut aggregate).await?;
+        Ok(aggregate)
+    }
+}
+
+impl<R> CommandHandler<RemoveAlertRule, R>
+where
+    R: TDeviceGroupPersist + TDeviceGroupQuery,
+{
+    // Alerts already raised for the rule are kept
+    pub async fn handle(self) -> Result<DeviceGroupAggregate, Error> {
+        let mut aggregate = self.repo.get(&self.command.device_group_serial).await?;
+        aggregate.remove_alert_rule(self.command.rule_id)?;
+        self.repo.update(&mut aggregate).await?;
+        Ok(aggregate)
+    }
+}
+
+impl<R> CommandHandler<SetAllowedTemperatureRange, R>
+where
+    R: TDeviceGroupPersist + TDeviceGroupQuery,
+{
+    pub async fn handle(self) -> Result<DeviceGroupAggregate, Error> {
+        let mut aggregate = self.repo.get(&self.command.device_group_serial).await?;
+        aggregate.set_allowed_range(self.command)?;
+        self.repo.update(&mut aggregate).await?;
         Ok(aggregate)
     }
 }
 
+impl<R> QueryHandler<ListDeviceGroupsQuery, R>
+where
+    R: TDeviceGroupQuery + TDeviceQuery,
+{
+    // Counts the devices currently in each group
+    pub async fn handle(self) -> Result<Vec<(DeviceGroupAggregate, DeviceCounts)>, Error> {
+        let devices = TDeviceQuery::list(&self.repo).await?;
+        Ok(TDeviceGroupQuery::list(&self.repo)
+            .await?
+            .into_iter()
+            .map(|group| {
+                let counts = devices
+                    .iter()
+                    .filter(|device| device.device_group_serial_number == group.serial_number)
+                    .collect();
+                (group, counts)
+            })
+            .collect())
+    }
+}
+
 #[cfg(test)]
 pub mod test_device_handler {
     use chrono::{DateTime, Utc};
@@ -0,0 +1,123 @@
+use chrono::Utc;
+
+use crate::domain::{
+    device::repository::TDeviceGroupQuery,
+    device_group::{repository::TDeviceGroupPersist, DeviceGroupAggregate},
+    incident::{
+        commands::{AcknowledgeIncident, AnnotateIncident, AssignIncident, SilenceIncidents},
+        query::{GetIncidentQuery, ListIncidentsQuery},
+        repository::{TIncidentPersist, TIncidentQuery},
+        IncidentAggregate,
+    },
+    response::Error,
+};
+
+use super::{matches_filter, CommandHandler, QueryHandler};
+
+impl<R> CommandHandler<AcknowledgeIncident, R>
+where
+    R: TIncidentPersist + TIncidentQuery,
+{
+    pub async fn handle(self) -> Result<IncidentAggregate, Error> {
+        let mut aggregate = self.repo.get(self.command.incident_id).await?;
+        aggregate.refresh_status(Utc::now());
+        aggregate.acknowledge(self.command.actor)?;
+        self.repo.update(&mut aggregate).await?;
+        Ok(aggregate)
+    }
+}
+
+impl<R> CommandHandler<AssignIncident, R>
+where
+    R: TIncidentPersist + TIncidentQuery,
+{
+    pub async fn handle(self) -> Result<IncidentAggregate, Error> {
+        let mut aggregate = self.repo.get(self.command.incident_id).await?;
+        aggregate.refresh_status(Utc::now());
+        aggregate.assign(self.command.actor, self.command.assignee)?;
+        self.repo.update(&mut aggregate).await?;
+        Ok(aggregate)
+    }
+}
+
+impl<R> CommandHandler<AnnotateIncident, R>
+where
+    R: TIncidentPersist + TIncidentQuery,
+{
+    pub async fn handle(self) -> Result<IncidentAggregate, Error> {
+        let mut aggregate = self.repo.get(self.command.incident_id).await?;
+        aggregate.refresh_status(Utc::now());
+        aggregate.annotate(self.command.actor, self.command.note)?;
+        self.repo.update(&mut aggregate).await?;
+        Ok(aggregate)
+    }
+}
+
+impl<R> CommandHandler<SilenceIncidents, R>
+where
+    R: TDeviceGroupPersist + TDeviceGroupQuery + TIncidentPersist + TIncidentQuery,
+{
+    // Incidents of the device or group already open are silenced along with the ones to come
+    pub async fn handle(self) -> Result<DeviceGroupAggregate, Error> {
+        let mut group =
+            TDeviceGroupQuery::get(&self.repo, &self.command.device_group_serial).await?;
+        let silence = group.silence(
+            self.command.serial_number,
+            self.command.until,
+            self.command.actor,
+        )?;
+        TDeviceGroupPersist::update(&self.repo, &mut group).await?;
+
+        for mut incident in TIncidentQuery::list(&self.repo).await? {
+            incident.refresh_status(silence.created_at);
+            if incident.device_group_serial == group.serial_number
+                && silence.covers(&incident.serial_number, silence.created_at)
+                && incident.is_open()
+            {
+                incident.silence(Some(silence.actor.clone()), silence.until);
+                TIncidentPersist::update(&self.repo, &mut incident).await?;
+            }
+        }
+        Ok(group)
+    }
+}
+
+impl<R> QueryHandler<GetIncidentQuery, R>
+where
+    R: TIncidentQuery,
+{
+    pub async fn handle(self) -> Result<IncidentAggregate, Error> {
+        let mut aggregate = self.repo.get(self.query.incident_id).await?;
+        aggregate.refresh_status(Utc::now());
+        Ok(aggregate)
+    }
+}
+
+impl<R> QueryHandler<ListIncidentsQuery, R>
+where
+    R: TIncidentQuery,
+{
+    // Latest first
+    pub async fn handle(self) -> Result<Vec<IncidentAggregate>, Error> {
+        let now = Utc::now();
+        let mut incidents = self
+            .repo
+            .list()
+            .await?
+            .into_iter()
+            .map(|mut incident| {
+                incident.refresh_status(now);
+                incident
+            })
+            .filter(|incident| {
+                matches_filter(
+                    &self.query.device_group_serial,
+                    &incident.device_group_serial,
+                ) && matches_filter(&self.query.serial_number, &incident.serial_number)
+                    && matches_filter(&self.query.status, &incident.status)
+            })
+            .collect::<Vec<_>>();
+        incidents.sort_by_key(|incident| std::cmp::Reverse(incident.created_at));
+        Ok(incidents)
+    }
+}
@@ -1,5 +1,8 @@
+pub mod alert;
 pub mod device;
 pub mod device_group;
+pub mod incident;
+pub mod notification;
 
 pub struct CommandHandler<C, R> {
     pub(crate) command: C,
@@ -21,3 +24,8 @@ impl<Q, R> QueryHandler<Q, R> {
         Self { query, repo }
     }
 }
+
+// Filter of the list queries, which only applies when given
+pub(crate) fn matches_filter<T: PartialEq>(given: &Option<T>, value: &T) -> bool {
+    !matches!(given, Some(given) if given != value)
+}
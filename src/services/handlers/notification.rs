@@ -0,0 +1,18 @@
+use crate::domain::{
+    notification::{query::ListDeadLettersQuery, repository::TDeadLetterStore, DeadLetter},
+    response::Error,
+};
+
+use super::QueryHandler;
+
+impl<R> QueryHandler<ListDeadLettersQuery, R>
+where
+    R: TDeadLetterStore,
+{
+    // Latest first
+    pub async fn handle(self) -> Result<Vec<DeadLetter>, Error> {
+        let mut dead_letters = self.repo.list().await?;
+        dead_letters.reverse();
+        Ok(dead_letters)
+    }
+}
@@ -0,0 +1,467 @@
+pub mod signature;
+
+use std::{collections::BTreeMap, time::Duration};
+
+use chrono::{DateTime, Utc};
+use futures_util::future::join_all;
+
+use crate::domain::{
+    notification::{
+        repository::{TDeadLetterStore, TNotificationOutbox},
+        DeadLetter, Delivery,
+    },
+    response::Error,
+};
+
+use self::signature::sign;
+
+// Delivers a notification body to a webhook. Any error is worth retrying.
+pub trait TWebhookSender {
+    fn send(
+        &self,
+        url: &str,
+        headers: &[(&str, String)],
+        body: &str,
+    ) -> impl std::future::Future<Output = Result<(), String>> + Send;
+}
+
+#[derive(Clone, Debug)]
+pub struct WebhookConfig {
+    pub urls: Vec<String>,
+    // Signs the bodies so webhooks can tell they come from us
+    pub secret: String,
+    pub max_attempts: u32,
+    // Waited after the first failed attempt, then doubled after each one
+    pub base_backoff: Duration,
+}
+
+pub struct WebhookNotifier<R, S> {
+    repo: R,
+    sender: S,
+    config: WebhookConfig,
+}
+
+impl<R, S> WebhookNotifier<R, S> {
+    pub fn new(repo: R, sender: S, config: WebhookConfig) -> Self {
+        Self {
+            repo,
+            sender,
+            config,
+        }
+    }
+}
+
+impl<R, S> WebhookNotifier<R, S>
+where
+    R: TNotificationOutbox + TDeadLetterStore,
+    S: TWebhookSender,
+{
+    // Attempts the deliveries which are due once each. A failed one waits in the outbox for its
+    // backoff rather than holding up the others. Webhooks are posted to all at once, so a slow
+    // one doesn't hold up the others either, and each gets its notifications in order.
+    pub async fn dispatch(&self) -> Result<(), Error> {
+        self.repo.schedule_deliveries(&self.config.urls).await?;
+        let mut by_url: BTreeMap<String, Vec<Delivery>> = BTreeMap::new();
+        for delivery in self.repo.list_due_deliveries(Utc::now()).await? {
+            by_url
+                .entry(delivery.url.clone())
+                .or_default()
+                .push(delivery);
+        }
+
+        join_all(by_url.into_values().map(|deliveries| async move {
+            for delivery in deliveries {
+                self.attempt(delivery).await?;
+            }
+            Ok(())
+        }))
+        .await
+        .into_iter()
+        .collect()
+    }
+
+    // Posts the notification to the webhook, dead-lettering it once the attempts run out
+    pub async fn attempt(&self, mut delivery: Delivery) -> Result<(), Error> {
+        let body =
+            serde_json::to_string(&delivery.notification).map_err(|_| Error::ConversionFailed)?;
+        let headers = [
+            (
+                "X-Signature",
+                format!("sha256={}", sign(&self.config.secret, &body)),
+            ),
+            (
+                "X-Notification-Id",
+                delivery.notification.notification_id.to_string(),
+            ),
+        ];
+
+        let Err(last_error) = self.sender.send(&delivery.url, &headers, &body).await else {
+            return self.repo.remove_delivery(delivery.delivery_id).await;
+        };
+
+        delivery.attempts += 1;
+        if delivery.attempts >= self.config.max_attempts.max(1) {
+            let dead_letter = DeadLetter {
+                notification: delivery.notification,
+                url: delivery.url,
+                attempts: delivery.attempts,
+                last_error,
+                failed_at: Utc::now(),
+            };
+            self.repo.add(&dead_letter).await?;
+            return self.repo.remove_delivery(delivery.delivery_id).await;
+        }
+
+        // A backoff beyond any date puts the delivery off for good
+        delivery.next_attempt_at = self
+            .backoff(delivery.attempts)
+            .and_then(|backoff| Utc::now().checked_add_signed(backoff))
+            .unwrap_or(DateTime::<Utc>::MAX_UTC);
+        self.repo.reschedule_delivery(&delivery).await
+    }
+
+    // `None` when too long to represent
+    fn backoff(&self, attempt: u32) -> Option<chrono::Duration> {
+        let backoff = self
+            .config
+            .base_backoff
+            .saturating_mul(2u32.saturating_pow(attempt - 1));
+        chrono::Duration::from_std(backoff).ok()
+    }
+}
+
+#[cfg(test)]
+mod test_notifier {
+    use std::{
+        sync::{
+            atomic::{AtomicI64, Ordering},
+            Arc,
+        },
+        time::Duration,
+    };
+
+    use chrono::{DateTime, Utc};
+    use tokio::{
+        io::{AsyncReadExt, AsyncWriteExt},
+        net::TcpListener,
+        sync::Mutex,
+    };
+
+    use crate::{
+        adapters::webhook::HttpWebhookSender,
+        domain::{
+            notification::{
+                repository::{TDeadLetterStore, TNotificationOutbox},
+                DeadLetter, Delivery, DeviceEvent, Notification,
+            },
+            response::Error,
+        },
+    };
+
+    use super::{signature::sign, TWebhookSender, WebhookConfig, WebhookNotifier};
+
+    // Outbox of its own, so that the events other tests publish to `MockDb` are not posted here
+    #[derive(Default)]
+    struct OutboxStub {
+        published: Mutex<Vec<Notification>>,
+        deliveries: Mutex<Vec<Delivery>>,
+        dead_letters: Mutex<Vec<DeadLetter>>,
+        next_delivery_id: AtomicI64,
+    }
+
+    impl TNotificationOutbox for OutboxStub {
+        async fn publish(&self, event: DeviceEvent) -> Result<(), Error> {
+            self.published.lock().await.push(Notification::new(event));
+            Ok(())
+        }
+
+        async fn schedule_deliveries(&self, urls: &[String]) -> Result<(), Error> {
+            let mut deliveries = self.deliveries.lock().await;
+            for notification in std::mem::take(&mut *self.published.lock().await) {
+                for url in urls {
+                    deliveries.push(Delivery {
+                        delivery_id: self.next_delivery_id.fetch_add(1, Ordering::SeqCst),
+                        notification: notification.clone(),
+                        url: url.clone(),
+                        attempts: 0,
+                        next_attempt_at: Utc::now(),
+                    });
+                }
+            }
+            Ok(())
+        }
+
+        async fn list_due_deliveries(&self, now: DateTime<Utc>) -> Result<Vec<Delivery>, Error> {
+            Ok(self
+                .deliveries
+                .lock()
+                .await
+                .iter()
+                .filter(|delivery| delivery.next_attempt_at <= now)
+                .cloned()
+                .collect())
+        }
+
+        async fn reschedule_delivery(&self, delivery: &Delivery) -> Result<(), Error> {
+            for stored in self.deliveries.lock().await.iter_mut() {
+                if stored.delivery_id == delivery.delivery_id {
+                    *stored = delivery.clone();
+                }
+            }
+            Ok(())
+        }
+
+        async fn remove_delivery(&self, delivery_id: i64) -> Result<(), Error> {
+            self.deliveries
+                .lock()
+                .await
+                .retain(|delivery| delivery.delivery_id != delivery_id);
+            Ok(())
+        }
+    }
+
+    impl TDeadLetterStore for OutboxStub {
+        async fn add(&self, dead_letter: &DeadLetter) -> Result<(), Error> {
+            self.dead_letters.lock().await.push(dead_letter.clone());
+            Ok(())
+        }
+
+        async fn list(&self) -> Result<Vec<DeadLetter>, Error> {
+            Ok(self.dead_letters.lock().await.clone())
+        }
+    }
+
+    // Takes `delay` to accept what is posted to `slow_url`, and records the webhooks in the order
+    // they accepted
+    struct SlowSenderStub {
+        slow_url: String,
+        delay: Duration,
+        accepted: Mutex<Vec<String>>,
+    }
+
+    impl TWebhookSender for SlowSenderStub {
+        async fn send(
+            &self,
+            url: &str,
+            _headers: &[(&str, String)],
+            _body: &str,
+        ) -> Result<(), String> {
+            if url == self.slow_url {
+                tokio::time::sleep(self.delay).await;
+            }
+            self.accepted.lock().await.push(url.to_string());
+            Ok(())
+        }
+    }
+
+    // Answers with `statuses` in turn, then with 500, and records the requests it receives
+    async fn stub_server_helper(statuses: Vec<u16>) -> (String, Arc<Mutex<Vec<String>>>) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/hooks", listener.local_addr().unwrap());
+        let requests = Arc::new(Mutex::new(vec![]));
+
+        let received = requests.clone();
+        tokio::spawn(async move {
+            let mut statuses = statuses.into_iter();
+            loop {
+                let (mut stream, _) = listener.accept().await.unwrap();
+                let mut request = vec![];
+                let mut buffer = [0u8; 1024];
+                // Reads until the body is complete
+                while !is_complete(&request) {
+                    let read = stream.read(&mut buffer).await.unwrap();
+                    if read == 0 {
+                        break;
+                    }
+                    request.extend_from_slice(&buffer[..read]);
+                }
+                received
+                    .lock()
+                    .await
+                    .push(String::from_utf8(request).unwrap());
+
+                let status = statuses.next().unwrap_or(500);
+                let response = format!("HTTP/1.1 {} Stub\r\nContent-Length: 0\r\n\r\n", status);
+                stream.write_all(response.as_bytes()).await.unwrap();
+            }
+        });
+        (url, requests)
+    }
+
+    fn is_complete(request: &[u8]) -> bool {
+        let request = String::from_utf8_lossy(request);
+        let Some((head, body)) = request.split_once("\r\n\r\n") else {
+            return false;
+        };
+        let content_length = head
+            .to_ascii_lowercase()
+            .lines()
+            .find_map(|line| line.strip_prefix("content-length: ").map(str::to_string))
+            .and_then(|value| value.parse::<usize>().ok())
+            .unwrap_or(0);
+        body.len() >= content_length
+    }
+
+    fn config_helper(urls: &[&str]) -> WebhookConfig {
+        WebhookConfig {
+            urls: urls.iter().map(|url| url.to_string()).collect(),
+            secret: "secret".to_string(),
+            max_attempts: 3,
+            base_backoff: Duration::from_millis(1),
+        }
+    }
+
+    fn event_helper(serial_number: &str) -> DeviceEvent {
+        DeviceEvent::DeviceRegistered {
+            serial_number: serial_number.to_string(),
+            device_group_serial: "W1".to_string(),
+        }
+    }
+
+    // Dispatches until the outbox has nothing left, waiting out the backoffs in between
+    async fn dispatch_all_helper(notifier: &WebhookNotifier<OutboxStub, HttpWebhookSender>) {
+        loop {
+            notifier.dispatch().await.unwrap();
+            if notifier.repo.deliveries.lock().await.is_empty() {
+                return;
+            }
+            tokio::time::sleep(Duration::from_millis(5)).await;
+        }
+    }
+
+    #[tokio::test]
+    async fn test_dispatch_retries_until_accepted() {
+        //GIVEN
+        let (url, requests) = stub_server_helper(vec![500, 503, 204]).await;
+        let notifier = WebhookNotifier::new(
+            OutboxStub::default(),
+            HttpWebhookSender::default(),
+            config_helper(&[&url]),
+        );
+        notifier
+            .repo
+            .publish(event_helper("W18302DDA"))
+            .await
+            .unwrap();
+
+        //WHEN
+        dispatch_all_helper(&notifier).await;
+
+        //THEN
+        let requests = requests.lock().await;
+        assert_eq!(requests.len(), 3);
+
+        let (head, body) = requests[2].split_once("\r\n\r\n").unwrap();
+        assert!(head.starts_with("POST /hooks HTTP/1.1"));
+        // Header names come lowercased
+        assert!(head.contains(&format!("x-signature: sha256={}", sign("secret", body))));
+        assert!(body.contains(r#""type":"DeviceRegistered""#));
+        assert!(body.contains(r#""serialNumber":"W18302DDA""#));
+
+        assert!(notifier.repo.list().await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_dispatch_dead_letters_once_attempts_run_out() {
+        //GIVEN
+        let (url, requests) = stub_server_helper(vec![]).await;
+        let notifier = WebhookNotifier::new(
+            OutboxStub::default(),
+            HttpWebhookSender::default(),
+            config_helper(&[&url]),
+        );
+        notifier
+            .repo
+            .publish(event_helper("W18302DDB"))
+            .await
+            .unwrap();
+
+        //WHEN
+        dispatch_all_helper(&notifier).await;
+
+        //THEN
+        assert_eq!(requests.lock().await.len(), 3);
+
+        let dead_letters = notifier.repo.list().await.unwrap();
+        assert_eq!(dead_letters.len(), 1);
+        let dead_letter = &dead_letters[0];
+        assert_eq!(dead_letter.url, url);
+        assert_eq!(dead_letter.attempts, 3);
+        assert!(dead_letter.last_error.contains("500"));
+        assert!(matches!(
+            &dead_letter.notification.event,
+            DeviceEvent::DeviceRegistered { serial_number, .. } if serial_number == "W18302DDB"
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_dispatch_does_not_wait_for_failing_webhook() {
+        //GIVEN
+        let (failing_url, failing_requests) = stub_server_helper(vec![]).await;
+        let (url, requests) = stub_server_helper(vec![204, 204]).await;
+        let mut config = config_helper(&[&failing_url, &url]);
+        config.base_backoff = Duration::from_secs(3600);
+        let notifier =
+            WebhookNotifier::new(OutboxStub::default(), HttpWebhookSender::default(), config);
+        notifier
+            .repo
+            .publish(event_helper("W18302DDC"))
+            .await
+            .unwrap();
+
+        //WHEN
+        notifier.dispatch().await.unwrap();
+        notifier
+            .repo
+            .publish(event_helper("W18302DDD"))
+            .await
+            .unwrap();
+        notifier.dispatch().await.unwrap();
+
+        //THEN
+        // The failed delivery waits for its backoff in the outbox
+        assert_eq!(failing_requests.lock().await.len(), 2);
+        assert_eq!(requests.lock().await.len(), 2);
+
+        let deliveries = notifier.repo.deliveries.lock().await;
+        assert_eq!(deliveries.len(), 2);
+        assert!(deliveries.iter().all(|delivery| delivery.url == failing_url
+            && delivery.attempts == 1
+            && delivery.next_attempt_at > Utc::now()));
+        assert!(notifier.repo.list().await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_dispatch_posts_to_webhooks_concurrently() {
+        //GIVEN
+        let (slow_url, fast_url) = ("http://a.example/hooks", "http://b.example/hooks");
+        let sender = SlowSenderStub {
+            slow_url: slow_url.to_string(),
+            delay: Duration::from_millis(100),
+            accepted: Mutex::new(vec![]),
+        };
+        let notifier = WebhookNotifier::new(
+            OutboxStub::default(),
+            sender,
+            config_helper(&[slow_url, fast_url]),
+        );
+        for serial_number in ["W18302DDE", "W18302DDF"] {
+            notifier
+                .repo
+                .publish(event_helper(serial_number))
+                .await
+                .unwrap();
+        }
+
+        //WHEN
+        notifier.dispatch().await.unwrap();
+
+        //THEN
+        // The fast webhook got both without waiting for the slow one
+        assert_eq!(
+            *notifier.sender.accepted.lock().await,
+            vec![fast_url, fast_url, slow_url, slow_url]
+        );
+        assert!(notifier.repo.deliveries.lock().await.is_empty());
+    }
+}
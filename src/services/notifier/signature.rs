@@ -0,0 +1,36 @@
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+
+// Hex encoded HMAC-SHA256 of `message`
+pub fn sign(secret: &str, message: &str) -> String {
+    let mut mac =
+        Hmac::<Sha256>::new_from_slice(secret.as_bytes()).expect("HMAC accepts keys of any length");
+    mac.update(message.as_bytes());
+    mac.finalize()
+        .into_bytes()
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect()
+}
+
+#[cfg(test)]
+mod test_signature {
+    use super::sign;
+
+    // Test case 2 of RFC 4231
+    #[test]
+    fn test_sign() {
+        assert_eq!(
+            sign("Jefe", "what do ya want for nothing?"),
+            "5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843"
+        );
+        // Keys longer than a block are hashed first
+        assert_eq!(
+            sign(
+                &"k".repeat(100),
+                "Test Using Larger Than Block-Size Key - Hash Key First"
+            ),
+            "2bc51c04a41032b5f44910acdb471c67a7b1de64e91089e052761fe59a9dac4b"
+        );
+    }
+}